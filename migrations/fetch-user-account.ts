@@ -9,7 +9,7 @@ type AccountBalance = {
   token: string;
   balance: string;
   debt: string;
-  lastUpdate: string;
+  emissionsIndexCheckpoint: string;
   details: {
     bankAddress: string;
     tokenMint: string;
@@ -85,7 +85,7 @@ async function main() {
             console.log(`  ${balance.token}:`);
             console.log(`    Balance: ${balance.balance}`);
             console.log(`    Debt: ${balance.debt}`);
-            console.log(`    Last Update: ${balance.lastUpdate}`);
+            console.log(`    Emissions Index Checkpoint: ${balance.emissionsIndexCheckpoint}`);
           });
         }
 
@@ -132,6 +132,9 @@ export async function addBankWithConfig(bankKeyPair: Keypair, marginGroupKeyPair
       insuranceIrFee: bigNumberToWrappedI80F48(0),
       protocolFixedFeeApr: bigNumberToWrappedI80F48(0.01),
       protocolIrFee: bigNumberToWrappedI80F48(0.05),
+      originationFeeBps: 0,
+      originationFeeInsuranceShareBps: 0,
+      flashloanFeeBps: 0,
       startRateAtTarget: bigNumberToWrappedI80F48(0.04 / ONE_YEAR_IN_SECONDS),
       minRateAtTarget: bigNumberToWrappedI80F48(0.001 / ONE_YEAR_IN_SECONDS),
       adjustmentSpeed: bigNumberToWrappedI80F48(50 / ONE_YEAR_IN_SECONDS),
@@ -143,6 +146,11 @@ export async function addBankWithConfig(bankKeyPair: Keypair, marginGroupKeyPair
     riskTier: { collateral: {} },
     totalAssetValueInitLimit: new BN("18446744073709551615"), // u64::MAX
     oracleMaxAge: 60,
+    fixedPrice: bigNumberToWrappedI80F48(0),
+    oracleMaxSlotAge: 0,
+    oracleMinPrice: new BN(0),
+    oracleMaxPrice: new BN(0),
+    secondaryOracleKey: PublicKey.default,
   };
 
   await addBank(bankKeyPair, marginGroupKeyPair, admin, bankConfig, config.tokenMint, config.pythFeed);
@@ -194,6 +202,10 @@ export function createBankConfigOpt(config: Partial<BankConfigOptRaw>): BankConf
       : null,
     oracleMaxAge: config.oracleMaxAge ?? null,
     permissionlessBadDebtSettlement: config.permissionlessBadDebtSettlement ?? null,
+    fixedPrice: config.fixedPrice ?? null,
+    oracleMaxSlotAge: config.oracleMaxSlotAge ?? null,
+    oracleMinPrice: config.oracleMinPrice ?? null,
+    oracleMaxPrice: config.oracleMaxPrice ?? null,
   };
 }
 
@@ -450,7 +462,7 @@ async function getAdminTokenAccountForBank(bankPubkey: PublicKey): Promise<Publi
 
 export async function marginfiGroupConfigure(marginGroupKeyPair: Keypair, admin: Keypair, newAdmin: anchor.web3.PublicKey | null) {
   await lendingProgram.methods
-    .marginfiGroupConfigure({ admin: newAdmin })
+    .marginfiGroupConfigure({ admin: newAdmin, bankruptThreshold: null, feeDistribution: null, feeMarginfiAccount: null, flashloanCpiCallers: null, flashloanFeeExemptAuthorities: null })
     .accounts({ marginfiGroup: marginGroupKeyPair.publicKey, admin: admin.publicKey })
     .signers([admin])
     .rpc({ commitment: "confirmed" });
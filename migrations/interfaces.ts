@@ -13,6 +13,9 @@ export interface InterestRateConfigRaw {
   insuranceIrFee: WrappedI80F48 | null;
   protocolFixedFeeApr: WrappedI80F48 | null;
   protocolIrFee: WrappedI80F48 | null;
+  originationFeeBps: number | null;
+  originationFeeInsuranceShareBps: number | null;
+  flashloanFeeBps: number | null;
 
   // Curve Params
   startRateAtTarget: WrappedI80F48 | null;
@@ -37,10 +40,14 @@ export interface BankConfigOptRaw {
   operationalState: { paused: {} } | { operational: {} } | { reduceOnly: {} } | null;
 
   oracle: {
-    setup: { none: {} } | { pythLegacy: {} } | { switchboardV2: {} } | { pythPushOracle: {} } | { switchboardPull: {} };
+    setup: { none: {} } | { pythLegacy: {} } | { switchboardV2: {} } | { pythPushOracle: {} } | { switchboardPull: {} } | { fixed: {} } | { stakePoolPythPush: {} };
     keys: PublicKey[] | null;
   } | null;
 
   oracleMaxAge: number | null;
   permissionlessBadDebtSettlement: boolean | null;
+  fixedPrice: WrappedI80F48 | null;
+  oracleMaxSlotAge: number | null;
+  oracleMinPrice: BN | null;
+  oracleMaxPrice: BN | null;
 }
@@ -20,7 +20,7 @@ export function parseLendingAccountBalances(lendingAccount: any) {
       assetShares: balance.assetShares.value ? wrappedI80F48toBigNumber(balance.assetShares).toFixed() : "0",
       liabilityShares: balance.liabilityShares.value = wrappedI80F48toBigNumber(balance.liabilityShares).toFixed(),
       emissionsOutstanding: balance.emissionsOutstanding.value ? wrappedI80F48toBigNumber(balance.emissionsOutstanding).toFixed() : "0",
-      lastUpdate: balance.lastUpdate.toString(),
+      emissionsIndexCheckpoint: balance.emissionsIndexCheckpoint.value ? wrappedI80F48toBigNumber(balance.emissionsIndexCheckpoint).toFixed() : "0",
     };
   });
 }
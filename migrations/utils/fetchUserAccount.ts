@@ -276,7 +276,7 @@ async function processMarginfiAccounts(marginfiAccounts: any[], lendingProgram:
               liabilityShareValue,
               tokenBalance,
               tokenDebt,
-              lastUpdate: new Date(balance.lastUpdate * 1000).toISOString(),
+              emissionsIndexCheckpoint: interpretWrappedI80F48(balance.emissionsIndexCheckpoint, "balance"),
             };
           })
       );
@@ -298,7 +298,7 @@ async function processMarginfiAccounts(marginfiAccounts: any[], lendingProgram:
         token: balance.bankInfo.tokenSymbol,
         balance: balance.tokenBalance,
         debt: balance.tokenDebt,
-        lastUpdate: balance.lastUpdate,
+        emissionsIndexCheckpoint: balance.emissionsIndexCheckpoint,
         details: {
           bankAddress: balance.bankInfo.bankAddress,
           tokenMint: balance.bankInfo.tokenMint,
@@ -419,7 +419,7 @@ export async function fetchAccountBalances(accountAddress: string) {
             token: bankInfo.tokenSymbol,
             balance: tokenBalance,
             debt: tokenDebt,
-            lastUpdate: new Date(balance.lastUpdate * 1000).toISOString(),
+            emissionsIndexCheckpoint: interpretWrappedI80F48(balance.emissionsIndexCheckpoint, "balance"),
             details: {
               bankAddress: bankInfo.bankAddress,
               tokenMint: bankInfo.tokenMint,
@@ -837,6 +837,9 @@ impl<'state> MarginfiFuzzContext<'state> {
             vec![],
             vec![],
         ));
+        // Opt into sweeping the residual assets of the account
+        remaining_accounts
+            .extend(marginfi_account.get_residual_asset_bank_ais(&self.get_bank_map()));
         let res = marginfi::instructions::lending_pool_handle_bankruptcy(Context::new(
             &marginfi::ID,
             &mut marginfi::instructions::LendingPoolHandleBankruptcy {
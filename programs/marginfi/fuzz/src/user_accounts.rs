@@ -6,7 +6,7 @@ use anchor_lang::{
 };
 use fixed::types::I80F48;
 
-use marginfi::state::marginfi_account::MarginfiAccount;
+use marginfi::state::marginfi_account::{BalanceSide, MarginfiAccount};
 
 use crate::{arbitrary_helpers::BankIdx, bank_accounts::BankAccounts};
 
@@ -115,4 +115,22 @@ impl<'info> UserAccount<'info> {
 
         ais
     }
+
+    /// Banks of the asset balances, swept when handling a bankruptcy.
+    pub fn get_residual_asset_bank_ais(
+        &'info self,
+        bank_map: &HashMap<Pubkey, &BankAccounts<'info>>,
+    ) -> Vec<AccountInfo<'info>> {
+        let marginfi_account_al =
+            AccountLoader::<MarginfiAccount>::try_from(&self.margin_account).unwrap();
+        let marginfi_account = marginfi_account_al.load().unwrap();
+
+        marginfi_account
+            .lending_account
+            .balances
+            .iter()
+            .filter(|balance| balance.active && !balance.is_empty(BalanceSide::Assets))
+            .map(|balance| bank_map.get(&balance.bank_pk).unwrap().bank.clone())
+            .collect()
+    }
 }
@@ -12,9 +12,23 @@ pub const LIQUIDITY_VAULT_SEED: &str = "liquidity_vault";
 pub const INSURANCE_VAULT_SEED: &str = "insurance_vault";
 pub const FEE_VAULT_SEED: &str = "fee_vault";
 
+pub const GROUP_INSURANCE_VAULT_AUTHORITY_SEED: &str = "group_insurance_vault_auth";
+pub const GROUP_INSURANCE_VAULT_SEED: &str = "group_insurance_vault";
+
+pub const INSURANCE_SHARE_MINT_SEED: &str = "insurance_share_mint";
+pub const INSURANCE_UNSTAKE_TICKET_SEED: &str = "insurance_unstake_ticket";
+
 pub const EMISSIONS_AUTH_SEED: &str = "emissions_auth_seed";
 pub const EMISSIONS_TOKEN_ACCOUNT_SEED: &str = "emissions_token_account_seed";
 
+pub const EMISSIONS_CAMPAIGN_AUTH_SEED: &str = "emissions_campaign_auth_seed";
+pub const EMISSIONS_CAMPAIGN_VAULT_SEED: &str = "emissions_campaign_vault_seed";
+pub const EMISSIONS_CAMPAIGN_POSITION_SEED: &str = "emissions_campaign_position_seed";
+
+pub const MARGINFI_ACCOUNT_SEED: &str = "marginfi_account";
+
+pub const CONDITIONAL_ORDER_SEED: &str = "conditional_order";
+
 cfg_if::cfg_if! {
     if #[cfg(feature = "devnet")] {
         pub const PYTH_ID: Pubkey = pubkey!("gSbePebfvPy7tRqimPoVecS2UsBvYv46ynrzWocc92s");
@@ -34,6 +48,17 @@ cfg_if::cfg_if! {
     }
 }
 
+/// SPL stake pool program, used to read the exchange rate of stake pool LSTs (e.g. jitoSOL, bSOL)
+pub const SPL_STAKE_POOL_ID: Pubkey = pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+/// Number of epochs a stake pool can go without an update before its exchange rate is considered stale
+pub const MAX_STAKE_POOL_EPOCH_AGE: u64 = 1;
+
+/// Decimals of the bank oracle price band bounds (`oracle_min_price`, `oracle_max_price`)
+pub const ORACLE_PRICE_BAND_DECIMALS: u8 = 9;
+
+/// Maximum relative price difference between the legacy and the new oracle when migrating a bank oracle
+pub const ORACLE_MIGRATION_MAX_PRICE_DEVIATION: I80F48 = I80F48!(0.02);
+
 /// TODO: Make these variable per bank
 pub const LIQUIDATION_LIQUIDATOR_FEE: I80F48 = I80F48!(0.025);
 pub const LIQUIDATION_INSURANCE_FEE: I80F48 = I80F48!(0.025);
@@ -63,9 +88,35 @@ pub const EMPTY_BALANCE_THRESHOLD: I80F48 = I80F48!(1);
 /// Any account with assets below this threshold is considered bankrupt.
 /// The account also needs to have more liabilities than assets.
 ///
-/// This is USD denominated, so 0.001 = $0.1
+/// This is the USD value of the equity weighted assets, so 0.1 = $0.1
+///
+/// Default for groups that don't configure `MarginfiGroup::bankrupt_threshold`.
 pub const BANKRUPT_THRESHOLD: I80F48 = I80F48!(0.1);
 
+/// Utilization ratio an over-utilized bank is brought back to by auto deleveraging.
+///
+/// Auto deleveraging is only allowed once liabilities exceed assets, i.e. utilization is above 100%.
+pub const ADL_TARGET_UTILIZATION_RATIO: I80F48 = I80F48!(0.95);
+
+/// Share of the total liabilities of a bank a borrower must owe to be auto deleveraged at the start
+/// of a deleveraging round.
+pub const ADL_INITIAL_BORROWER_THRESHOLD_RATIO: I80F48 = I80F48!(0.1);
+
+/// Time in seconds after which the borrower threshold of a deleveraging round is halved.
+pub const ADL_THRESHOLD_HALVING_PERIOD: i64 = 300;
+
+/// Time in seconds after which a deleveraging round is considered stale and restarted.
+pub const ADL_ROUND_DURATION: i64 = 86_400;
+
+/// Virtual shares and tokens added to both sides of the insurance vault share price, so a
+/// donation to a (nearly) empty vault can't inflate the share price and round the shares of later
+/// stakers down to nothing.
+pub const INSURANCE_VIRTUAL_SHARES: u64 = 1_000_000;
+
+/// Max fee a conditional order can pay to the keeper executing it, in basis points of the
+/// repaid liability value
+pub const CONDITIONAL_ORDER_MAX_KEEPER_FEE_BPS: u16 = 500;
+
 /// Comparios threshold used to account for arithmetic artifacts on balances
 pub const ZERO_AMOUNT_THRESHOLD: I80F48 = I80F48!(0.0001);
 
@@ -73,11 +124,17 @@ pub const EMISSIONS_FLAG_BORROW_ACTIVE: u64 = 1 << 0;
 pub const EMISSIONS_FLAG_LENDING_ACTIVE: u64 = 1 << 1;
 pub const PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG: u64 = 1 << 2;
 
+/// Time in seconds after the end of an emissions campaign for users to claim their emissions,
+/// after which the campaign authority can reclaim what is left.
+pub const EMISSIONS_CAMPAIGN_RECLAIM_DELAY: i64 = 30 * 24 * 60 * 60;
+
 pub(crate) const EMISSION_FLAGS: u64 = EMISSIONS_FLAG_BORROW_ACTIVE | EMISSIONS_FLAG_LENDING_ACTIVE;
 pub(crate) const GROUP_FLAGS: u64 = PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG;
 
 /// Cutoff timestamp for balance last_update used in accounting collected emissions.
 /// Any balance updates before this timestamp are ignored, and current_timestamp is used instead.
+///
+/// Only used by the legacy per balance emissions, see `Bank::activate_emissions_index`.
 pub const MIN_EMISSIONS_START_TIME: u64 = 1681989983;
 
 pub const MAX_EXP_10_I80F48: usize = 24;
@@ -96,6 +96,63 @@ pub enum MarginfiError {
     IllegalAction,
     #[msg("Token22 Banks require mint account as first remaining account")] // 6047
     T22MintRequired,
+    #[msg("Oracle setup is deprecated")] // 6048
+    DeprecatedOracleSetup,
+    #[msg("Oracle prices deviate more than the allowed tolerance")] // 6049
+    OraclePriceMismatch,
+    #[msg("Oracle price is outside of the bank price band")] // 6050
+    OraclePriceOutOfBand,
+    #[msg("Not all bad debt of the bankrupt account was settled")] // 6051
+    BadDebtNotFullySettled,
+    #[msg("Group insurance fund is already initialized")] // 6052
+    GroupInsuranceFundAlreadyInitialized,
+    #[msg("Group insurance fund does not cover the bank mint")] // 6053
+    GroupInsuranceFundMintMismatch,
+    #[msg("Group insurance fund accounts missing or invalid")] // 6054
+    GroupInsuranceFundAccountRequired,
+    #[msg("Insurance staking is not initialized for this bank")] // 6055
+    InsuranceStakingNotInitialized,
+    #[msg("Insurance staking is already initialized for this bank")] // 6056
+    InsuranceStakingAlreadyInitialized,
+    #[msg("Insurance vault is depleted")] // 6057
+    InsuranceVaultDepleted,
+    #[msg("Insurance unstake cooldown has not ended")] // 6058
+    InsuranceUnstakeCooldownActive,
+    #[msg("Insurance withdrawal exceeds the protocol owned shares")] // 6059
+    InsuranceWithdrawExceedsProtocolShares,
+    #[msg("Insurance stake amount is too small to mint shares")] // 6060
+    InsuranceStakeTooSmall,
+    #[msg("Insurance share mint missing or invalid")] // 6061
+    InsuranceShareMintRequired,
+    #[msg("No bad debt recorded for this bank")] // 6062
+    BadDebtRecordNotFound,
+    #[msg("Bank utilization is not above 100%")] // 6063
+    BankNotOverUtilized,
+    #[msg("Invalid auto deleveraging")] // 6064
+    IllegalAutoDeleverage,
+    #[msg("Group fee distribution is not configured")] // 6065
+    FeeDistributionNotConfigured,
+    #[msg("Fee destination account missing or invalid")] // 6066
+    InvalidFeeDestination,
+    #[msg("Group fee marginfi account is not configured")] // 6067
+    FeeMarginfiAccountNotConfigured,
+    #[msg("Invalid emissions campaign")] // 6068
+    InvalidEmissionsCampaign,
+    #[msg("Invalid emissions schedule")] // 6069
+    InvalidEmissionsSchedule,
+    #[msg("Emissions have not ended")] // 6070
+    EmissionsNotEnded,
+    #[msg("Emissions claim accounts missing or invalid")] // 6071
+    InvalidEmissionsClaimAccounts,
+    #[msg("Emissions auto-compound is not enabled, or the emissions mint is not the bank mint")]
+    // 6072
+    EmissionsCompoundNotAllowed,
+    #[msg("Invalid account delegate permissions or expiry")] // 6073
+    InvalidDelegateConfig,
+    #[msg("Invalid conditional order")] // 6074
+    InvalidConditionalOrder,
+    #[msg("Conditional order trigger not met")] // 6075
+    ConditionalOrderNotTriggered,
 }
 
 impl From<MarginfiError> for ProgramError {
@@ -1,4 +1,7 @@
-use crate::{prelude::*, state::marginfi_group::BankConfigOpt};
+use crate::{
+    prelude::*,
+    state::marginfi_group::{BankConfigOpt, OracleConfig, MAX_FEE_DESTINATIONS},
+};
 use anchor_lang::prelude::*;
 
 // Event headers
@@ -9,7 +12,7 @@ pub struct GroupEventHeader {
     pub marginfi_group: Pubkey,
 }
 
-#[derive(AnchorSerialize, AnchorDeserialize)]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
 pub struct AccountEventHeader {
     pub signer: Option<Pubkey>,
     pub marginfi_account: Pubkey,
@@ -30,6 +33,21 @@ pub struct MarginfiGroupConfigureEvent {
     pub config: GroupConfig,
 }
 
+#[event]
+pub struct MarginfiGroupInitInsuranceFundEvent {
+    pub header: GroupEventHeader,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+}
+
+#[event]
+pub struct MarginfiGroupFundInsuranceEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
 #[event]
 pub struct LendingPoolBankCreateEvent {
     pub header: GroupEventHeader,
@@ -45,6 +63,16 @@ pub struct LendingPoolBankConfigureEvent {
     pub config: BankConfigOpt,
 }
 
+#[event]
+pub struct LendingPoolBankMigrateOracleEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub oracle: OracleConfig,
+    pub legacy_price: f64,
+    pub new_price: f64,
+}
+
 #[event]
 pub struct LendingPoolBankAccrueInterestEvent {
     pub header: GroupEventHeader,
@@ -66,6 +94,24 @@ pub struct LendingPoolBankCollectFeesEvent {
     pub insurance_fees_outstanding: f64,
 }
 
+#[event]
+pub struct LendingPoolBankCollectFeesAsDepositEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub fee_marginfi_account: Pubkey,
+    pub group_fees_deposited: f64,
+}
+
+#[event]
+pub struct LendingPoolBankDistributeFeesEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    /// Amount sent to each group fee destination, in `MarginfiGroup::fee_distribution` order
+    pub amounts: [u64; MAX_FEE_DESTINATIONS],
+}
+
 #[event]
 pub struct LendingPoolBankHandleBankruptcyEvent {
     pub header: AccountEventHeader,
@@ -73,16 +119,106 @@ pub struct LendingPoolBankHandleBankruptcyEvent {
     pub mint: Pubkey,
     pub bad_debt: f64,
     pub covered_amount: f64,
+    pub group_insurance_covered_amount: f64,
     pub socialized_amount: f64,
+    /// Bad debt of the account in this bank written off and not recovered, including this event
+    pub account_bad_debt: f64,
+    /// Bad debt written off by the bank over its lifetime, including this event
+    pub bank_bad_debt_written_off: f64,
+}
+
+#[event]
+pub struct LendingPoolBankInitInsuranceStakingEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub share_mint: Pubkey,
+    pub protocol_shares: u64,
+    pub unstake_cooldown: u64,
+}
+
+#[event]
+pub struct LendingPoolBankInsuranceStakeEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+}
+
+#[event]
+pub struct LendingPoolBankInsuranceRequestUnstakeEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub shares: u64,
+    pub unlock_timestamp: i64,
+}
+
+#[event]
+pub struct LendingPoolBankInsuranceClaimUnstakeEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub shares: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LendingPoolBankReclaimEmissionsEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub emissions_mint: Pubkey,
+    pub amount: u64,
+    /// Vault balance left for distributed emissions not yet withdrawn by users
+    pub outstanding_user_emissions: u64,
+}
+
+#[event]
+pub struct LendingPoolBankCreateEmissionsCampaignEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub campaign: Pubkey,
+    pub emissions_mint: Pubkey,
+    pub emissions_flag: u64,
+    pub emissions_rate: u64,
+    pub total_emissions: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct LendingPoolBankReclaimCampaignEmissionsEvent {
+    pub header: GroupEventHeader,
+    pub bank: Pubkey,
+    pub campaign: Pubkey,
+    pub emissions_mint: Pubkey,
+    pub amount: u64,
 }
 
 // marginfi account events
 
+#[event]
+pub struct LendingAccountSweepResidualAssetsEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub amount: f64,
+}
+
 #[event]
 pub struct MarginfiAccountCreateEvent {
     pub header: AccountEventHeader,
 }
 
+#[event]
+pub struct MarginfiAccountSetDelegateEvent {
+    pub header: AccountEventHeader,
+    pub delegate: Pubkey,
+    pub permissions: u64,
+    pub expiry: i64,
+}
+
 #[event]
 pub struct LendingAccountDepositEvent {
     pub header: AccountEventHeader,
@@ -106,6 +242,19 @@ pub struct LendingAccountBorrowEvent {
     pub bank: Pubkey,
     pub mint: Pubkey,
     pub amount: u64,
+    /// Origination fee added to the liability on top of `amount`
+    pub origination_fee: f64,
+}
+
+#[event]
+pub struct LendingAccountFlashloanFeeEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    /// Net liability increase of the balance over the flashloan
+    pub net_borrowed_amount: f64,
+    /// Flashloan fee added to the liability
+    pub flashloan_fee: f64,
 }
 
 #[event]
@@ -140,6 +289,85 @@ pub struct LendingAccountLiquidateEvent {
     pub post_balances: LiquidationBalances,
 }
 
+#[event]
+pub struct LendingAccountRecoverBadDebtEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub remaining_bad_debt: f64,
+}
+
+#[event]
+pub struct LendingAccountCompoundEmissionsEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LendingAccountWithdrawEmissionsBatchEvent {
+    pub header: AccountEventHeader,
+    pub banks: Vec<Pubkey>,
+    /// Withdrawn amount per emissions mint
+    pub emissions_mints: Vec<Pubkey>,
+    pub amounts: Vec<u64>,
+}
+
+#[event]
+pub struct LendingAccountClaimCampaignEmissionsEvent {
+    pub header: AccountEventHeader,
+    pub bank: Pubkey,
+    pub campaign: Pubkey,
+    pub emissions_mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct LendingAccountAutoDeleverageEvent {
+    pub header: AccountEventHeader,
+    pub deleveraged_marginfi_account: Pubkey,
+    pub deleveraged_marginfi_account_authority: Pubkey,
+    pub asset_bank: Pubkey,
+    pub asset_mint: Pubkey,
+    pub liability_bank: Pubkey,
+    pub liability_mint: Pubkey,
+    pub liability_amount: u64,
+    pub asset_amount: f64,
+    pub pre_utilization_ratio: f64,
+    pub post_utilization_ratio: f64,
+}
+
+#[event]
+pub struct LendingAccountCreateConditionalOrderEvent {
+    pub header: AccountEventHeader,
+    pub conditional_order: Pubkey,
+    pub asset_bank: Pubkey,
+    pub liability_bank: Pubkey,
+    pub trigger: u8,
+    pub trigger_health: f64,
+    pub liability_amount: u64,
+    pub keeper_fee_bps: u16,
+}
+
+#[event]
+pub struct LendingAccountExecuteConditionalOrderEvent {
+    pub header: AccountEventHeader,
+    pub conditional_order: Pubkey,
+    pub marginfi_account: Pubkey,
+    pub marginfi_account_authority: Pubkey,
+    pub asset_bank: Pubkey,
+    pub asset_mint: Pubkey,
+    pub liability_bank: Pubkey,
+    pub liability_mint: Pubkey,
+    pub liability_amount: u64,
+    /// Collateral paid to the keeper, including the keeper fee
+    pub asset_amount: f64,
+    pub pre_health: f64,
+    pub post_health: f64,
+}
+
 #[event]
 pub struct MarginfiAccountTransferAccountAuthorityEvent {
     pub header: AccountEventHeader,
@@ -0,0 +1,377 @@
+use crate::constants::LIQUIDITY_VAULT_SEED;
+use crate::events::{AccountEventHeader, LendingAccountAutoDeleverageEvent};
+use crate::state::marginfi_account::{
+    calc_amount, calc_value, BankAccountWrapper, MarginfiAccount, DISABLED_FLAG, IN_FLASHLOAN_FLAG,
+};
+use crate::state::marginfi_group::Bank;
+use crate::state::price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter};
+use crate::{check, debug, math_error, prelude::*, utils};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenInterface;
+use fixed::types::I80F48;
+use solana_program::clock::Clock;
+use solana_program::sysvar::Sysvar;
+
+/// Permissionless instruction deleveraging a borrower of a bank whose liabilities exceed its
+/// assets, e.g. after a large socialized loss.
+///
+/// The keeper repays the liability of the deleveraged account with tokens from its own token
+/// account and receives collateral of the deleveraged account of the same value, at the oracle
+/// price and without any liquidation bonus.
+///
+/// The largest borrowers of the bank are deleveraged first: a deleveraging round starts with the
+/// first deleveraging of an over-utilized bank, and only borrowers owing at least
+/// `Bank::get_auto_deleverage_borrower_threshold` can be deleveraged. The threshold is lowered as
+/// the round goes on, and the round ends once the bank is no longer over-utilized.
+///
+/// ### Deleveraging math:
+/// - `q_l`: Quantity of liability repaid by the keeper
+/// - `q_a`: Quantity of collateral received by the keeper
+/// - `p_l`: Price of the liability
+/// - `p_a`: Price of the collateral
+///
+/// `q_a = q_l * p_l / p_a`
+///
+/// `q_l` is capped at the liability of the deleveraged account and at the amount bringing the
+/// bank utilization down to `ADL_TARGET_UTILIZATION_RATIO`.
+///
+/// The exchange of equal value can only improve the health of the deleveraged account, as
+/// liability weights are >= 1 and asset weights are <= 1.
+///
+/// Accounting changes in the deleveraging process:
+/// 1. The deleveraged account repays `q_l` of the liability
+/// 2. The keeper transfers `q_l` of the liability token into the bank liquidity vault
+/// 3. The deleveraged account removes `q_a` of the collateral
+/// 4. The keeper account receives `q_a` of the collateral
+///
+/// Expected remaining account schema
+/// [
+///    liab_mint_ai (if token2022 mint),
+///    asset_oracle_ais...,
+///    liab_oracle_ais...,
+///    instructions_sysvar (optional, for CPI flashloan callers),
+///  ]
+pub fn lending_account_auto_deleverage<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingAccountAutoDeleverage<'info>>,
+    liab_amount: u64,
+) -> MarginfiResult {
+    check!(
+        liab_amount > 0,
+        MarginfiError::IllegalAutoDeleverage,
+        "Liability amount must be positive"
+    );
+
+    check!(
+        ctx.accounts.asset_bank.key() != ctx.accounts.liab_bank.key(),
+        MarginfiError::IllegalAutoDeleverage,
+        "Asset and liability bank cannot be the same"
+    );
+
+    check!(
+        ctx.accounts.keeper_marginfi_account.key()
+            != ctx.accounts.deleveraged_marginfi_account.key(),
+        MarginfiError::IllegalAutoDeleverage,
+        "Keeper and deleveraged account cannot be the same"
+    );
+
+    let LendingAccountAutoDeleverage {
+        keeper_marginfi_account: keeper_marginfi_account_loader,
+        deleveraged_marginfi_account: deleveraged_marginfi_account_loader,
+        asset_bank: asset_bank_loader,
+        liab_bank: liab_bank_loader,
+        signer,
+        signer_token_account,
+        bank_liquidity_vault,
+        token_program,
+        ..
+    } = ctx.accounts;
+
+    let mut keeper_marginfi_account = keeper_marginfi_account_loader.load_mut()?;
+    let mut deleveraged_marginfi_account = deleveraged_marginfi_account_loader.load_mut()?;
+    let clock = Clock::get()?;
+
+    let maybe_liab_bank_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*liab_bank_loader.load()?,
+        token_program.key,
+    )?;
+
+    check_auto_deleverage_accounts(&keeper_marginfi_account, &deleveraged_marginfi_account)?;
+
+    let mut asset_bank = asset_bank_loader.load_mut()?;
+    let mut liab_bank = liab_bank_loader.load_mut()?;
+
+    asset_bank.assert_operational_mode(None)?;
+    liab_bank.assert_operational_mode(None)?;
+
+    asset_bank.accrue_interest(
+        clock.unix_timestamp,
+        #[cfg(not(feature = "client"))]
+        asset_bank_loader.key(),
+    )?;
+    liab_bank.accrue_interest(
+        clock.unix_timestamp,
+        #[cfg(not(feature = "client"))]
+        liab_bank_loader.key(),
+    )?;
+
+    let pre_utilization_ratio = liab_bank.get_utilization_ratio()?;
+
+    check!(
+        pre_utilization_ratio > I80F48::ONE,
+        MarginfiError::BankNotOverUtilized,
+        "Utilization ratio {}",
+        pre_utilization_ratio
+    );
+
+    let asset_oracle_ais_len = asset_bank.config.oracle_setup.get_oracle_ais_len();
+    let liab_oracle_ais_len = liab_bank.config.oracle_setup.get_oracle_ais_len();
+
+    check!(
+        ctx.remaining_accounts.len() >= asset_oracle_ais_len + liab_oracle_ais_len,
+        MarginfiError::InvalidOracleAccount
+    );
+
+    let asset_price = OraclePriceFeedAdapter::try_from_bank_config(
+        &asset_bank.config,
+        &ctx.remaining_accounts[0..asset_oracle_ais_len],
+        &clock,
+    )?
+    .get_price_of_type(OraclePriceType::RealTime, None)?;
+    let liab_price = OraclePriceFeedAdapter::try_from_bank_config(
+        &liab_bank.config,
+        &ctx.remaining_accounts[asset_oracle_ais_len..asset_oracle_ais_len + liab_oracle_ais_len],
+        &clock,
+    )?
+    .get_price_of_type(OraclePriceType::RealTime, None)?;
+
+    let mut deleveraged_liab_bank_account = BankAccountWrapper::find(
+        &liab_bank_loader.key(),
+        &mut liab_bank,
+        &mut deleveraged_marginfi_account.lending_account,
+    )?;
+
+    let deleveraged_liability = deleveraged_liab_bank_account.bank.get_liability_amount(
+        deleveraged_liab_bank_account
+            .balance
+            .liability_shares
+            .into(),
+    )?;
+    deleveraged_liab_bank_account
+        .bank
+        .start_auto_deleverage_round(clock.unix_timestamp);
+    let borrower_threshold = deleveraged_liab_bank_account
+        .bank
+        .get_auto_deleverage_borrower_threshold(clock.unix_timestamp)?;
+
+    check!(
+        deleveraged_liability >= borrower_threshold,
+        MarginfiError::IllegalAutoDeleverage,
+        "Liability {} below the deleveraging threshold {}, larger borrowers go first",
+        deleveraged_liability,
+        borrower_threshold
+    );
+
+    let max_deleverage_amount = deleveraged_liab_bank_account
+        .bank
+        .get_auto_deleverage_amount()?;
+
+    let liab_amount = I80F48::from_num(liab_amount)
+        .min(deleveraged_liability)
+        .min(max_deleverage_amount)
+        .checked_to_num::<u64>()
+        .ok_or_else(math_error!())?;
+
+    check!(
+        liab_amount > 0,
+        MarginfiError::IllegalAutoDeleverage,
+        "Nothing to deleverage"
+    );
+
+    let asset_amount = calc_amount(
+        calc_value(
+            I80F48::from_num(liab_amount),
+            liab_price,
+            deleveraged_liab_bank_account.bank.mint_decimals,
+            None,
+        )?,
+        asset_price,
+        asset_bank.mint_decimals,
+    )?;
+
+    debug!(
+        "liab_amount: {}, asset_amount: {}, liab_price: {}, asset_price: {}",
+        liab_amount, asset_amount, liab_price, asset_price
+    );
+
+    // Deleveraged account repays its liability with the keeper funds
+    deleveraged_liab_bank_account.repay(I80F48::from_num(liab_amount))?;
+
+    let liab_amount_pre_fee = maybe_liab_bank_mint
+        .as_ref()
+        .map(|mint| {
+            utils::calculate_pre_fee_spl_deposit_amount(
+                mint.to_account_info(),
+                liab_amount,
+                clock.epoch,
+            )
+        })
+        .transpose()?
+        .unwrap_or(liab_amount);
+
+    deleveraged_liab_bank_account.deposit_spl_transfer(
+        liab_amount_pre_fee,
+        signer_token_account.to_account_info(),
+        bank_liquidity_vault.to_account_info(),
+        signer.to_account_info(),
+        maybe_liab_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+
+    // Deleveraged account pays the keeper with its collateral
+    BankAccountWrapper::find(
+        &asset_bank_loader.key(),
+        &mut asset_bank,
+        &mut deleveraged_marginfi_account.lending_account,
+    )?
+    .withdraw(asset_amount)
+    .map_err(|_| MarginfiError::IllegalAutoDeleverage)?;
+
+    BankAccountWrapper::find_or_create(
+        &asset_bank_loader.key(),
+        &mut asset_bank,
+        &mut keeper_marginfi_account.lending_account,
+    )?
+    .increase_balance_in_liquidation(asset_amount)?;
+
+    let post_utilization_ratio = liab_bank.get_utilization_ratio()?;
+
+    if post_utilization_ratio <= I80F48::ONE {
+        liab_bank.adl_round_started_at = 0;
+    }
+
+    emit!(LendingAccountAutoDeleverageEvent {
+        header: AccountEventHeader {
+            signer: Some(signer.key()),
+            marginfi_account: keeper_marginfi_account_loader.key(),
+            marginfi_account_authority: keeper_marginfi_account.authority,
+            marginfi_group: keeper_marginfi_account.group,
+        },
+        deleveraged_marginfi_account: deleveraged_marginfi_account_loader.key(),
+        deleveraged_marginfi_account_authority: deleveraged_marginfi_account.authority,
+        asset_bank: asset_bank_loader.key(),
+        asset_mint: asset_bank.mint,
+        liability_bank: liab_bank_loader.key(),
+        liability_mint: liab_bank.mint,
+        liability_amount: liab_amount,
+        asset_amount: asset_amount.to_num::<f64>(),
+        pre_utilization_ratio: pre_utilization_ratio.to_num::<f64>(),
+        post_utilization_ratio: post_utilization_ratio.to_num::<f64>(),
+    });
+
+    Ok(())
+}
+
+/// Neither account can be disabled, and the deleveraged account can not be in a flashloan, where
+/// its balances are not backed by a health check yet.
+fn check_auto_deleverage_accounts(
+    keeper_marginfi_account: &MarginfiAccount,
+    deleveraged_marginfi_account: &MarginfiAccount,
+) -> MarginfiResult {
+    check!(
+        !keeper_marginfi_account.get_flag(DISABLED_FLAG)
+            && !deleveraged_marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+
+    check!(
+        !deleveraged_marginfi_account.get_flag(IN_FLASHLOAN_FLAG),
+        MarginfiError::AccountInFlashloan
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountAutoDeleverage<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = asset_bank.load()?.group == marginfi_group.key()
+    )]
+    pub asset_bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        constraint = liab_bank.load()?.group == marginfi_group.key()
+    )]
+    pub liab_bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        constraint = keeper_marginfi_account.load()?.group == marginfi_group.key()
+    )]
+    pub keeper_marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = keeper_marginfi_account.load()?.authority
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = deleveraged_marginfi_account.load()?.group == marginfi_group.key()
+    )]
+    pub deleveraged_marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    /// CHECK: Token mint/authority are checked at transfer
+    #[account(mut)]
+    pub signer_token_account: AccountInfo<'info>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_SEED.as_bytes(),
+            liab_bank.key().as_ref(),
+        ],
+        bump = liab_bank.load()?.liquidity_vault_bump,
+    )]
+    pub bank_liquidity_vault: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_deleverage_account_flags() {
+        let keeper = MarginfiAccount::default();
+        let mut deleveraged = MarginfiAccount::default();
+
+        assert!(check_auto_deleverage_accounts(&keeper, &deleveraged).is_ok());
+
+        deleveraged.set_flag(IN_FLASHLOAN_FLAG);
+        assert_eq!(
+            check_auto_deleverage_accounts(&keeper, &deleveraged).unwrap_err(),
+            MarginfiError::AccountInFlashloan.into()
+        );
+        // The keeper can deleverage from its own flashloan
+        assert!(check_auto_deleverage_accounts(&deleveraged, &keeper).is_ok());
+
+        deleveraged.unset_flag(IN_FLASHLOAN_FLAG);
+        deleveraged.set_flag(DISABLED_FLAG);
+        assert_eq!(
+            check_auto_deleverage_accounts(&keeper, &deleveraged).unwrap_err(),
+            MarginfiError::AccountDisabled.into()
+        );
+        assert_eq!(
+            check_auto_deleverage_accounts(&deleveraged, &keeper).unwrap_err(),
+            MarginfiError::AccountDisabled.into()
+        );
+    }
+}
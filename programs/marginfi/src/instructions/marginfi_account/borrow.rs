@@ -2,9 +2,12 @@ use crate::{
     bank_signer, check,
     constants::{LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED},
     events::{AccountEventHeader, LendingAccountBorrowEvent},
+    math_error,
     prelude::{MarginfiError, MarginfiGroup, MarginfiResult},
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, RiskEngine, DISABLED_FLAG},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, RiskEngine, DISABLED_FLAG, IN_FLASHLOAN_FLAG,
+        },
         marginfi_group::{Bank, BankVaultType},
     },
     utils,
@@ -16,11 +19,15 @@ use solana_program::{clock::Clock, sysvar::Sysvar};
 
 /// 1. Accrue interest
 /// 2. Create the user's bank account for the asset borrowed if it does not exist yet
-/// 3. Record liability increase in the bank account
+/// 3. Record liability increase in the bank account, including the origination fee
 /// 4. Transfer funds from the bank's liquidity vault to the signer's token account
 /// 5. Verify that the user account is in a healthy state
 ///
 /// Will error if there is an existing asset <=> withdrawing is not allowed.
+///
+/// Borrows within a flashloan pay no origination fee, the greater of the origination and flashloan
+/// fees is charged on the net borrowed amount when the flashloan ends, see
+/// `lending_account_end_flashloan`.
 pub fn lending_account_borrow<'info>(
     mut ctx: Context<'_, '_, 'info, 'info, LendingAccountBorrow<'info>>,
     amount: u64,
@@ -48,6 +55,8 @@ pub fn lending_account_borrow<'info>(
         MarginfiError::AccountDisabled
     );
 
+    let in_flashloan = marginfi_account.get_flag(IN_FLASHLOAN_FLAG);
+
     bank_loader.load_mut()?.accrue_interest(
         clock.unix_timestamp,
         #[cfg(not(feature = "client"))]
@@ -78,7 +87,22 @@ pub fn lending_account_borrow<'info>(
             .transpose()?
             .unwrap_or(amount);
 
-        bank_account.borrow(I80F48::from_num(amount_pre_fee))?;
+        let origination_fee = if in_flashloan {
+            I80F48::ZERO
+        } else {
+            bank_account
+                .bank
+                .config
+                .interest_rate_config
+                .get_origination_fee(I80F48::from_num(amount_pre_fee))?
+        };
+
+        bank_account.borrow(
+            I80F48::from_num(amount_pre_fee)
+                .checked_add(origination_fee)
+                .ok_or_else(math_error!())?,
+        )?;
+        bank_account.bank.collect_borrow_fee(origination_fee)?;
         bank_account.withdraw_spl_transfer(
             amount_pre_fee,
             bank_liquidity_vault.to_account_info(),
@@ -104,6 +128,7 @@ pub fn lending_account_borrow<'info>(
             bank: bank_loader.key(),
             mint: bank.mint,
             amount: amount_pre_fee,
+            origination_fee: origination_fee.to_num::<f64>(),
         });
     }
 
@@ -0,0 +1,510 @@
+use crate::{
+    check,
+    constants::{CONDITIONAL_ORDER_SEED, LIQUIDITY_VAULT_SEED},
+    debug,
+    events::{
+        AccountEventHeader, LendingAccountCreateConditionalOrderEvent,
+        LendingAccountExecuteConditionalOrderEvent,
+    },
+    math_error,
+    prelude::*,
+    state::{
+        conditional_order::ConditionalOrder,
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, RiskEngine, RiskRequirementType, DISABLED_FLAG,
+            IN_FLASHLOAN_FLAG,
+        },
+        marginfi_group::{Bank, WrappedI80F48},
+        price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter, PriceBias},
+    },
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenInterface;
+use fixed::types::I80F48;
+use solana_program::{clock::Clock, sysvar::Sysvar};
+
+/// Create a conditional order repaying up to `liab_amount` of the liability in `liab_bank` with
+/// collateral from `asset_bank`, once the maintenance health of the account crosses
+/// `trigger_health` in the direction of `trigger`.
+///
+/// The keeper executing the order earns `keeper_fee_bps` of the repaid value in collateral.
+pub fn lending_account_create_conditional_order(
+    ctx: Context<LendingAccountCreateConditionalOrder>,
+    order_index: u16,
+    trigger: u8,
+    trigger_health: WrappedI80F48,
+    liab_amount: u64,
+    keeper_fee_bps: u16,
+) -> MarginfiResult {
+    let marginfi_account = ctx.accounts.marginfi_account.load()?;
+
+    check!(
+        !marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+
+    let mut conditional_order = ctx.accounts.conditional_order.load_init()?;
+
+    *conditional_order = ConditionalOrder {
+        marginfi_account: ctx.accounts.marginfi_account.key(),
+        asset_bank: ctx.accounts.asset_bank.key(),
+        liab_bank: ctx.accounts.liab_bank.key(),
+        trigger_health,
+        liab_amount_remaining: liab_amount,
+        keeper_fee_bps,
+        order_index,
+        trigger,
+        bump: ctx.bumps.conditional_order,
+        ..Default::default()
+    };
+
+    conditional_order.validate()?;
+
+    emit!(LendingAccountCreateConditionalOrderEvent {
+        header: AccountEventHeader {
+            signer: Some(ctx.accounts.signer.key()),
+            marginfi_account: ctx.accounts.marginfi_account.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        conditional_order: ctx.accounts.conditional_order.key(),
+        asset_bank: conditional_order.asset_bank,
+        liability_bank: conditional_order.liab_bank,
+        trigger,
+        trigger_health: I80F48::from(trigger_health).to_num::<f64>(),
+        liability_amount: liab_amount,
+        keeper_fee_bps,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(order_index: u16)]
+pub struct LendingAccountCreateConditionalOrder<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        constraint = marginfi_account.load()?.group == marginfi_group.key(),
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = marginfi_account.load()?.authority,
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        constraint = asset_bank.load()?.group == marginfi_group.key(),
+    )]
+    pub asset_bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        constraint = liab_bank.load()?.group == marginfi_group.key(),
+    )]
+    pub liab_bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        init,
+        payer = fee_payer,
+        space = 8 + std::mem::size_of::<ConditionalOrder>(),
+        seeds = [
+            CONDITIONAL_ORDER_SEED.as_bytes(),
+            marginfi_account.key().as_ref(),
+            &order_index.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub conditional_order: AccountLoader<'info, ConditionalOrder>,
+
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Close a conditional order, the rent goes back to the account authority.
+pub fn lending_account_cancel_conditional_order(
+    _ctx: Context<LendingAccountCancelConditionalOrder>,
+) -> MarginfiResult {
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountCancelConditionalOrder<'info> {
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        mut,
+        address = marginfi_account.load()?.authority,
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        close = signer,
+        constraint = conditional_order.load()?.marginfi_account == marginfi_account.key(),
+    )]
+    pub conditional_order: AccountLoader<'info, ConditionalOrder>,
+}
+
+/// Permissionless instruction executing a triggered conditional order.
+///
+/// The keeper repays the liability of the account with tokens from its own token account and
+/// receives collateral of the account of the same value plus the order keeper fee, at the oracle
+/// price.
+///
+/// There is no swap CPI: the program does not depend on a DEX and never sells the collateral
+/// itself. The keeper sources the liability tokens as it sees fit, e.g. by swapping in the same
+/// transaction, and the oracle price bounds what the account pays for the repayment.
+///
+/// The order triggers on the maintenance health of the account, see
+/// `RiskEngine::get_account_health`. The execution can't lower the maintenance health of the
+/// account.
+///
+/// ### Execution math:
+/// - `q_l`: Quantity of liability repaid by the keeper
+/// - `q_a`: Quantity of collateral received by the keeper
+/// - `p_l`: Price of the liability
+/// - `p_a`: Price of the collateral
+/// - `f_k`: Keeper fee of the order
+///
+/// `q_a = q_l * p_l / p_a * (1 + f_k)`
+///
+/// `p_l` is the high price of the liability and `p_a` the low price of the collateral, see
+/// `ConditionalOrder::get_execution_amounts`. `q_l` is capped at the liability of the account and
+/// at the amount left in the order.
+///
+/// Expected remaining account schema
+/// [
+///    liab_mint_ai (if token2022 mint),
+///    asset_oracle_ais...,
+///    liab_oracle_ais...,
+///    marginfi_account_observation_ais...,
+///  ]
+pub fn lending_account_execute_conditional_order<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingAccountExecuteConditionalOrder<'info>>,
+    liab_amount: u64,
+) -> MarginfiResult {
+    check!(
+        liab_amount > 0,
+        MarginfiError::InvalidConditionalOrder,
+        "Liability amount must be positive"
+    );
+
+    check!(
+        ctx.accounts.keeper_marginfi_account.key() != ctx.accounts.marginfi_account.key(),
+        MarginfiError::InvalidConditionalOrder,
+        "Keeper and order account cannot be the same"
+    );
+
+    let LendingAccountExecuteConditionalOrder {
+        keeper_marginfi_account: keeper_marginfi_account_loader,
+        marginfi_account: marginfi_account_loader,
+        conditional_order: conditional_order_loader,
+        asset_bank: asset_bank_loader,
+        liab_bank: liab_bank_loader,
+        signer,
+        signer_token_account,
+        bank_liquidity_vault,
+        token_program,
+        ..
+    } = ctx.accounts;
+
+    let mut keeper_marginfi_account = keeper_marginfi_account_loader.load_mut()?;
+    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+    let mut conditional_order = conditional_order_loader.load_mut()?;
+    let clock = Clock::get()?;
+
+    let maybe_liab_bank_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*liab_bank_loader.load()?,
+        token_program.key,
+    )?;
+
+    check_conditional_order_accounts(&keeper_marginfi_account, &marginfi_account)?;
+
+    {
+        asset_bank_loader.load()?.assert_operational_mode(None)?;
+        liab_bank_loader.load()?.assert_operational_mode(None)?;
+
+        asset_bank_loader.load_mut()?.accrue_interest(
+            clock.unix_timestamp,
+            #[cfg(not(feature = "client"))]
+            asset_bank_loader.key(),
+        )?;
+        liab_bank_loader.load_mut()?.accrue_interest(
+            clock.unix_timestamp,
+            #[cfg(not(feature = "client"))]
+            liab_bank_loader.key(),
+        )?;
+    }
+
+    let asset_oracle_ais_len = asset_bank_loader
+        .load()?
+        .config
+        .oracle_setup
+        .get_oracle_ais_len();
+    let liab_oracle_ais_len = liab_bank_loader
+        .load()?
+        .config
+        .oracle_setup
+        .get_oracle_ais_len();
+    let account_remaining_len =
+        marginfi_account.get_remaining_accounts_len(ctx.remaining_accounts)?;
+
+    check!(
+        ctx.remaining_accounts.len()
+            >= asset_oracle_ais_len + liab_oracle_ais_len + account_remaining_len,
+        MarginfiError::InvalidOracleAccount
+    );
+
+    let account_remaining_ais =
+        &ctx.remaining_accounts[ctx.remaining_accounts.len() - account_remaining_len..];
+
+    let pre_health = RiskEngine::new(&marginfi_account, account_remaining_ais)?
+        .get_account_health(RiskRequirementType::Maintenance)?;
+
+    check!(
+        conditional_order.is_triggered(pre_health),
+        MarginfiError::ConditionalOrderNotTriggered,
+        "Maintenance health {}",
+        pre_health
+    );
+
+    let (liab_amount, asset_amount, asset_mint, liab_mint) = {
+        let mut asset_bank = asset_bank_loader.load_mut()?;
+        let mut liab_bank = liab_bank_loader.load_mut()?;
+
+        let asset_price = OraclePriceFeedAdapter::try_from_bank_config(
+            &asset_bank.config,
+            &ctx.remaining_accounts[0..asset_oracle_ais_len],
+            &clock,
+        )?
+        .get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::Low))?;
+        let liab_price = OraclePriceFeedAdapter::try_from_bank_config(
+            &liab_bank.config,
+            &ctx.remaining_accounts
+                [asset_oracle_ais_len..asset_oracle_ais_len + liab_oracle_ais_len],
+            &clock,
+        )?
+        .get_price_of_type(OraclePriceType::RealTime, Some(PriceBias::High))?;
+
+        let mut liab_bank_account = BankAccountWrapper::find(
+            &liab_bank_loader.key(),
+            &mut liab_bank,
+            &mut marginfi_account.lending_account,
+        )?;
+
+        let liability = liab_bank_account
+            .bank
+            .get_liability_amount(liab_bank_account.balance.liability_shares.into())?;
+
+        let (liab_amount, asset_amount) = conditional_order.get_execution_amounts(
+            liab_amount,
+            liability,
+            liab_price,
+            liab_bank_account.bank.mint_decimals,
+            asset_price,
+            asset_bank.mint_decimals,
+        )?;
+
+        debug!(
+            "liab_amount: {}, asset_amount: {}, liab_price: {}, asset_price: {}",
+            liab_amount, asset_amount, liab_price, asset_price
+        );
+
+        // Account repays its liability with the keeper funds
+        liab_bank_account.repay(I80F48::from_num(liab_amount))?;
+
+        let liab_amount_pre_fee = maybe_liab_bank_mint
+            .as_ref()
+            .map(|mint| {
+                utils::calculate_pre_fee_spl_deposit_amount(
+                    mint.to_account_info(),
+                    liab_amount,
+                    clock.epoch,
+                )
+            })
+            .transpose()?
+            .unwrap_or(liab_amount);
+
+        liab_bank_account.deposit_spl_transfer(
+            liab_amount_pre_fee,
+            signer_token_account.to_account_info(),
+            bank_liquidity_vault.to_account_info(),
+            signer.to_account_info(),
+            maybe_liab_bank_mint.as_ref(),
+            token_program.to_account_info(),
+            ctx.remaining_accounts,
+        )?;
+
+        // Account pays the keeper with its collateral
+        BankAccountWrapper::find(
+            &asset_bank_loader.key(),
+            &mut asset_bank,
+            &mut marginfi_account.lending_account,
+        )?
+        .withdraw(asset_amount)
+        .map_err(|_| MarginfiError::InvalidConditionalOrder)?;
+
+        BankAccountWrapper::find_or_create(
+            &asset_bank_loader.key(),
+            &mut asset_bank,
+            &mut keeper_marginfi_account.lending_account,
+        )?
+        .increase_balance_in_liquidation(asset_amount)?;
+
+        (liab_amount, asset_amount, asset_bank.mint, liab_bank.mint)
+    };
+
+    conditional_order.liab_amount_remaining = conditional_order
+        .liab_amount_remaining
+        .checked_sub(liab_amount)
+        .ok_or_else(math_error!())?;
+
+    let post_health = RiskEngine::new(&marginfi_account, account_remaining_ais)?
+        .get_account_health(RiskRequirementType::Maintenance)?;
+
+    check!(
+        post_health >= pre_health,
+        MarginfiError::InvalidConditionalOrder,
+        "Execution lowers the account health from {} to {}",
+        pre_health,
+        post_health
+    );
+
+    emit!(LendingAccountExecuteConditionalOrderEvent {
+        header: AccountEventHeader {
+            signer: Some(signer.key()),
+            marginfi_account: keeper_marginfi_account_loader.key(),
+            marginfi_account_authority: keeper_marginfi_account.authority,
+            marginfi_group: keeper_marginfi_account.group,
+        },
+        conditional_order: conditional_order_loader.key(),
+        marginfi_account: marginfi_account_loader.key(),
+        marginfi_account_authority: marginfi_account.authority,
+        asset_bank: asset_bank_loader.key(),
+        asset_mint,
+        liability_bank: liab_bank_loader.key(),
+        liability_mint: liab_mint,
+        liability_amount: liab_amount,
+        asset_amount: asset_amount.to_num::<f64>(),
+        pre_health: pre_health.to_num::<f64>(),
+        post_health: post_health.to_num::<f64>(),
+    });
+
+    Ok(())
+}
+
+/// The keeper can execute orders from its own flashloan, e.g. to borrow the liability tokens, but
+/// the account of the order can't be in a flashloan, where its health is not checked.
+fn check_conditional_order_accounts(
+    keeper_marginfi_account: &MarginfiAccount,
+    marginfi_account: &MarginfiAccount,
+) -> MarginfiResult {
+    check!(
+        !keeper_marginfi_account.get_flag(DISABLED_FLAG)
+            && !marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+
+    check!(
+        !marginfi_account.get_flag(IN_FLASHLOAN_FLAG),
+        MarginfiError::AccountInFlashloan
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountExecuteConditionalOrder<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = asset_bank.load()?.group == marginfi_group.key()
+    )]
+    pub asset_bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        constraint = liab_bank.load()?.group == marginfi_group.key()
+    )]
+    pub liab_bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        constraint = keeper_marginfi_account.load()?.group == marginfi_group.key()
+    )]
+    pub keeper_marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = keeper_marginfi_account.load()?.authority
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = marginfi_account.load()?.group == marginfi_group.key()
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        mut,
+        constraint = conditional_order.load()?.marginfi_account == marginfi_account.key(),
+        constraint = conditional_order.load()?.asset_bank == asset_bank.key(),
+        constraint = conditional_order.load()?.liab_bank == liab_bank.key(),
+    )]
+    pub conditional_order: AccountLoader<'info, ConditionalOrder>,
+
+    /// CHECK: Token mint/authority are checked at transfer
+    #[account(mut)]
+    pub signer_token_account: AccountInfo<'info>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_SEED.as_bytes(),
+            liab_bank.key().as_ref(),
+        ],
+        bump = liab_bank.load()?.liquidity_vault_bump,
+    )]
+    pub bank_liquidity_vault: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditional_order_account_flags() {
+        let keeper = MarginfiAccount::default();
+        let mut account = MarginfiAccount::default();
+
+        assert!(check_conditional_order_accounts(&keeper, &account).is_ok());
+
+        account.set_flag(IN_FLASHLOAN_FLAG);
+        assert_eq!(
+            check_conditional_order_accounts(&keeper, &account).unwrap_err(),
+            MarginfiError::AccountInFlashloan.into()
+        );
+        // The keeper can execute from its own flashloan
+        assert!(check_conditional_order_accounts(&account, &keeper).is_ok());
+
+        account.unset_flag(IN_FLASHLOAN_FLAG);
+        account.set_flag(DISABLED_FLAG);
+        assert_eq!(
+            check_conditional_order_accounts(&keeper, &account).unwrap_err(),
+            MarginfiError::AccountDisabled.into()
+        );
+        assert_eq!(
+            check_conditional_order_accounts(&account, &keeper).unwrap_err(),
+            MarginfiError::AccountDisabled.into()
+        );
+    }
+}
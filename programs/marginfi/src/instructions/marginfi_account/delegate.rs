@@ -0,0 +1,45 @@
+use crate::{
+    events::{AccountEventHeader, MarginfiAccountSetDelegateEvent},
+    prelude::*,
+    state::marginfi_account::MarginfiAccount,
+};
+use anchor_lang::prelude::*;
+
+/// Grant `delegate` the `DELEGATE_PERMISSION_*` flags in `permissions` until `expiry` (0 for no
+/// expiry), replacing any previous delegate. `Pubkey::default()` removes the delegate.
+pub fn set_account_delegate(
+    ctx: Context<MarginfiAccountSetDelegate>,
+    delegate: Pubkey,
+    permissions: u64,
+    expiry: i64,
+) -> MarginfiResult {
+    let clock = Clock::get()?;
+    let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
+
+    marginfi_account.set_delegate(delegate, permissions, expiry, clock.unix_timestamp)?;
+
+    emit!(MarginfiAccountSetDelegateEvent {
+        header: AccountEventHeader {
+            signer: Some(ctx.accounts.signer.key()),
+            marginfi_account: ctx.accounts.marginfi_account.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        delegate: marginfi_account.delegate,
+        permissions: marginfi_account.delegate_permissions,
+        expiry: marginfi_account.delegate_expiry,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct MarginfiAccountSetDelegate<'info> {
+    #[account(mut)]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = marginfi_account.load()?.authority,
+    )]
+    pub signer: Signer<'info>,
+}
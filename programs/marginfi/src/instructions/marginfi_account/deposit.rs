@@ -4,7 +4,9 @@ use crate::{
     events::{AccountEventHeader, LendingAccountDepositEvent},
     prelude::*,
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, DISABLED_FLAG},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, DELEGATE_PERMISSION_DEPOSIT, DISABLED_FLAG,
+        },
         marginfi_group::Bank,
     },
     utils,
@@ -107,7 +109,11 @@ pub struct LendingAccountDeposit<'info> {
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
     #[account(
-        address = marginfi_account.load()?.authority,
+        constraint = marginfi_account.load()?.can_act(
+            signer.key,
+            DELEGATE_PERMISSION_DEPOSIT,
+            Clock::get()?.unix_timestamp,
+        ) @ MarginfiError::Unauthorized,
     )]
     pub signer: Signer<'info>,
 
@@ -2,18 +2,34 @@ use anchor_lang::{prelude::*, Accounts, ToAccountInfo};
 use anchor_spl::token_interface::{
     transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
 };
+use fixed::types::I80F48;
 
 use crate::{
     check,
-    constants::{EMISSIONS_AUTH_SEED, EMISSIONS_TOKEN_ACCOUNT_SEED},
+    constants::{
+        EMISSIONS_AUTH_SEED, EMISSIONS_CAMPAIGN_AUTH_SEED, EMISSIONS_CAMPAIGN_POSITION_SEED,
+        EMISSIONS_CAMPAIGN_VAULT_SEED, EMISSIONS_TOKEN_ACCOUNT_SEED, LIQUIDITY_VAULT_SEED,
+    },
     debug,
+    events::{
+        AccountEventHeader, LendingAccountClaimCampaignEmissionsEvent,
+        LendingAccountCompoundEmissionsEvent, LendingAccountWithdrawEmissionsBatchEvent,
+    },
+    math_error,
     prelude::{MarginfiError, MarginfiResult},
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, DISABLED_FLAG},
+        emissions_campaign::{EmissionsCampaign, EmissionsCampaignPosition},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, DISABLED_FLAG, EMISSIONS_AUTO_COMPOUND_FLAG,
+        },
         marginfi_group::{Bank, MarginfiGroup},
     },
+    utils,
 };
 
+/// Withdraw the emissions of a balance, signed by the account authority, the emissions claim
+/// delegate or a delegate with `DELEGATE_PERMISSION_CLAIM_EMISSIONS`. Delegates can only withdraw
+/// to token accounts of the account authority.
 pub fn lending_account_withdraw_emissions<'info>(
     ctx: Context<'_, '_, 'info, 'info, LendingAccountWithdrawEmissions<'info>>,
 ) -> MarginfiResult {
@@ -23,6 +39,11 @@ pub fn lending_account_withdraw_emissions<'info>(
         !marginfi_account.get_flag(DISABLED_FLAG),
         MarginfiError::AccountDisabled
     );
+    check!(
+        ctx.accounts.signer.key() == marginfi_account.authority
+            || ctx.accounts.destination_account.owner == marginfi_account.authority,
+        MarginfiError::Unauthorized
+    );
 
     let mut bank = ctx.accounts.bank.load_mut()?;
 
@@ -75,7 +96,10 @@ pub struct LendingAccountWithdrawEmissions<'info> {
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
     #[account(
-        address = marginfi_account.load()?.authority,
+        constraint = marginfi_account.load()?.can_withdraw_emissions(
+            signer.key,
+            Clock::get()?.unix_timestamp,
+        ) @ MarginfiError::Unauthorized,
     )]
     pub signer: Signer<'info>,
 
@@ -117,6 +141,381 @@ pub struct LendingAccountWithdrawEmissions<'info> {
     pub token_program: Interface<'info, TokenInterface>,
 }
 
+/// Number of remaining accounts per bank in `lending_account_withdraw_emissions_batch`.
+const EMISSIONS_BATCH_ACCOUNTS_PER_BANK: usize = 5;
+
+/// Bumps of the emissions PDAs of a bank in `lending_account_withdraw_emissions_batch`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug)]
+pub struct EmissionsBatchBumps {
+    pub emissions_auth_bump: u8,
+    pub emissions_vault_bump: u8,
+}
+
+/// Withdraw the emissions of several balances of an account in one instruction.
+///
+/// Emissions are held in a vault per bank, owned by a per bank authority, so there is one transfer
+/// per bank with emissions to withdraw. The summary event adds up the amounts per emissions mint.
+///
+/// All emissions mints must belong to the passed token program. The same destination account can
+/// be passed for all banks sharing an emissions mint. Signed by the account authority, the
+/// emissions claim delegate or a delegate with `DELEGATE_PERMISSION_CLAIM_EMISSIONS`, delegates
+/// can only withdraw to token accounts of the authority.
+///
+/// `bumps` holds the emissions PDA bumps of each bank, in the order of the banks.
+///
+/// Expected remaining account schema
+/// [
+///    bank_ai,
+///    emissions_mint_ai,
+///    emissions_auth_ai,
+///    emissions_vault_ai,
+///    destination_account_ai,
+/// ] per bank
+pub fn lending_account_withdraw_emissions_batch<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingAccountWithdrawEmissionsBatch<'info>>,
+    bumps: Vec<EmissionsBatchBumps>,
+) -> MarginfiResult {
+    let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
+
+    check!(
+        !marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+    check!(
+        !ctx.remaining_accounts.is_empty()
+            && ctx.remaining_accounts.len() == bumps.len() * EMISSIONS_BATCH_ACCOUNTS_PER_BANK,
+        MarginfiError::InvalidEmissionsClaimAccounts
+    );
+
+    let mut banks = Vec::new();
+    let mut emissions_mints: Vec<Pubkey> = Vec::new();
+    let mut amounts: Vec<u64> = Vec::new();
+
+    for (bank_ais, bank_bumps) in ctx
+        .remaining_accounts
+        .chunks(EMISSIONS_BATCH_ACCOUNTS_PER_BANK)
+        .zip(bumps.iter())
+    {
+        let bank_ai = &bank_ais[0];
+        let emissions_mint_ai = &bank_ais[1];
+        let emissions_auth_ai = &bank_ais[2];
+        let emissions_vault_ai = &bank_ais[3];
+        let destination_ai = &bank_ais[4];
+
+        let bank_loader = AccountLoader::<Bank>::try_from(bank_ai)?;
+        let mut bank = bank_loader.load_mut()?;
+
+        check_emissions_batch_bank_ais(bank_ais, &bank, marginfi_account.group, bank_bumps)?;
+
+        let emissions_mint = InterfaceAccount::<Mint>::try_from(emissions_mint_ai)?;
+
+        if ctx.accounts.signer.key() != marginfi_account.authority {
+            let destination = InterfaceAccount::<TokenAccount>::try_from(destination_ai)?;
+            check!(
+                destination.owner == marginfi_account.authority,
+                MarginfiError::Unauthorized
+            );
+        }
+
+        let mut balance = BankAccountWrapper::find(
+            bank_ai.key,
+            &mut bank,
+            &mut marginfi_account.lending_account,
+        )?;
+
+        let emissions_settle_amount = balance.settle_emissions_and_get_transfer_amount()?;
+
+        if emissions_settle_amount > 0 {
+            debug!(
+                "Transferring {} emissions of bank {} to user",
+                emissions_settle_amount, bank_ai.key
+            );
+
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                EMISSIONS_AUTH_SEED.as_bytes(),
+                &bank_ai.key.to_bytes(),
+                &emissions_mint_ai.key.to_bytes(),
+                &[bank_bumps.emissions_auth_bump],
+            ]];
+
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: emissions_vault_ai.clone(),
+                        to: destination_ai.clone(),
+                        authority: emissions_auth_ai.clone(),
+                        mint: emissions_mint_ai.clone(),
+                    },
+                    signer_seeds,
+                ),
+                emissions_settle_amount,
+                emissions_mint.decimals,
+            )?;
+        }
+
+        banks.push(*bank_ai.key);
+        match emissions_mints
+            .iter()
+            .position(|mint| mint == emissions_mint_ai.key)
+        {
+            Some(index) => {
+                amounts[index] = amounts[index]
+                    .checked_add(emissions_settle_amount)
+                    .ok_or_else(math_error!())?
+            }
+            None => {
+                emissions_mints.push(*emissions_mint_ai.key);
+                amounts.push(emissions_settle_amount);
+            }
+        }
+    }
+
+    emit!(LendingAccountWithdrawEmissionsBatchEvent {
+        header: AccountEventHeader {
+            signer: Some(ctx.accounts.signer.key()),
+            marginfi_account: ctx.accounts.marginfi_account.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        banks,
+        emissions_mints,
+        amounts,
+    });
+
+    Ok(())
+}
+
+/// Check the emissions accounts of a bank in `lending_account_withdraw_emissions_batch`, deriving
+/// the emissions PDAs from the passed bumps.
+fn check_emissions_batch_bank_ais(
+    bank_ais: &[AccountInfo],
+    bank: &Bank,
+    marginfi_group_pk: Pubkey,
+    bumps: &EmissionsBatchBumps,
+) -> MarginfiResult {
+    let bank_pk = bank_ais[0].key;
+    let emissions_mint_pk = bank_ais[1].key;
+
+    check!(
+        bank.group == marginfi_group_pk && bank.emissions_mint == *emissions_mint_pk,
+        MarginfiError::InvalidEmissionsClaimAccounts
+    );
+
+    let emissions_auth = Pubkey::create_program_address(
+        &[
+            EMISSIONS_AUTH_SEED.as_bytes(),
+            bank_pk.as_ref(),
+            emissions_mint_pk.as_ref(),
+            &[bumps.emissions_auth_bump],
+        ],
+        &crate::id(),
+    )
+    .map_err(|_| MarginfiError::InvalidEmissionsClaimAccounts)?;
+    let emissions_vault = Pubkey::create_program_address(
+        &[
+            EMISSIONS_TOKEN_ACCOUNT_SEED.as_bytes(),
+            bank_pk.as_ref(),
+            emissions_mint_pk.as_ref(),
+            &[bumps.emissions_vault_bump],
+        ],
+        &crate::id(),
+    )
+    .map_err(|_| MarginfiError::InvalidEmissionsClaimAccounts)?;
+
+    check!(
+        emissions_auth == *bank_ais[2].key && emissions_vault == *bank_ais[3].key,
+        MarginfiError::InvalidEmissionsClaimAccounts
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountWithdrawEmissionsBatch<'info> {
+    #[account(mut)]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        constraint = marginfi_account.load()?.can_withdraw_emissions(
+            signer.key,
+            Clock::get()?.unix_timestamp,
+        ) @ MarginfiError::Unauthorized,
+    )]
+    pub signer: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Set the emissions claim delegate (`Pubkey::default()` to remove it) and the auto-compound mode
+/// of an account.
+pub fn lending_account_configure_emissions(
+    ctx: Context<LendingAccountConfigureEmissions>,
+    claim_delegate: Option<Pubkey>,
+    auto_compound: Option<bool>,
+) -> MarginfiResult {
+    let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
+
+    if let Some(claim_delegate) = claim_delegate {
+        msg!("Setting emissions claim delegate to {}", claim_delegate);
+        marginfi_account.emissions_claim_delegate = claim_delegate;
+    }
+
+    match auto_compound {
+        Some(true) => marginfi_account.set_flag(EMISSIONS_AUTO_COMPOUND_FLAG),
+        Some(false) => marginfi_account.unset_flag(EMISSIONS_AUTO_COMPOUND_FLAG),
+        None => {}
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountConfigureEmissions<'info> {
+    #[account(mut)]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = marginfi_account.load()?.authority,
+    )]
+    pub signer: Signer<'info>,
+}
+
+/// Permissionlessly deposit the emissions of a balance back into the balance, for accounts in
+/// auto-compound mode on banks emitting their own mint.
+///
+/// Emissions of a liability balance repay the liability.
+pub fn lending_account_compound_emissions<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingAccountCompoundEmissions<'info>>,
+) -> MarginfiResult {
+    let clock = Clock::get()?;
+    let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
+    let mut bank = ctx.accounts.bank.load_mut()?;
+
+    check!(
+        !marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+    check!(
+        marginfi_account.get_flag(EMISSIONS_AUTO_COMPOUND_FLAG) && bank.emissions_mint == bank.mint,
+        MarginfiError::EmissionsCompoundNotAllowed
+    );
+
+    bank.accrue_interest(
+        clock.unix_timestamp,
+        #[cfg(not(feature = "client"))]
+        ctx.accounts.bank.key(),
+    )?;
+
+    let mut balance = BankAccountWrapper::find(
+        ctx.accounts.bank.to_account_info().key,
+        &mut bank,
+        &mut marginfi_account.lending_account,
+    )?;
+
+    let emissions_settle_amount = balance.settle_emissions_and_get_transfer_amount()?;
+
+    if emissions_settle_amount > 0 {
+        let deposit_amount = utils::calculate_post_fee_spl_deposit_amount(
+            ctx.accounts.emissions_mint.to_account_info(),
+            emissions_settle_amount,
+            clock.epoch,
+        )?;
+
+        debug!("Compounding {} emissions", deposit_amount);
+
+        balance.deposit(I80F48::from_num(deposit_amount))?;
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            EMISSIONS_AUTH_SEED.as_bytes(),
+            &ctx.accounts.bank.key().to_bytes(),
+            &ctx.accounts.emissions_mint.key().to_bytes(),
+            &[ctx.bumps.emissions_auth],
+        ]];
+
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.emissions_vault.to_account_info(),
+                    to: ctx.accounts.bank_liquidity_vault.to_account_info(),
+                    authority: ctx.accounts.emissions_auth.to_account_info(),
+                    mint: ctx.accounts.emissions_mint.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            emissions_settle_amount,
+            ctx.accounts.emissions_mint.decimals,
+        )?;
+    }
+
+    emit!(LendingAccountCompoundEmissionsEvent {
+        header: AccountEventHeader {
+            signer: None,
+            marginfi_account: ctx.accounts.marginfi_account.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        bank: ctx.accounts.bank.key(),
+        mint: bank.mint,
+        amount: emissions_settle_amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountCompoundEmissions<'info> {
+    #[account(
+        mut,
+        constraint = marginfi_account.load()?.group == bank.load()?.group,
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(mut)]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        address = bank.load()?.emissions_mint
+    )]
+    pub emissions_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [
+            EMISSIONS_AUTH_SEED.as_bytes(),
+            bank.key().as_ref(),
+            emissions_mint.key().as_ref(),
+        ],
+        bump
+    )]
+    /// CHECK: Asserted by PDA
+    pub emissions_auth: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            EMISSIONS_TOKEN_ACCOUNT_SEED.as_bytes(),
+            bank.key().as_ref(),
+            emissions_mint.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub emissions_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        mut,
+        seeds = [
+            LIQUIDITY_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.liquidity_vault_bump,
+    )]
+    pub bank_liquidity_vault: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
 /// Permissionlessly settle unclaimed emissions to a users account.
 pub fn lending_account_settle_emissions(
     ctx: Context<LendingAccountSettleEmissions>,
@@ -146,3 +545,291 @@ pub struct LendingAccountSettleEmissions<'info> {
     #[account(mut)]
     pub bank: AccountLoader<'info, Bank>,
 }
+
+/// Opt a marginfi account into an emissions campaign.
+///
+/// Campaign emissions accrue from the creation of the position, on the balance checkpointed at
+/// the last update of the position, see `EmissionsCampaign::accrue_position`. Users claim after
+/// increasing their balance to start earning on it.
+pub fn lending_account_init_campaign_position(
+    ctx: Context<LendingAccountInitCampaignPosition>,
+) -> MarginfiResult {
+    let marginfi_account = ctx.accounts.marginfi_account.load()?;
+    let campaign = ctx.accounts.campaign.load()?;
+    let mut position = ctx.accounts.position.load_init()?;
+
+    let balance = marginfi_account
+        .lending_account
+        .balances
+        .iter()
+        .find(|balance| balance.active && balance.bank_pk == campaign.bank);
+
+    *position = EmissionsCampaignPosition {
+        campaign: ctx.accounts.campaign.key(),
+        marginfi_account: ctx.accounts.marginfi_account.key(),
+        emissions_outstanding: I80F48::ZERO.into(),
+        last_update: Clock::get()?.unix_timestamp,
+        bump: ctx.bumps.position,
+        _pad0: [0; 7],
+        balance_shares: campaign.get_balance_shares(balance).into(),
+        _padding: [0; 6],
+    };
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountInitCampaignPosition<'info> {
+    #[account(
+        constraint = marginfi_account.load()?.group == campaign.load()?.group,
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    pub campaign: AccountLoader<'info, EmissionsCampaign>,
+
+    #[account(
+        init,
+        seeds = [
+            EMISSIONS_CAMPAIGN_POSITION_SEED.as_bytes(),
+            campaign.key().as_ref(),
+            marginfi_account.key().as_ref(),
+        ],
+        bump,
+        payer = fee_payer,
+        space = 8 + std::mem::size_of::<EmissionsCampaignPosition>(),
+    )]
+    pub position: AccountLoader<'info, EmissionsCampaignPosition>,
+
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accrue and withdraw the emissions of a campaign position.
+pub fn lending_account_claim_campaign_emissions(
+    ctx: Context<LendingAccountClaimCampaignEmissions>,
+) -> MarginfiResult {
+    let marginfi_account = ctx.accounts.marginfi_account.load()?;
+
+    check!(
+        !marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+
+    let bank = ctx.accounts.bank.load()?;
+    let mut campaign = ctx.accounts.campaign.load_mut()?;
+    let mut position = ctx.accounts.position.load_mut()?;
+
+    let balance = marginfi_account
+        .lending_account
+        .balances
+        .iter()
+        .find(|balance| balance.active && balance.bank_pk == ctx.accounts.bank.key());
+
+    campaign.accrue_position(&mut position, &bank, balance, Clock::get()?.unix_timestamp)?;
+
+    let emissions_settle_amount = position.settle_emissions_and_get_transfer_amount()?;
+
+    if emissions_settle_amount > 0 {
+        debug!(
+            "Transferring {} campaign emissions to user",
+            emissions_settle_amount
+        );
+
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            EMISSIONS_CAMPAIGN_AUTH_SEED.as_bytes(),
+            &ctx.accounts.campaign.key().to_bytes(),
+            &[campaign.vault_authority_bump],
+        ]];
+
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.emissions_vault.to_account_info(),
+                    to: ctx.accounts.destination_account.to_account_info(),
+                    authority: ctx.accounts.emissions_auth.to_account_info(),
+                    mint: ctx.accounts.emissions_mint.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            emissions_settle_amount,
+            ctx.accounts.emissions_mint.decimals,
+        )?;
+    }
+
+    emit!(LendingAccountClaimCampaignEmissionsEvent {
+        header: AccountEventHeader {
+            signer: Some(ctx.accounts.signer.key()),
+            marginfi_account: ctx.accounts.marginfi_account.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        bank: ctx.accounts.bank.key(),
+        campaign: ctx.accounts.campaign.key(),
+        emissions_mint: campaign.emissions_mint,
+        amount: emissions_settle_amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountClaimCampaignEmissions<'info> {
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = marginfi_account.load()?.authority,
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        address = campaign.load()?.bank,
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(mut)]
+    pub campaign: AccountLoader<'info, EmissionsCampaign>,
+
+    #[account(
+        mut,
+        seeds = [
+            EMISSIONS_CAMPAIGN_POSITION_SEED.as_bytes(),
+            campaign.key().as_ref(),
+            marginfi_account.key().as_ref(),
+        ],
+        bump = position.load()?.bump,
+    )]
+    pub position: AccountLoader<'info, EmissionsCampaignPosition>,
+
+    #[account(
+        address = campaign.load()?.emissions_mint,
+    )]
+    pub emissions_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [
+            EMISSIONS_CAMPAIGN_AUTH_SEED.as_bytes(),
+            campaign.key().as_ref(),
+        ],
+        bump = campaign.load()?.vault_authority_bump,
+    )]
+    /// CHECK: Asserted by PDA
+    pub emissions_auth: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            EMISSIONS_CAMPAIGN_VAULT_SEED.as_bytes(),
+            campaign.key().as_ref(),
+        ],
+        bump = campaign.load()?.vault_bump,
+    )]
+    pub emissions_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub destination_account: Box<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaked_account_info(key: Pubkey) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            true,
+            Box::leak(Box::new(0)),
+            Box::leak(Vec::new().into_boxed_slice()),
+            Box::leak(Box::new(Pubkey::default())),
+            false,
+            0,
+        )
+    }
+
+    fn emissions_batch_bank(
+        group: Pubkey,
+    ) -> (Vec<AccountInfo<'static>>, Bank, EmissionsBatchBumps) {
+        let bank_pk = Pubkey::new_unique();
+        let emissions_mint = Pubkey::new_unique();
+        let (emissions_auth, emissions_auth_bump) = Pubkey::find_program_address(
+            &[
+                EMISSIONS_AUTH_SEED.as_bytes(),
+                bank_pk.as_ref(),
+                emissions_mint.as_ref(),
+            ],
+            &crate::id(),
+        );
+        let (emissions_vault, emissions_vault_bump) = Pubkey::find_program_address(
+            &[
+                EMISSIONS_TOKEN_ACCOUNT_SEED.as_bytes(),
+                bank_pk.as_ref(),
+                emissions_mint.as_ref(),
+            ],
+            &crate::id(),
+        );
+
+        let bank_ais = vec![
+            leaked_account_info(bank_pk),
+            leaked_account_info(emissions_mint),
+            leaked_account_info(emissions_auth),
+            leaked_account_info(emissions_vault),
+            leaked_account_info(Pubkey::new_unique()),
+        ];
+        let bank = Bank {
+            group,
+            emissions_mint,
+            ..Bank::default()
+        };
+        let bumps = EmissionsBatchBumps {
+            emissions_auth_bump,
+            emissions_vault_bump,
+        };
+
+        (bank_ais, bank, bumps)
+    }
+
+    #[test]
+    fn emissions_batch_bank_accounts() {
+        let group = Pubkey::new_unique();
+        let (bank_a_ais, bank_a, bumps_a) = emissions_batch_bank(group);
+        let (bank_b_ais, bank_b, bumps_b) = emissions_batch_bank(group);
+
+        assert!(check_emissions_batch_bank_ais(&bank_a_ais, &bank_a, group, &bumps_a).is_ok());
+        assert!(check_emissions_batch_bank_ais(&bank_b_ais, &bank_b, group, &bumps_b).is_ok());
+
+        // Bank of another group
+        assert_eq!(
+            check_emissions_batch_bank_ais(&bank_a_ais, &bank_a, Pubkey::new_unique(), &bumps_a)
+                .unwrap_err(),
+            MarginfiError::InvalidEmissionsClaimAccounts.into()
+        );
+
+        // Accounts of another bank
+        assert_eq!(
+            check_emissions_batch_bank_ais(&bank_b_ais, &bank_a, group, &bumps_b).unwrap_err(),
+            MarginfiError::InvalidEmissionsClaimAccounts.into()
+        );
+
+        // Emissions vault of another bank
+        let mut mixed_ais = bank_a_ais.clone();
+        mixed_ais[3] = bank_b_ais[3].clone();
+        assert_eq!(
+            check_emissions_batch_bank_ais(&mixed_ais, &bank_a, group, &bumps_a).unwrap_err(),
+            MarginfiError::InvalidEmissionsClaimAccounts.into()
+        );
+
+        // Wrong emissions authority bump
+        let wrong_bumps = EmissionsBatchBumps {
+            emissions_auth_bump: bumps_a.emissions_auth_bump.wrapping_sub(1),
+            ..bumps_a
+        };
+        assert_eq!(
+            check_emissions_batch_bank_ais(&bank_a_ais, &bank_a, group, &wrong_bumps).unwrap_err(),
+            MarginfiError::InvalidEmissionsClaimAccounts.into()
+        );
+    }
+}
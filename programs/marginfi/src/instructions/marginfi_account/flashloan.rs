@@ -1,4 +1,5 @@
 use anchor_lang::{prelude::*, Discriminator};
+use fixed::types::I80F48;
 use solana_program::{
     instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
     sysvar::{self, instructions},
@@ -6,22 +7,56 @@ use solana_program::{
 
 use crate::{
     check,
+    events::{AccountEventHeader, LendingAccountFlashloanFeeEvent},
     prelude::*,
-    state::marginfi_account::{MarginfiAccount, RiskEngine, DISABLED_FLAG, IN_FLASHLOAN_FLAG},
+    state::{
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, RiskEngine, DISABLED_FLAG, IN_CPI_FLASHLOAN_FLAG,
+            IN_FLASHLOAN_FLAG,
+        },
+        marginfi_group::Bank,
+    },
 };
 
-pub fn lending_account_start_flashloan(
-    ctx: Context<LendingAccountStartFlashloan>,
+/// Start a flashloan, either in a top level instruction or through a CPI from a program
+/// whitelisted on the group. In both cases the flashloan must be ended by an `end_flashloan` top
+/// level instruction at `end_index`, so the account is always health checked before the end of
+/// the transaction.
+///
+/// The liability shares of the account are recorded, the flashloan fee is charged on their net
+/// increase when the flashloan ends.
+///
+/// Expected remaining account schema for CPI flashloans
+/// [
+///    marginfi_group_ai,
+///  ]
+pub fn lending_account_start_flashloan<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingAccountStartFlashloan<'info>>,
     end_index: u64,
 ) -> MarginfiResult<()> {
-    check_flashloan_can_start(
-        &ctx.accounts.marginfi_account,
-        &ctx.accounts.ixs_sysvar,
-        end_index as usize,
-    )?;
+    let is_cpi = get_stack_height() != TRANSACTION_LEVEL_STACK_HEIGHT;
+
+    if is_cpi {
+        check_cpi_flashloan_can_start(
+            &ctx.accounts.marginfi_account,
+            &ctx.accounts.ixs_sysvar,
+            end_index as usize,
+            ctx.remaining_accounts,
+        )?;
+    } else {
+        check_flashloan_can_start(
+            &ctx.accounts.marginfi_account,
+            &ctx.accounts.ixs_sysvar,
+            end_index as usize,
+        )?;
+    }
 
     let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
     marginfi_account.set_flag(IN_FLASHLOAN_FLAG);
+    if is_cpi {
+        marginfi_account.set_flag(IN_CPI_FLASHLOAN_FLAG);
+    }
+    marginfi_account.snapshot_flashloan_liabilities()?;
 
     Ok(())
 }
@@ -79,6 +114,29 @@ pub fn check_flashloan_can_start(
         "Start flashloan ix should not be in CPI"
     );
 
+    check_end_flashloan_ix(marginfi_account.key(), sysvar_ixs, end_fl_idx)?;
+
+    let marginf_account = marginfi_account.load()?;
+
+    check!(
+        !marginf_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+
+    check!(
+        !marginf_account.get_flag(IN_FLASHLOAN_FLAG),
+        MarginfiError::IllegalFlashloan
+    );
+
+    Ok(())
+}
+
+/// Check that the top level instruction at `end_fl_idx` ends the flashloan of `marginfi_account`.
+fn check_end_flashloan_ix(
+    marginfi_account: Pubkey,
+    sysvar_ixs: &AccountInfo,
+    end_fl_idx: usize,
+) -> MarginfiResult {
     // Will error if ix doesn't exist
     let unchecked_end_fl_ix = instructions::load_instruction_at_checked(end_fl_idx, sysvar_ixs)?;
 
@@ -101,25 +159,87 @@ pub fn check_flashloan_can_start(
         .ok_or(MarginfiError::IllegalFlashloan)?;
 
     check!(
-        end_fl_marginfi_account.pubkey.eq(&marginfi_account.key()),
+        end_fl_marginfi_account.pubkey.eq(&marginfi_account),
         MarginfiError::IllegalFlashloan
     );
 
-    let marginf_account = marginfi_account.load()?;
+    Ok(())
+}
 
+/// Checklist
+/// 1. Start flashloan ix is called directly by the top level instruction program
+/// 2. Top level instruction program is whitelisted on the account group
+/// 3. `end_flashloan` top level ix is present after the current top level ix
+/// 4. `end_flashloan` ix is for the marginfi program and the same marginfi account
+/// 5. Account is not disabled
+/// 6. Account is not already in a flashloan
+pub fn check_cpi_flashloan_can_start<'info>(
+    marginfi_account: &AccountLoader<MarginfiAccount>,
+    sysvar_ixs: &AccountInfo,
+    end_fl_idx: usize,
+    remaining_ais: &'info [AccountInfo<'info>],
+) -> MarginfiResult {
     check!(
-        !marginf_account.get_flag(DISABLED_FLAG),
+        get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT + 1,
+        MarginfiError::IllegalFlashloan,
+        "CPI flashloans must be started by the top level program"
+    );
+
+    let marginfi_account = marginfi_account.load()?;
+
+    check!(
+        !marginfi_account.get_flag(DISABLED_FLAG),
         MarginfiError::AccountDisabled
     );
 
     check!(
-        !marginf_account.get_flag(IN_FLASHLOAN_FLAG),
+        !marginfi_account.get_flag(IN_FLASHLOAN_FLAG),
+        MarginfiError::IllegalFlashloan
+    );
+
+    let marginfi_group_ai = remaining_ais
+        .first()
+        .ok_or(MarginfiError::IllegalFlashloan)?;
+
+    check!(
+        marginfi_group_ai.key.eq(&marginfi_account.group),
         MarginfiError::IllegalFlashloan
     );
 
+    let marginfi_group = AccountLoader::<MarginfiGroup>::try_from(marginfi_group_ai)?;
+
+    let current_ix_idx: usize = instructions::load_current_index_checked(sysvar_ixs)?.into();
+    let current_ix = instructions::load_instruction_at_checked(current_ix_idx, sysvar_ixs)?;
+
+    check!(
+        marginfi_group
+            .load()?
+            .is_flashloan_cpi_caller(&current_ix.program_id),
+        MarginfiError::IllegalFlashloan,
+        "CPI flashloan caller is not whitelisted"
+    );
+
+    // The caller can't end the flashloan within its own instruction, the transaction must end it
+    // so the health check can't be skipped
+    check!(current_ix_idx < end_fl_idx, MarginfiError::IllegalFlashloan);
+
+    check_end_flashloan_ix(marginfi_account.key(), sysvar_ixs, end_fl_idx)?;
+
     Ok(())
 }
 
+/// End a flashloan, charge the flashloan fee and check the account health.
+///
+/// The flashloan fee is charged on the net liability increase of each balance since the start of
+/// the flashloan, unless the account authority is exempt in the group. It is at least the
+/// origination fee, which borrows within the flashloan don't pay. Exempt accounts pass the group
+/// as first remaining account, followed by the health check accounts, and only pay the
+/// origination fee. Banks with a net liability increase and a non zero fee must be writable in
+/// the health check accounts.
+///
+/// Flashloans started through CPI can be ended by any signer, since the authority of the account
+/// is usually a PDA of the caller program. The end instruction was checked to be part of the
+/// transaction when the flashloan started, see `check_cpi_flashloan_can_start`.
 pub fn lending_account_end_flashloan<'info>(
     ctx: Context<'_, '_, 'info, 'info, LendingAccountEndFlashloan<'info>>,
 ) -> MarginfiResult<()> {
@@ -131,9 +251,101 @@ pub fn lending_account_end_flashloan<'info>(
 
     let mut marginfi_account = ctx.accounts.marginfi_account.load_mut()?;
 
-    marginfi_account.unset_flag(IN_FLASHLOAN_FLAG);
+    check!(
+        marginfi_account.get_flag(IN_FLASHLOAN_FLAG),
+        MarginfiError::IllegalFlashloan
+    );
 
-    RiskEngine::check_account_init_health(&marginfi_account, ctx.remaining_accounts)?;
+    let (is_fee_exempt, health_ais) =
+        check_flashloan_fee_exemption(&marginfi_account, ctx.remaining_accounts)?;
+
+    let header = AccountEventHeader {
+        signer: Some(ctx.accounts.signer.key()),
+        marginfi_account: ctx.accounts.marginfi_account.key(),
+        marginfi_account_authority: marginfi_account.authority,
+        marginfi_group: marginfi_account.group,
+    };
+
+    charge_flashloan_fees(&mut marginfi_account, health_ais, is_fee_exempt, header)?;
+
+    marginfi_account.clear_flashloan();
+
+    RiskEngine::check_account_init_health(&marginfi_account, health_ais)?;
+
+    Ok(())
+}
+
+/// Whether the account authority is exempt from the flashloan fee, if the group is passed as first
+/// remaining account, and the remaining health check accounts.
+fn check_flashloan_fee_exemption<'info>(
+    marginfi_account: &MarginfiAccount,
+    remaining_ais: &'info [AccountInfo<'info>],
+) -> MarginfiResult<(bool, &'info [AccountInfo<'info>])> {
+    match remaining_ais.split_first() {
+        Some((marginfi_group_ai, health_ais))
+            if marginfi_group_ai.key.eq(&marginfi_account.group) =>
+        {
+            let marginfi_group = AccountLoader::<MarginfiGroup>::try_from(marginfi_group_ai)?;
+            let is_fee_exempt = marginfi_group
+                .load()?
+                .is_flashloan_fee_exempt(&marginfi_account.authority);
+
+            Ok((is_fee_exempt, health_ais))
+        }
+        _ => Ok((false, remaining_ais)),
+    }
+}
+
+/// Charge the bank flashloan end fee on the net liability increase of each balance since the start
+/// of the flashloan, see `InterestRateConfig::get_flashloan_end_fee` and
+/// `MarginfiAccount::snapshot_flashloan_liabilities`.
+fn charge_flashloan_fees(
+    marginfi_account: &mut MarginfiAccount,
+    health_ais: &[AccountInfo],
+    is_fee_exempt: bool,
+    header: AccountEventHeader,
+) -> MarginfiResult {
+    for index in 0..marginfi_account.lending_account.balances.len() {
+        let liability_shares_increase =
+            marginfi_account.get_flashloan_liability_shares_increase(index)?;
+        if liability_shares_increase == I80F48::ZERO {
+            continue;
+        }
+
+        let bank_pk = marginfi_account.lending_account.balances[index].bank_pk;
+        let bank_ai = health_ais
+            .iter()
+            .find(|ai| *ai.key == bank_pk)
+            .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+        let bank_loader = AccountLoader::<Bank>::try_from(bank_ai)?;
+
+        // Only fee charging banks need to be writable
+        let (net_borrowed_amount, flashloan_fee) = {
+            let bank = bank_loader.load()?;
+            let net_borrowed_amount = bank.get_liability_amount(liability_shares_increase)?;
+            let flashloan_fee = bank
+                .config
+                .interest_rate_config
+                .get_flashloan_end_fee(net_borrowed_amount, is_fee_exempt)?;
+
+            (net_borrowed_amount, flashloan_fee)
+        };
+        if flashloan_fee == I80F48::ZERO {
+            continue;
+        }
+
+        let mut bank = bank_loader.load_mut()?;
+        BankAccountWrapper::find(&bank_pk, &mut bank, &mut marginfi_account.lending_account)?
+            .charge_borrow_fee(flashloan_fee)?;
+
+        emit!(LendingAccountFlashloanFeeEvent {
+            header: header.clone(),
+            bank: bank_pk,
+            mint: bank.mint,
+            net_borrowed_amount: net_borrowed_amount.to_num::<f64>(),
+            flashloan_fee: flashloan_fee.to_num::<f64>(),
+        });
+    }
 
     Ok(())
 }
@@ -142,6 +354,9 @@ pub fn lending_account_end_flashloan<'info>(
 pub struct LendingAccountEndFlashloan<'info> {
     #[account(mut)]
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
-    #[account(address = marginfi_account.load()?.authority)]
+    #[account(
+        constraint = marginfi_account.load()?.can_end_flashloan(signer.key)
+            @ MarginfiError::Unauthorized,
+    )]
     pub signer: Signer<'info>,
 }
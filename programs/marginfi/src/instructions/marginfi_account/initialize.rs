@@ -1,4 +1,5 @@
 use crate::{
+    constants::MARGINFI_ACCOUNT_SEED,
     events::{AccountEventHeader, MarginfiAccountCreateEvent},
     prelude::*,
     state::marginfi_account::MarginfiAccount,
@@ -48,3 +49,67 @@ pub struct MarginfiAccountInitialize<'info> {
 
     pub system_program: Program<'info, System>,
 }
+
+/// Initialize a marginfi account at the PDA derived from the group, the authority and
+/// `account_index`, see `find_marginfi_account_pda`.
+///
+/// Lets an authority hold several isolated accounts in a group without managing a keypair for
+/// each of them.
+pub fn initialize_account_pda(
+    ctx: Context<MarginfiAccountInitializePda>,
+    account_index: u16,
+) -> MarginfiResult {
+    let MarginfiAccountInitializePda {
+        authority,
+        marginfi_group,
+        marginfi_account: marginfi_account_loader,
+        ..
+    } = ctx.accounts;
+
+    let mut marginfi_account = marginfi_account_loader.load_init()?;
+
+    marginfi_account.initialize_pda(
+        marginfi_group.key(),
+        authority.key(),
+        account_index,
+        ctx.bumps.marginfi_account,
+    );
+
+    emit!(MarginfiAccountCreateEvent {
+        header: AccountEventHeader {
+            signer: Some(authority.key()),
+            marginfi_account: marginfi_account_loader.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        }
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(account_index: u16)]
+pub struct MarginfiAccountInitializePda<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        init,
+        payer = fee_payer,
+        space = 8 + std::mem::size_of::<MarginfiAccount>(),
+        seeds = [
+            MARGINFI_ACCOUNT_SEED.as_bytes(),
+            marginfi_group.key().as_ref(),
+            authority.key().as_ref(),
+            &account_index.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub fee_payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
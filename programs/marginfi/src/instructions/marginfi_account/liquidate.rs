@@ -69,8 +69,8 @@ use solana_program::sysvar::Sysvar;
 /// Expected remaining account schema
 /// [
 ///    liab_mint_ai (if token2022 mint),
-///    asset_oracle_ai,
-///    liab_oracle_ai,
+///    asset_oracle_ais...,
+///    liab_oracle_ais...,
 ///    liquidator_observation_ais...,
 ///    liquidatee_observation_ais...,
 ///  ]
@@ -119,7 +119,23 @@ pub fn lending_account_liquidate<'info>(
             ctx.accounts.liab_bank.key(),
         )?;
     }
-    let init_liquidatee_remaining_len = liquidatee_marginfi_account.get_remaining_accounts_len();
+    let asset_oracle_ais_len = ctx
+        .accounts
+        .asset_bank
+        .load()?
+        .config
+        .oracle_setup
+        .get_oracle_ais_len();
+    let liab_oracle_ais_len = ctx
+        .accounts
+        .liab_bank
+        .load()?
+        .config
+        .oracle_setup
+        .get_oracle_ais_len();
+
+    let init_liquidatee_remaining_len =
+        liquidatee_marginfi_account.get_remaining_accounts_len(ctx.remaining_accounts)?;
     let pre_liquidation_health = {
         let liquidatee_accounts_starting_pos =
             ctx.remaining_accounts.len() - init_liquidatee_remaining_len;
@@ -137,7 +153,7 @@ pub fn lending_account_liquidate<'info>(
 
         let mut asset_bank = ctx.accounts.asset_bank.load_mut()?;
         let asset_price = {
-            let oracle_ais = &ctx.remaining_accounts[0..1];
+            let oracle_ais = &ctx.remaining_accounts[0..asset_oracle_ais_len];
             let asset_pf = OraclePriceFeedAdapter::try_from_bank_config(
                 &asset_bank.config,
                 oracle_ais,
@@ -148,7 +164,8 @@ pub fn lending_account_liquidate<'info>(
 
         let mut liab_bank = ctx.accounts.liab_bank.load_mut()?;
         let liab_price = {
-            let oracle_ais = &ctx.remaining_accounts[1..2];
+            let oracle_ais = &ctx.remaining_accounts
+                [asset_oracle_ais_len..asset_oracle_ais_len + liab_oracle_ais_len];
             let liab_pf = OraclePriceFeedAdapter::try_from_bank_config(
                 &liab_bank.config,
                 oracle_ais,
@@ -342,8 +359,8 @@ pub fn lending_account_liquidate<'info>(
 
     let liquidatee_accounts_starting_pos =
         ctx.remaining_accounts.len() - init_liquidatee_remaining_len;
-    let liquidator_accounts_starting_pos =
-        liquidatee_accounts_starting_pos - liquidator_marginfi_account.get_remaining_accounts_len();
+    let liquidator_accounts_starting_pos = liquidatee_accounts_starting_pos
+        - liquidator_marginfi_account.get_remaining_accounts_len(ctx.remaining_accounts)?;
 
     let liquidatee_remaining_accounts = &ctx.remaining_accounts[liquidatee_accounts_starting_pos..];
     let liquidator_remaining_accounts =
@@ -1,23 +1,31 @@
+mod auto_deleverage;
 mod borrow;
 mod close;
 mod close_balance;
+mod conditional_order;
+mod delegate;
 mod deposit;
 mod emissions;
 mod flashloan;
 mod initialize;
 mod liquidate;
+mod recover_bad_debt;
 mod repay;
 mod transfer_authority;
 mod withdraw;
 
+pub use auto_deleverage::*;
 pub use borrow::*;
 pub use close::*;
 pub use close_balance::*;
+pub use conditional_order::*;
+pub use delegate::*;
 pub use deposit::*;
 pub use emissions::*;
 pub use flashloan::*;
 pub use initialize::*;
 pub use liquidate::*;
+pub use recover_bad_debt::*;
 pub use repay::*;
 pub use transfer_authority::*;
 pub use withdraw::*;
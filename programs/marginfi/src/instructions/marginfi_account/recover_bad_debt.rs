@@ -0,0 +1,136 @@
+use crate::{
+    check,
+    constants::INSURANCE_VAULT_SEED,
+    events::{AccountEventHeader, LendingAccountRecoverBadDebtEvent},
+    prelude::*,
+    state::{marginfi_account::MarginfiAccount, marginfi_group::Bank},
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenInterface;
+use fixed::types::I80F48;
+use solana_program::clock::Clock;
+use solana_program::sysvar::Sysvar;
+
+/// Repay bad debt written off for the account by a bankruptcy back to the bank insurance vault.
+///
+/// 1. Cap the repayment at the bad debt recorded for the bank
+/// 2. Transfer funds from the signer's token account to the bank's insurance vault
+/// 3. Reduce the bad debt record by the amount received, clearing it once fully repaid
+///
+/// Recovery is optional and does not affect the account health. It is allowed on disabled
+/// accounts, which bankrupt accounts are.
+pub fn lending_account_recover_bad_debt<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingAccountRecoverBadDebt<'info>>,
+    amount: u64,
+) -> MarginfiResult {
+    let LendingAccountRecoverBadDebt {
+        marginfi_account: marginfi_account_loader,
+        signer,
+        signer_token_account,
+        bank_insurance_vault,
+        token_program,
+        bank: bank_loader,
+        ..
+    } = ctx.accounts;
+    let clock = Clock::get()?;
+    let maybe_bank_mint = utils::maybe_take_bank_mint(
+        &mut ctx.remaining_accounts,
+        &*bank_loader.load()?,
+        token_program.key,
+    )?;
+
+    let mut bank = bank_loader.load_mut()?;
+    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+
+    let recoverable_bad_debt = marginfi_account.get_recoverable_bad_debt(&bank_loader.key())?;
+
+    check!(
+        recoverable_bad_debt > 0,
+        MarginfiError::BadDebtRecordNotFound
+    );
+
+    let amount = amount.min(recoverable_bad_debt);
+
+    let amount_received = maybe_bank_mint
+        .as_ref()
+        .map(|mint| {
+            utils::calculate_post_fee_spl_deposit_amount(
+                mint.to_account_info(),
+                amount,
+                clock.epoch,
+            )
+        })
+        .transpose()?
+        .unwrap_or(amount);
+
+    let remaining_bad_debt = marginfi_account.recover_bad_debt(
+        &mut bank,
+        &bank_loader.key(),
+        I80F48::from_num(amount_received),
+    )?;
+
+    bank.insurance_deposit_spl_transfer(
+        amount,
+        signer_token_account.to_account_info(),
+        bank_insurance_vault.to_account_info(),
+        signer.to_account_info(),
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(LendingAccountRecoverBadDebtEvent {
+        header: AccountEventHeader {
+            signer: Some(signer.key()),
+            marginfi_account: marginfi_account_loader.key(),
+            marginfi_account_authority: marginfi_account.authority,
+            marginfi_group: marginfi_account.group,
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        amount,
+        remaining_bad_debt: remaining_bad_debt.max(I80F48::ZERO).to_num::<f64>(),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingAccountRecoverBadDebt<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = marginfi_account.load()?.group == marginfi_group.key(),
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+
+    #[account(
+        address = marginfi_account.load()?.authority,
+    )]
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    /// CHECK: Token mint/authority are checked at transfer
+    #[account(mut)]
+    pub signer_token_account: AccountInfo<'info>,
+
+    /// CHECK: Seed constraint check
+    #[account(
+        mut,
+        seeds = [
+            INSURANCE_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_bump,
+    )]
+    pub bank_insurance_vault: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -4,7 +4,9 @@ use crate::{
     events::{AccountEventHeader, LendingAccountRepayEvent},
     prelude::{MarginfiError, MarginfiGroup, MarginfiResult},
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, DISABLED_FLAG},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, DELEGATE_PERMISSION_REPAY, DISABLED_FLAG,
+        },
         marginfi_group::Bank,
     },
     utils,
@@ -119,7 +121,11 @@ pub struct LendingAccountRepay<'info> {
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
     #[account(
-        address = marginfi_account.load()?.authority,
+        constraint = marginfi_account.load()?.can_act(
+            signer.key,
+            DELEGATE_PERMISSION_REPAY,
+            Clock::get()?.unix_timestamp,
+        ) @ MarginfiError::Unauthorized,
     )]
     pub signer: Signer<'info>,
 
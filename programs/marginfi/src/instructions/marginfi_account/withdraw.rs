@@ -4,7 +4,10 @@ use crate::{
     events::{AccountEventHeader, LendingAccountWithdrawEvent},
     prelude::*,
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, RiskEngine, DISABLED_FLAG},
+        marginfi_account::{
+            BankAccountWrapper, MarginfiAccount, RiskEngine, DELEGATE_PERMISSION_WITHDRAW,
+            DISABLED_FLAG,
+        },
         marginfi_group::{Bank, BankVaultType},
     },
     utils,
@@ -21,6 +24,8 @@ use solana_program::{clock::Clock, sysvar::Sysvar};
 /// 5. Verify that the user account is in a healthy state
 ///
 /// Will error if there is no existing asset <=> borrowing is not allowed.
+///
+/// An account delegate can only withdraw to token accounts of the account authority.
 pub fn lending_account_withdraw<'info>(
     mut ctx: Context<'_, '_, 'info, 'info, LendingAccountWithdraw<'info>>,
     amount: u64,
@@ -44,6 +49,11 @@ pub fn lending_account_withdraw<'info>(
         !marginfi_account.get_flag(DISABLED_FLAG),
         MarginfiError::AccountDisabled
     );
+    check!(
+        ctx.accounts.signer.key() == marginfi_account.authority
+            || destination_token_account.owner == marginfi_account.authority,
+        MarginfiError::Unauthorized
+    );
 
     let maybe_bank_mint = utils::maybe_take_bank_mint(
         &mut ctx.remaining_accounts,
@@ -135,7 +145,11 @@ pub struct LendingAccountWithdraw<'info> {
     pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
 
     #[account(
-        address = marginfi_account.load()?.authority,
+        constraint = marginfi_account.load()?.can_act(
+            signer.key,
+            DELEGATE_PERMISSION_WITHDRAW,
+            Clock::get()?.unix_timestamp,
+        ) @ MarginfiError::Unauthorized,
     )]
     pub signer: Signer<'info>,
 
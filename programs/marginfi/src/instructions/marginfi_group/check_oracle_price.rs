@@ -0,0 +1,37 @@
+use crate::{
+    prelude::*,
+    state::{
+        marginfi_group::{Bank, WrappedI80F48},
+        price::{OraclePriceFeedAdapter, OraclePriceType, PriceAdapter},
+    },
+};
+use anchor_lang::prelude::*;
+
+/// Load the oracle price of a bank and check it against the bank price band.
+///
+/// Returns the real time price, fails with `OraclePriceOutOfBand` if the price is
+/// outside of the configured band.
+///
+/// Expected remaining account schema
+/// [
+///    oracle_ais...,
+///  ]
+pub fn lending_pool_check_oracle_price<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingPoolCheckOraclePrice<'info>>,
+) -> MarginfiResult<WrappedI80F48> {
+    let bank = ctx.accounts.bank.load()?;
+    let clock = Clock::get()?;
+
+    let price =
+        OraclePriceFeedAdapter::try_from_bank_config(&bank.config, ctx.remaining_accounts, &clock)?
+            .get_price_of_type(OraclePriceType::RealTime, None)?;
+
+    msg!("Bank {} oracle price {}", ctx.accounts.bank.key(), price);
+
+    Ok(price.into())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolCheckOraclePrice<'info> {
+    pub bank: AccountLoader<'info, Bank>,
+}
@@ -1,24 +1,47 @@
 use crate::constants::{FEE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_AUTHORITY_SEED};
-use crate::events::{GroupEventHeader, LendingPoolBankCollectFeesEvent};
+use crate::events::{
+    GroupEventHeader, LendingPoolBankCollectFeesAsDepositEvent, LendingPoolBankCollectFeesEvent,
+    LendingPoolBankDistributeFeesEvent,
+};
 use crate::utils;
 use crate::{
-    bank_signer,
+    bank_signer, check,
     constants::{
         FEE_VAULT_SEED, INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED,
     },
     math_error,
-    state::marginfi_group::{Bank, BankVaultType, MarginfiGroup},
+    prelude::MarginfiError,
+    state::{
+        marginfi_account::{BankAccountWrapper, MarginfiAccount, DISABLED_FLAG, IN_FLASHLOAN_FLAG},
+        marginfi_group::{
+            Bank, BankVaultType, MarginfiGroup, FEE_DESTINATION_INSURANCE_VAULT,
+            MAX_FEE_DESTINATIONS,
+        },
+    },
     MarginfiResult,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 use fixed::types::I80F48;
 use std::cmp::min;
 
+/// Move the outstanding fees of a bank from the liquidity vault to the insurance and fee vaults,
+/// up to the liquidity vault balance.
+///
+/// If the group has a fee distribution, the group fees are split between the group fee
+/// destinations as in `lending_pool_distribute_bank_fees`, and only the rounding dust goes to the
+/// fee vault.
+///
+/// Expected remaining account schema
+/// [
+///    bank_mint_ai (if token2022 mint),
+///    destination_token_account_ais... (with a fee distribution, see `lending_pool_distribute_bank_fees`),
+///  ]
 pub fn lending_pool_collect_bank_fees<'info>(
     mut ctx: Context<'_, '_, 'info, 'info, LendingPoolCollectBankFees<'info>>,
 ) -> MarginfiResult {
     let LendingPoolCollectBankFees {
+        marginfi_group: marginfi_group_loader,
         liquidity_vault_authority,
         insurance_vault,
         fee_vault,
@@ -27,6 +50,7 @@ pub fn lending_pool_collect_bank_fees<'info>(
         ..
     } = ctx.accounts;
 
+    let marginfi_group = marginfi_group_loader.load()?;
     let mut bank = ctx.accounts.bank.load_mut()?;
     let maybe_bank_mint =
         utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
@@ -71,56 +95,183 @@ pub fn lending_pool_collect_bank_fees<'info>(
 
     bank.collected_group_fees_outstanding = new_outstanding_group_fees.into();
 
+    let bank_pk = ctx.accounts.bank.key();
+    let liquidity_vault_authority_bump = bank.liquidity_vault_authority_bump;
+
+    // Insurance fees go first, so that group fees sent to the insurance vault are priced in
+    // insurance shares after them
     bank.withdraw_spl_transfer(
-        group_fee_transfer_amount
+        insurance_fee_transfer_amount
             .checked_to_num()
             .ok_or_else(math_error!())?,
         liquidity_vault.to_account_info(),
-        fee_vault.to_account_info(),
+        insurance_vault.to_account_info(),
         liquidity_vault_authority.to_account_info(),
         maybe_bank_mint.as_ref(),
         token_program.to_account_info(),
         bank_signer!(
             BankVaultType::Liquidity,
-            ctx.accounts.bank.key(),
-            bank.liquidity_vault_authority_bump
+            bank_pk,
+            liquidity_vault_authority_bump
         ),
         ctx.remaining_accounts,
     )?;
 
+    let group_fee_transfer_amount: u64 = group_fee_transfer_amount
+        .checked_to_num()
+        .ok_or_else(math_error!())?;
+
+    let mut fee_vault_transfer_amount = group_fee_transfer_amount;
+
+    if marginfi_group.is_fee_distribution_configured() {
+        let amounts = distribute_group_fees(
+            &marginfi_group,
+            &mut bank,
+            group_fee_transfer_amount,
+            liquidity_vault.to_account_info(),
+            liquidity_vault_authority.to_account_info(),
+            bank_signer!(
+                BankVaultType::Liquidity,
+                bank_pk,
+                liquidity_vault_authority_bump
+            ),
+            insurance_vault,
+            &mut ctx.remaining_accounts,
+            maybe_bank_mint.as_ref(),
+            token_program.to_account_info(),
+        )?;
+
+        fee_vault_transfer_amount = fee_vault_transfer_amount
+            .checked_sub(amounts.iter().sum())
+            .ok_or_else(math_error!())?;
+
+        emit!(LendingPoolBankDistributeFeesEvent {
+            header: GroupEventHeader {
+                marginfi_group: marginfi_group_loader.key(),
+                signer: None
+            },
+            bank: bank_pk,
+            mint: bank.mint,
+            amounts,
+        });
+    }
+
     bank.withdraw_spl_transfer(
-        insurance_fee_transfer_amount
-            .checked_to_num()
-            .ok_or_else(math_error!())?,
+        fee_vault_transfer_amount,
         liquidity_vault.to_account_info(),
-        insurance_vault.to_account_info(),
+        fee_vault.to_account_info(),
         liquidity_vault_authority.to_account_info(),
         maybe_bank_mint.as_ref(),
         token_program.to_account_info(),
         bank_signer!(
             BankVaultType::Liquidity,
-            ctx.accounts.bank.key(),
-            bank.liquidity_vault_authority_bump
+            bank_pk,
+            liquidity_vault_authority_bump
         ),
         ctx.remaining_accounts,
     )?;
 
     emit!(LendingPoolBankCollectFeesEvent {
         header: GroupEventHeader {
-            marginfi_group: ctx.accounts.marginfi_group.key(),
+            marginfi_group: marginfi_group_loader.key(),
             signer: None
         },
-        bank: ctx.accounts.bank.key(),
+        bank: bank_pk,
         mint: liquidity_vault.mint,
         insurance_fees_collected: insurance_fee_transfer_amount.to_num::<f64>(),
         insurance_fees_outstanding: new_outstanding_insurance_fees.to_num::<f64>(),
-        group_fees_collected: group_fee_transfer_amount.to_num::<f64>(),
+        group_fees_collected: group_fee_transfer_amount as f64,
         group_fees_outstanding: new_outstanding_group_fees.to_num::<f64>(),
     });
 
     Ok(())
 }
 
+/// Split `amount` of `source_vault` between the group fee destinations, returning the amount sent
+/// to each destination.
+///
+/// Token account destinations are taken from `destination_ais` in order. Fees sent to the insurance
+/// vault are booked as protocol owned insurance shares, see `Bank::deposit_protocol_insurance`.
+#[allow(clippy::too_many_arguments)]
+fn distribute_group_fees<'info>(
+    marginfi_group: &MarginfiGroup,
+    bank: &mut Bank,
+    amount: u64,
+    source_vault: AccountInfo<'info>,
+    source_vault_authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    insurance_vault: &AccountInfo<'info>,
+    destination_ais: &mut &'info [AccountInfo<'info>],
+    maybe_bank_mint: Option<&InterfaceAccount<'info, Mint>>,
+    token_program: AccountInfo<'info>,
+) -> MarginfiResult<[u64; MAX_FEE_DESTINATIONS]> {
+    let amounts = marginfi_group.get_fee_distribution_amounts(amount)?;
+
+    for (destination, destination_amount) in marginfi_group.fee_distribution.iter().zip(amounts) {
+        if destination.is_empty() {
+            continue;
+        }
+
+        let destination_ai = match destination.destination_type {
+            FEE_DESTINATION_INSURANCE_VAULT => insurance_vault.clone(),
+            _ => {
+                check!(
+                    !destination_ais.is_empty(),
+                    MarginfiError::InvalidFeeDestination
+                );
+                let (destination_ai, remaining) = destination_ais.split_at(1);
+                *destination_ais = remaining;
+
+                let destination_token_account =
+                    InterfaceAccount::<TokenAccount>::try_from(&destination_ai[0])?;
+                check!(
+                    destination_token_account.owner == destination.owner
+                        && destination_token_account.mint == bank.mint,
+                    MarginfiError::InvalidFeeDestination
+                );
+
+                destination_ai[0].clone()
+            }
+        };
+
+        if destination_amount == 0 {
+            continue;
+        }
+
+        let insurance_vault_amount = match destination.destination_type {
+            FEE_DESTINATION_INSURANCE_VAULT => {
+                Some(InterfaceAccount::<TokenAccount>::try_from(insurance_vault)?.amount)
+            }
+            _ => None,
+        };
+
+        bank.withdraw_spl_transfer(
+            destination_amount,
+            source_vault.clone(),
+            destination_ai,
+            source_vault_authority.clone(),
+            maybe_bank_mint,
+            token_program.clone(),
+            signer_seeds,
+            *destination_ais,
+        )?;
+
+        if let Some(pre_transfer_amount) = insurance_vault_amount {
+            let post_transfer_amount =
+                InterfaceAccount::<TokenAccount>::try_from(insurance_vault)?.amount;
+
+            bank.deposit_protocol_insurance(
+                post_transfer_amount
+                    .checked_sub(pre_transfer_amount)
+                    .ok_or_else(math_error!())?,
+                pre_transfer_amount,
+            )?;
+        }
+    }
+
+    Ok(amounts)
+}
+
 #[derive(Accounts)]
 pub struct LendingPoolCollectBankFees<'info> {
     pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
@@ -254,6 +405,231 @@ pub struct LendingPoolWithdrawFees<'info> {
     pub token_program: Interface<'info, TokenInterface>,
 }
 
+/// Book the outstanding group fees of a bank as a deposit of the group fee marginfi account,
+/// instead of transferring them to the fee vault.
+///
+/// Permissionless, and not capped by the liquidity vault balance, so fees can be collected at any
+/// utilization. The deposit earns interest and can be withdrawn by the fee account authority like
+/// any other deposit.
+///
+/// If the fee account has no balance in the bank and all of its balance slots are taken, the fees
+/// stay outstanding and can still be collected with `lending_pool_collect_bank_fees`.
+pub fn lending_pool_collect_bank_fees_as_deposit(
+    ctx: Context<LendingPoolCollectBankFeesAsDeposit>,
+) -> MarginfiResult {
+    let LendingPoolCollectBankFeesAsDeposit {
+        marginfi_group: marginfi_group_loader,
+        bank: bank_loader,
+        fee_marginfi_account: fee_marginfi_account_loader,
+    } = ctx.accounts;
+
+    let marginfi_group = marginfi_group_loader.load()?;
+
+    check!(
+        marginfi_group.is_fee_marginfi_account_configured(),
+        MarginfiError::FeeMarginfiAccountNotConfigured
+    );
+    check!(
+        fee_marginfi_account_loader.key() == marginfi_group.fee_marginfi_account,
+        MarginfiError::FeeMarginfiAccountNotConfigured
+    );
+
+    let mut bank = bank_loader.load_mut()?;
+    let mut fee_marginfi_account = fee_marginfi_account_loader.load_mut()?;
+
+    let mut group_fees_deposited = I80F48::ZERO;
+
+    if can_book_group_fees(&fee_marginfi_account, &bank_loader.key())? {
+        group_fees_deposited = bank.collected_group_fees_outstanding.into();
+    } else {
+        msg!("Fee marginfi account balance slots full, group fees stay outstanding");
+    }
+
+    if group_fees_deposited > I80F48::ZERO {
+        BankAccountWrapper::find_or_create(
+            &bank_loader.key(),
+            &mut bank,
+            &mut fee_marginfi_account.lending_account,
+        )?
+        .increase_balance_in_liquidation(group_fees_deposited)?;
+
+        bank.collected_group_fees_outstanding = I80F48::ZERO.into();
+    }
+
+    emit!(LendingPoolBankCollectFeesAsDepositEvent {
+        header: GroupEventHeader {
+            marginfi_group: marginfi_group_loader.key(),
+            signer: None
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        fee_marginfi_account: fee_marginfi_account_loader.key(),
+        group_fees_deposited: group_fees_deposited.to_num::<f64>(),
+    });
+
+    Ok(())
+}
+
+/// Whether group fees of `bank_pk` can be booked on the fee marginfi account, which needs a
+/// balance in the bank or a free balance slot.
+fn can_book_group_fees(
+    fee_marginfi_account: &MarginfiAccount,
+    bank_pk: &Pubkey,
+) -> MarginfiResult<bool> {
+    check!(
+        !fee_marginfi_account.get_flag(DISABLED_FLAG),
+        MarginfiError::AccountDisabled
+    );
+    check!(
+        !fee_marginfi_account.get_flag(IN_FLASHLOAN_FLAG),
+        MarginfiError::AccountInFlashloan
+    );
+
+    let lending_account = &fee_marginfi_account.lending_account;
+
+    Ok(lending_account
+        .balances
+        .iter()
+        .any(|balance| balance.active && balance.bank_pk == *bank_pk)
+        || lending_account.get_first_empty_balance().is_some())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolCollectBankFeesAsDeposit<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        constraint = fee_marginfi_account.load()?.group == marginfi_group.key(),
+    )]
+    pub fee_marginfi_account: AccountLoader<'info, MarginfiAccount>,
+}
+
+/// Split the fee vault balance of a bank between the group fee destinations.
+///
+/// Permissionless. `lending_pool_collect_bank_fees` already splits newly collected group fees, this
+/// distributes what is left in the fee vault, e.g. fees collected before the distribution was
+/// configured.
+///
+/// Shares are rounded down, the remaining dust stays in the fee vault until the next distribution.
+///
+/// Fees sent to the insurance vault are owned by the protocol and don't accrue to insurance
+/// stakers, see `Bank::deposit_protocol_insurance`.
+///
+/// Expected remaining account schema
+/// [
+///    bank_mint_ai (if token2022 mint),
+///    destination_token_account_ais... (one per `FEE_DESTINATION_TOKEN_ACCOUNT` destination, in order),
+///  ]
+pub fn lending_pool_distribute_bank_fees<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingPoolDistributeBankFees<'info>>,
+) -> MarginfiResult {
+    let LendingPoolDistributeBankFees {
+        marginfi_group: marginfi_group_loader,
+        bank: bank_loader,
+        fee_vault,
+        fee_vault_authority,
+        insurance_vault,
+        token_program,
+    } = ctx.accounts;
+
+    let marginfi_group = marginfi_group_loader.load()?;
+    let mut bank = bank_loader.load_mut()?;
+
+    check!(
+        marginfi_group.is_fee_distribution_configured(),
+        MarginfiError::FeeDistributionNotConfigured
+    );
+
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
+
+    let fee_vault_authority_bump = bank.fee_vault_authority_bump;
+
+    let amounts = distribute_group_fees(
+        &marginfi_group,
+        &mut bank,
+        fee_vault.amount,
+        fee_vault.to_account_info(),
+        fee_vault_authority.to_account_info(),
+        bank_signer!(
+            BankVaultType::Fee,
+            bank_loader.key(),
+            fee_vault_authority_bump
+        ),
+        insurance_vault,
+        &mut ctx.remaining_accounts,
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+    )?;
+
+    emit!(LendingPoolBankDistributeFeesEvent {
+        header: GroupEventHeader {
+            marginfi_group: marginfi_group_loader.key(),
+            signer: None
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        amounts,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolDistributeBankFees<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        seeds = [
+            FEE_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.fee_vault_bump
+    )]
+    pub fee_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
+    #[account(
+        seeds = [
+            FEE_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.fee_vault_authority_bump
+    )]
+    pub fee_vault_authority: AccountInfo<'info>,
+
+    /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
+    #[account(
+        mut,
+        seeds = [
+            INSURANCE_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_bump
+    )]
+    pub insurance_vault: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Withdraw funds from the bank insurance vault.
+///
+/// Once insurance staking is initialized only the value of the protocol owned insurance shares
+/// can be withdrawn.
 pub fn lending_pool_withdraw_insurance<'info>(
     mut ctx: Context<'_, '_, 'info, 'info, LendingPoolWithdrawInsurance<'info>>,
     amount: u64,
@@ -267,10 +643,13 @@ pub fn lending_pool_withdraw_insurance<'info>(
         ..
     } = ctx.accounts;
 
-    let bank = bank_loader.load()?;
+    let mut bank = bank_loader.load_mut()?;
     let maybe_bank_mint =
         utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
 
+    // Only the protocol owned part of the insurance vault can be withdrawn once stakers joined
+    bank.withdraw_protocol_insurance(amount, insurance_vault.amount)?;
+
     bank.withdraw_spl_transfer(
         amount,
         insurance_vault.to_account_info(),
@@ -294,6 +673,7 @@ pub struct LendingPoolWithdrawInsurance<'info> {
     pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
 
     #[account(
+        mut,
         constraint = bank.load()?.group == marginfi_group.key(),
     )]
     pub bank: AccountLoader<'info, Bank>,
@@ -303,7 +683,6 @@ pub struct LendingPoolWithdrawInsurance<'info> {
     )]
     pub admin: Signer<'info>,
 
-    /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
     #[account(
         mut,
         seeds = [
@@ -312,7 +691,7 @@ pub struct LendingPoolWithdrawInsurance<'info> {
         ],
         bump = bank.load()?.insurance_vault_bump
     )]
-    pub insurance_vault: AccountInfo<'info>,
+    pub insurance_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
     #[account(
@@ -330,3 +709,44 @@ pub struct LendingPoolWithdrawInsurance<'info> {
 
     pub token_program: Interface<'info, TokenInterface>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::marginfi_account::Balance;
+
+    #[test]
+    fn group_fees_booking() {
+        let bank_pk = Pubkey::new_unique();
+        let mut fee_marginfi_account = MarginfiAccount::default();
+
+        assert!(can_book_group_fees(&fee_marginfi_account, &bank_pk).unwrap());
+
+        // All balance slots taken by other banks
+        for balance in fee_marginfi_account.lending_account.balances.iter_mut() {
+            *balance = Balance {
+                active: true,
+                bank_pk: Pubkey::new_unique(),
+                ..Balance::empty_deactivated()
+            };
+        }
+        assert!(!can_book_group_fees(&fee_marginfi_account, &bank_pk).unwrap());
+
+        // An existing balance in the bank is reused
+        fee_marginfi_account.lending_account.balances[3].bank_pk = bank_pk;
+        assert!(can_book_group_fees(&fee_marginfi_account, &bank_pk).unwrap());
+
+        fee_marginfi_account.set_flag(IN_FLASHLOAN_FLAG);
+        assert_eq!(
+            can_book_group_fees(&fee_marginfi_account, &bank_pk).unwrap_err(),
+            MarginfiError::AccountInFlashloan.into()
+        );
+
+        fee_marginfi_account.unset_flag(IN_FLASHLOAN_FLAG);
+        fee_marginfi_account.set_flag(DISABLED_FLAG);
+        assert_eq!(
+            can_book_group_fees(&fee_marginfi_account, &bank_pk).unwrap_err(),
+            MarginfiError::AccountDisabled.into()
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use crate::constants::{EMISSIONS_AUTH_SEED, EMISSIONS_TOKEN_ACCOUNT_SEED};
-use crate::events::{GroupEventHeader, LendingPoolBankConfigureEvent};
+use crate::events::{
+    GroupEventHeader, LendingPoolBankConfigureEvent, LendingPoolBankReclaimEmissionsEvent,
+};
 use crate::prelude::MarginfiError;
 use crate::{check, math_error, utils};
 use crate::{
@@ -20,7 +22,8 @@ pub fn lending_pool_configure_bank(
     bank.configure(&bank_config)?;
 
     if bank_config.oracle.is_some() {
-        bank.config.validate_oracle_setup(ctx.remaining_accounts)?;
+        bank.config
+            .validate_oracle_setup(ctx.remaining_accounts, &bank.mint)?;
     }
 
     emit!(LendingPoolBankConfigureEvent {
@@ -67,6 +70,9 @@ pub fn lending_pool_setup_emissions(
 
     bank.emissions_mint = ctx.accounts.emissions_mint.key();
 
+    // Emissions accrue into the bank emissions index from now on
+    bank.activate_emissions_index(Clock::get()?.unix_timestamp);
+
     bank.override_emissions_flag(emissions_flags);
 
     bank.emissions_rate = emissions_rate;
@@ -164,12 +170,18 @@ pub fn lending_pool_update_emissions_parameters(
         MarginfiError::EmissionsUpdateError
     );
 
+    // Distribute the emissions accrued under the previous parameters
+    bank.update_emissions_index(Clock::get()?.unix_timestamp)?;
+
     if let Some(flags) = emissions_flags {
         msg!("Updating emissions flags to {:#010b}", flags);
         bank.flags = flags;
     }
 
     if let Some(rate) = emissions_rate {
+        // A new rate is in tokens per second, banks still on legacy emissions move to the index
+        bank.activate_emissions_index(Clock::get()?.unix_timestamp);
+
         msg!("Updating emissions rate to {}", rate);
         bank.emissions_rate = rate;
     }
@@ -245,3 +257,167 @@ pub struct LendingPoolUpdateEmissionsParameters<'info> {
 
     pub token_program: Interface<'info, TokenInterface>,
 }
+
+/// Schedule the distribution of the bank emissions, a time of 0 leaves that side open.
+///
+/// Admin only
+pub fn lending_pool_update_emissions_schedule(
+    ctx: Context<LendingPoolUpdateEmissionsSchedule>,
+    start_time: i64,
+    end_time: i64,
+) -> MarginfiResult {
+    let mut bank = ctx.accounts.bank.load_mut()?;
+
+    Bank::validate_emissions_schedule(start_time, end_time)?;
+
+    // Distribute the emissions accrued under the previous schedule
+    bank.update_emissions_index(Clock::get()?.unix_timestamp)?;
+
+    msg!(
+        "Updating emissions schedule to start {} end {}",
+        start_time,
+        end_time
+    );
+
+    bank.emissions_start_time = start_time;
+    bank.emissions_end_time = end_time;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolUpdateEmissionsSchedule<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        address = marginfi_group.load()?.admin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+}
+
+/// Withdraw the emissions that were not distributed by the end of the emissions schedule.
+///
+/// Admin only
+///
+/// Distributed emissions stay in the vault for users to claim, only `emissions_remaining` can be
+/// reclaimed.
+pub fn lending_pool_reclaim_emissions(ctx: Context<LendingPoolReclaimEmissions>) -> MarginfiResult {
+    let mut bank = ctx.accounts.bank.load_mut()?;
+    let current_timestamp = Clock::get()?.unix_timestamp;
+
+    bank.update_emissions_index(current_timestamp)?;
+
+    // Legacy emissions accrue per balance and are only taken out of `emissions_remaining` at claim
+    check!(
+        bank.is_emissions_index_active() && bank.is_emissions_ended(current_timestamp),
+        MarginfiError::EmissionsNotEnded
+    );
+
+    let emissions_remaining = I80F48::from(bank.emissions_remaining);
+    let reclaim_amount = emissions_remaining
+        .checked_floor()
+        .ok_or_else(math_error!())?
+        .checked_to_num::<u64>()
+        .ok_or_else(math_error!())?
+        .min(ctx.accounts.emissions_token_account.amount);
+
+    bank.emissions_remaining = emissions_remaining
+        .checked_sub(I80F48::from_num(reclaim_amount))
+        .ok_or_else(math_error!())?
+        .into();
+
+    if reclaim_amount > 0 {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            EMISSIONS_AUTH_SEED.as_bytes(),
+            &ctx.accounts.bank.key().to_bytes(),
+            &ctx.accounts.emissions_mint.key().to_bytes(),
+            &[ctx.bumps.emissions_auth],
+        ]];
+
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.emissions_token_account.to_account_info(),
+                    to: ctx.accounts.destination_account.to_account_info(),
+                    authority: ctx.accounts.emissions_auth.to_account_info(),
+                    mint: ctx.accounts.emissions_mint.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            reclaim_amount,
+            ctx.accounts.emissions_mint.decimals,
+        )?;
+    }
+
+    emit!(LendingPoolBankReclaimEmissionsEvent {
+        header: GroupEventHeader {
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        bank: ctx.accounts.bank.key(),
+        emissions_mint: ctx.accounts.emissions_mint.key(),
+        amount: reclaim_amount,
+        outstanding_user_emissions: ctx
+            .accounts
+            .emissions_token_account
+            .amount
+            .saturating_sub(reclaim_amount),
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolReclaimEmissions<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        address = marginfi_group.load()?.admin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        address = bank.load()?.emissions_mint,
+    )]
+    pub emissions_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [
+            EMISSIONS_AUTH_SEED.as_bytes(),
+            bank.key().as_ref(),
+            emissions_mint.key().as_ref(),
+        ],
+        bump
+    )]
+    /// CHECK: Asserted by PDA constraints
+    pub emissions_auth: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            EMISSIONS_TOKEN_ACCOUNT_SEED.as_bytes(),
+            bank.key().as_ref(),
+            emissions_mint.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub emissions_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub destination_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,242 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+use fixed::types::I80F48;
+
+use crate::{
+    constants::{EMISSIONS_CAMPAIGN_AUTH_SEED, EMISSIONS_CAMPAIGN_VAULT_SEED},
+    events::{
+        GroupEventHeader, LendingPoolBankCreateEmissionsCampaignEvent,
+        LendingPoolBankReclaimCampaignEmissionsEvent,
+    },
+    prelude::MarginfiResult,
+    state::{
+        emissions_campaign::EmissionsCampaign,
+        marginfi_group::{Bank, MarginfiGroup},
+    },
+    utils,
+};
+
+/// Create and fund an emissions campaign on a bank.
+///
+/// Permissionless, the campaign authority is the signer funding the campaign. Any number of
+/// campaigns can run on the same bank, next to the bank's own emissions.
+pub fn lending_pool_create_emissions_campaign(
+    ctx: Context<LendingPoolCreateEmissionsCampaign>,
+    emissions_flag: u64,
+    emissions_rate: u64,
+    total_emissions: u64,
+    start_time: i64,
+    end_time: i64,
+) -> MarginfiResult {
+    let clock = Clock::get()?;
+
+    EmissionsCampaign::validate_schedule(
+        emissions_flag,
+        start_time,
+        end_time,
+        clock.unix_timestamp,
+    )?;
+
+    let mut campaign = ctx.accounts.campaign.load_init()?;
+
+    *campaign = EmissionsCampaign {
+        group: ctx.accounts.marginfi_group.key(),
+        bank: ctx.accounts.bank.key(),
+        authority: ctx.accounts.authority.key(),
+        emissions_mint: ctx.accounts.emissions_mint.key(),
+        emissions_flag,
+        emissions_rate,
+        start_time,
+        end_time,
+        emissions_remaining: I80F48::from_num(total_emissions).into(),
+        vault_bump: ctx.bumps.emissions_vault,
+        vault_authority_bump: ctx.bumps.emissions_auth,
+        _pad0: [0; 6],
+        _padding: [0; 16],
+    };
+
+    let total_emissions_pre_fee = utils::calculate_pre_fee_spl_deposit_amount(
+        ctx.accounts.emissions_mint.to_account_info(),
+        total_emissions,
+        clock.epoch,
+    )?;
+
+    transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.emissions_funding_account.to_account_info(),
+                to: ctx.accounts.emissions_vault.to_account_info(),
+                authority: ctx.accounts.authority.to_account_info(),
+                mint: ctx.accounts.emissions_mint.to_account_info(),
+            },
+        ),
+        total_emissions_pre_fee,
+        ctx.accounts.emissions_mint.decimals,
+    )?;
+
+    emit!(LendingPoolBankCreateEmissionsCampaignEvent {
+        header: GroupEventHeader {
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+            signer: Some(ctx.accounts.authority.key()),
+        },
+        bank: ctx.accounts.bank.key(),
+        campaign: ctx.accounts.campaign.key(),
+        emissions_mint: ctx.accounts.emissions_mint.key(),
+        emissions_flag,
+        emissions_rate,
+        total_emissions,
+        start_time,
+        end_time,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolCreateEmissionsCampaign<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        init,
+        space = 8 + std::mem::size_of::<EmissionsCampaign>(),
+        payer = authority,
+    )]
+    pub campaign: AccountLoader<'info, EmissionsCampaign>,
+
+    pub emissions_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        seeds = [
+            EMISSIONS_CAMPAIGN_AUTH_SEED.as_bytes(),
+            campaign.key().as_ref(),
+        ],
+        bump
+    )]
+    /// CHECK: Asserted by PDA constraints
+    pub emissions_auth: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = emissions_mint,
+        token::authority = emissions_auth,
+        seeds = [
+            EMISSIONS_CAMPAIGN_VAULT_SEED.as_bytes(),
+            campaign.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub emissions_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Account provided only for funding rewards
+    #[account(mut)]
+    pub emissions_funding_account: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Withdraw the emissions of a campaign that were not claimed after its end.
+///
+/// Campaign authority only
+///
+/// Campaign positions accrue lazily, so users have `EMISSIONS_CAMPAIGN_RECLAIM_DELAY` after the end
+/// of the campaign to claim before the remaining emissions can be reclaimed.
+pub fn lending_pool_reclaim_campaign_emissions(
+    ctx: Context<LendingPoolReclaimCampaignEmissions>,
+) -> MarginfiResult {
+    let mut campaign = ctx.accounts.campaign.load_mut()?;
+
+    let reclaim_amount = campaign.reclaim_remaining_emissions(
+        Clock::get()?.unix_timestamp,
+        ctx.accounts.emissions_vault.amount,
+    )?;
+
+    if reclaim_amount > 0 {
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            EMISSIONS_CAMPAIGN_AUTH_SEED.as_bytes(),
+            &ctx.accounts.campaign.key().to_bytes(),
+            &[campaign.vault_authority_bump],
+        ]];
+
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.emissions_vault.to_account_info(),
+                    to: ctx.accounts.destination_account.to_account_info(),
+                    authority: ctx.accounts.emissions_auth.to_account_info(),
+                    mint: ctx.accounts.emissions_mint.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            reclaim_amount,
+            ctx.accounts.emissions_mint.decimals,
+        )?;
+    }
+
+    emit!(LendingPoolBankReclaimCampaignEmissionsEvent {
+        header: GroupEventHeader {
+            marginfi_group: campaign.group,
+            signer: Some(ctx.accounts.authority.key()),
+        },
+        bank: campaign.bank,
+        campaign: ctx.accounts.campaign.key(),
+        emissions_mint: campaign.emissions_mint,
+        amount: reclaim_amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolReclaimCampaignEmissions<'info> {
+    #[account(mut)]
+    pub campaign: AccountLoader<'info, EmissionsCampaign>,
+
+    #[account(
+        address = campaign.load()?.authority,
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(
+        address = campaign.load()?.emissions_mint,
+    )]
+    pub emissions_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [
+            EMISSIONS_CAMPAIGN_AUTH_SEED.as_bytes(),
+            campaign.key().as_ref(),
+        ],
+        bump = campaign.load()?.vault_authority_bump,
+    )]
+    /// CHECK: Asserted by PDA
+    pub emissions_auth: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            EMISSIONS_CAMPAIGN_VAULT_SEED.as_bytes(),
+            campaign.key().as_ref(),
+        ],
+        bump = campaign.load()?.vault_bump,
+    )]
+    pub emissions_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub destination_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
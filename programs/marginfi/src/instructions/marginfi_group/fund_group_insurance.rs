@@ -0,0 +1,114 @@
+use crate::{
+    bank_signer, check,
+    constants::{INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED},
+    events::{GroupEventHeader, MarginfiGroupFundInsuranceEvent},
+    prelude::*,
+    state::marginfi_group::{Bank, BankVaultType},
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+/// Top up the group insurance fund from the insurance vault of a bank holding the fund mint.
+///
+/// Admin only
+///
+/// Only the protocol owned part of the bank insurance vault can be moved, see
+/// `lending_pool_withdraw_insurance`.
+pub fn lending_pool_fund_group_insurance<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingPoolFundGroupInsurance<'info>>,
+    amount: u64,
+) -> MarginfiResult {
+    let LendingPoolFundGroupInsurance {
+        marginfi_group: marginfi_group_loader,
+        admin,
+        bank: bank_loader,
+        insurance_vault,
+        insurance_vault_authority,
+        insurance_fund_vault,
+        token_program,
+    } = ctx.accounts;
+
+    let mut bank = bank_loader.load_mut()?;
+
+    check!(
+        marginfi_group_loader.load()?.is_insurance_fund_mint(&bank),
+        MarginfiError::GroupInsuranceFundMintMismatch
+    );
+
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
+
+    bank.withdraw_protocol_insurance(amount, insurance_vault.amount)?;
+
+    bank.withdraw_spl_transfer(
+        amount,
+        insurance_vault.to_account_info(),
+        insurance_fund_vault.to_account_info(),
+        insurance_vault_authority.to_account_info(),
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        bank_signer!(
+            BankVaultType::Insurance,
+            bank_loader.key(),
+            bank.insurance_vault_authority_bump
+        ),
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(MarginfiGroupFundInsuranceEvent {
+        header: GroupEventHeader {
+            marginfi_group: marginfi_group_loader.key(),
+            signer: Some(admin.key())
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolFundGroupInsurance<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        address = marginfi_group.load()?.admin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        mut,
+        seeds = [
+            INSURANCE_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_bump
+    )]
+    pub insurance_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Seed constraint
+    #[account(
+        seeds = [
+            INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_authority_bump
+    )]
+    pub insurance_vault_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        address = marginfi_group.load()?.insurance_fund_vault,
+    )]
+    pub insurance_fund_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,331 @@
+use crate::{
+    bank_signer, check,
+    constants::PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG,
+    events::{AccountEventHeader, LendingPoolBankHandleBankruptcyEvent},
+    prelude::MarginfiError,
+    state::{
+        marginfi_account::{BalanceSide, MarginfiAccount, RiskEngine, DISABLED_FLAG},
+        marginfi_group::{Bank, BankVaultType, MarginfiGroup},
+    },
+    utils, MarginfiResult,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+use super::{settle_bank_bad_debt, sweep_residual_assets, take_residual_asset_bank_ais};
+
+/// Number of accounts required per bank, excluding the token 2022 mint and the optional group
+/// insurance fund accounts.
+const BANKRUPTCY_BANK_ACCOUNTS_LEN: usize = 5;
+
+/// Handle a bankrupt marginfi account across all of its liability banks in one instruction.
+///
+/// The account is checked for bankruptcy once, then the bad debt of every listed bank is settled
+/// the same way as in `lending_pool_handle_bankruptcy`, so a price move between transactions can
+/// not leave the account with partially settled bad debt. Every liability of the account must be
+/// settled by the end of the instruction. Residual assets are swept to the insurance fees of their
+/// banks.
+///
+/// Expected remaining account schema
+/// [
+///    observation_ais... (bank and oracle accounts of every active balance),
+///    residual_asset_bank_ais... (writable, see `take_residual_asset_bank_ais`),
+///    for each bank with bad debt:
+///      bank,
+///      liquidity_vault,
+///      insurance_vault,
+///      insurance_vault_authority,
+///      token_program,
+///      mint (token 2022 banks only),
+///      group_insurance_fund_ais... (optional, see `utils::maybe_take_group_insurance_fund`),
+///  ]
+pub fn lending_pool_handle_bankruptcies<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingPoolHandleBankruptcies<'info>>,
+) -> MarginfiResult {
+    let LendingPoolHandleBankruptcies {
+        marginfi_group: marginfi_group_loader,
+        signer,
+        marginfi_account: marginfi_account_loader,
+    } = ctx.accounts;
+
+    let clock = Clock::get()?;
+    let marginfi_group = marginfi_group_loader.load()?;
+    let admin = marginfi_group.admin;
+
+    let mut marginfi_account = marginfi_account_loader.load_mut()?;
+
+    let observation_ais_len =
+        marginfi_account.get_remaining_accounts_len(ctx.remaining_accounts)?;
+    check!(
+        ctx.remaining_accounts.len() > observation_ais_len,
+        MarginfiError::MissingPythOrBankAccount
+    );
+    let (observation_ais, mut bankruptcy_ais) =
+        ctx.remaining_accounts.split_at(observation_ais_len);
+
+    RiskEngine::new(&marginfi_account, observation_ais)?
+        .check_account_bankrupt(marginfi_group.get_bankrupt_threshold())?;
+
+    let header = AccountEventHeader {
+        signer: Some(signer.key()),
+        marginfi_account: marginfi_account_loader.key(),
+        marginfi_account_authority: marginfi_account.authority,
+        marginfi_group: marginfi_account.group,
+    };
+
+    let residual_asset_bank_ais =
+        take_residual_asset_bank_ais(&marginfi_account.lending_account, &mut bankruptcy_ais)?;
+
+    sweep_residual_assets(
+        &mut marginfi_account,
+        residual_asset_bank_ais,
+        &clock,
+        &header,
+    )?;
+
+    while !bankruptcy_ais.is_empty() {
+        let bank_ais = take_bankruptcy_bank_ais(&mut bankruptcy_ais)?;
+
+        let bank_ai = &bank_ais[0];
+        let liquidity_vault_ai = &bank_ais[1];
+        let insurance_vault_ai = &bank_ais[2];
+        let insurance_vault_authority_ai = &bank_ais[3];
+        let token_program_ai = &bank_ais[4];
+
+        let bank_loader = AccountLoader::<Bank>::try_from(bank_ai)?;
+
+        let (insurance_vault, token_program, maybe_bank_mint, maybe_group_insurance_fund) = {
+            let bank = bank_loader.load()?;
+
+            check_bankruptcy_bank_ais(
+                bank_ais,
+                &bank,
+                bank_loader.key(),
+                marginfi_group_loader.key(),
+            )?;
+
+            if !bank.get_flag(PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG) {
+                check!(signer.key() == admin, MarginfiError::Unauthorized);
+            }
+
+            let insurance_vault = InterfaceAccount::<TokenAccount>::try_from(insurance_vault_ai)?;
+            let token_program = Interface::<TokenInterface>::try_from(token_program_ai)?;
+
+            let maybe_bank_mint =
+                utils::maybe_take_bank_mint(&mut bankruptcy_ais, &bank, token_program.key)?;
+            let maybe_group_insurance_fund = utils::maybe_take_group_insurance_fund(
+                &mut bankruptcy_ais,
+                marginfi_group_loader.key(),
+                &marginfi_group,
+                &bank,
+            )?;
+
+            (
+                insurance_vault,
+                token_program,
+                maybe_bank_mint,
+                maybe_group_insurance_fund,
+            )
+        };
+
+        let settlement = settle_bank_bad_debt(
+            &mut marginfi_account,
+            &bank_loader,
+            liquidity_vault_ai.clone(),
+            &insurance_vault,
+            insurance_vault_authority_ai.clone(),
+            maybe_group_insurance_fund.as_ref(),
+            maybe_bank_mint.as_ref(),
+            token_program.to_account_info(),
+            ctx.remaining_accounts,
+            &clock,
+        )?;
+
+        emit!(LendingPoolBankHandleBankruptcyEvent {
+            header: header.clone(),
+            bank: bank_loader.key(),
+            mint: bank_loader.load()?.mint,
+            bad_debt: settlement.bad_debt.to_num::<f64>(),
+            covered_amount: settlement.covered_by_insurance.to_num::<f64>(),
+            group_insurance_covered_amount: settlement.covered_by_group_insurance.to_num::<f64>(),
+            socialized_amount: settlement.socialized_loss.to_num::<f64>(),
+            account_bad_debt: settlement.account_bad_debt.to_num::<f64>(),
+            bank_bad_debt_written_off: settlement.bank_bad_debt_written_off.to_num::<f64>(),
+        });
+    }
+
+    check!(
+        marginfi_account
+            .lending_account
+            .balances
+            .iter()
+            .filter(|balance| balance.active)
+            .all(|balance| balance.is_empty(BalanceSide::Liabilities)),
+        MarginfiError::BadDebtNotFullySettled
+    );
+
+    marginfi_account.set_flag(DISABLED_FLAG);
+
+    Ok(())
+}
+
+/// Split the fixed per-bank accounts of the next bank with bad debt off `bankruptcy_ais`.
+fn take_bankruptcy_bank_ais<'a, 'info>(
+    bankruptcy_ais: &mut &'a [AccountInfo<'info>],
+) -> MarginfiResult<&'a [AccountInfo<'info>]> {
+    check!(
+        bankruptcy_ais.len() >= BANKRUPTCY_BANK_ACCOUNTS_LEN,
+        MarginfiError::MissingPythOrBankAccount
+    );
+    let (bank_ais, remaining) = bankruptcy_ais.split_at(BANKRUPTCY_BANK_ACCOUNTS_LEN);
+    *bankruptcy_ais = remaining;
+
+    Ok(bank_ais)
+}
+
+/// Check that the per-bank accounts belong to `bank`, so that accounts shifted by a missing or
+/// extra entry in the schema are rejected before any transfer.
+fn check_bankruptcy_bank_ais(
+    bank_ais: &[AccountInfo],
+    bank: &Bank,
+    bank_pk: Pubkey,
+    marginfi_group_pk: Pubkey,
+) -> MarginfiResult {
+    check!(
+        bank.group == marginfi_group_pk,
+        MarginfiError::InvalidBankAccount
+    );
+
+    let insurance_vault_authority = Pubkey::create_program_address(
+        bank_signer!(
+            BankVaultType::Insurance,
+            bank_pk,
+            bank.insurance_vault_authority_bump
+        )[0],
+        &crate::id(),
+    )
+    .map_err(|_| MarginfiError::InvalidBankAccount)?;
+
+    check!(
+        *bank_ais[1].key == bank.liquidity_vault
+            && *bank_ais[2].key == bank.insurance_vault
+            && *bank_ais[3].key == insurance_vault_authority,
+        MarginfiError::InvalidBankAccount
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolHandleBankruptcies<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    pub signer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = marginfi_account.load()?.group == marginfi_group.key(),
+    )]
+    pub marginfi_account: AccountLoader<'info, MarginfiAccount>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::INSURANCE_VAULT_AUTHORITY_SEED;
+
+    fn leaked_account_info(key: Pubkey) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            true,
+            Box::leak(Box::new(0)),
+            Box::leak(Vec::new().into_boxed_slice()),
+            Box::leak(Box::new(Pubkey::default())),
+            false,
+            0,
+        )
+    }
+
+    fn test_bank(group: Pubkey) -> (Pubkey, Bank) {
+        let bank_pk = Pubkey::new_unique();
+        let (_, insurance_vault_authority_bump) = Pubkey::find_program_address(
+            &[INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(), bank_pk.as_ref()],
+            &crate::id(),
+        );
+
+        let bank = Bank {
+            group,
+            liquidity_vault: Pubkey::new_unique(),
+            insurance_vault: Pubkey::new_unique(),
+            insurance_vault_authority_bump,
+            ..Bank::default()
+        };
+
+        (bank_pk, bank)
+    }
+
+    fn bank_ais(bank_pk: Pubkey, bank: &Bank) -> Vec<AccountInfo<'static>> {
+        let (insurance_vault_authority, _) = Pubkey::find_program_address(
+            &[INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(), bank_pk.as_ref()],
+            &crate::id(),
+        );
+
+        vec![
+            leaked_account_info(bank_pk),
+            leaked_account_info(bank.liquidity_vault),
+            leaked_account_info(bank.insurance_vault),
+            leaked_account_info(insurance_vault_authority),
+            leaked_account_info(anchor_spl::token::ID),
+        ]
+    }
+
+    #[test]
+    fn bankruptcy_bank_account_schema() {
+        let group = Pubkey::new_unique();
+        let (bank_a_pk, bank_a) = test_bank(group);
+        let (bank_b_pk, bank_b) = test_bank(group);
+
+        // Two banks with bad debt, each group checked against its own bank
+        let mut ais = bank_ais(bank_a_pk, &bank_a);
+        ais.extend(bank_ais(bank_b_pk, &bank_b));
+        let mut bankruptcy_ais: &'static [AccountInfo<'static>] = Box::leak(ais.into_boxed_slice());
+
+        let first = take_bankruptcy_bank_ais(&mut bankruptcy_ais).unwrap();
+        assert!(check_bankruptcy_bank_ais(first, &bank_a, bank_a_pk, group).is_ok());
+        let second = take_bankruptcy_bank_ais(&mut bankruptcy_ais).unwrap();
+        assert!(check_bankruptcy_bank_ais(second, &bank_b, bank_b_pk, group).is_ok());
+        assert!(bankruptcy_ais.is_empty());
+
+        // Accounts of the second bank checked against the first one
+        assert_eq!(
+            check_bankruptcy_bank_ais(second, &bank_a, bank_a_pk, group).unwrap_err(),
+            MarginfiError::InvalidBankAccount.into()
+        );
+
+        // Bank of another group
+        assert_eq!(
+            check_bankruptcy_bank_ais(first, &bank_a, bank_a_pk, Pubkey::new_unique()).unwrap_err(),
+            MarginfiError::InvalidBankAccount.into()
+        );
+
+        // Liquidity and insurance vaults swapped
+        let mut swapped = bank_ais(bank_a_pk, &bank_a);
+        swapped.swap(1, 2);
+        assert_eq!(
+            check_bankruptcy_bank_ais(&swapped, &bank_a, bank_a_pk, group).unwrap_err(),
+            MarginfiError::InvalidBankAccount.into()
+        );
+
+        // Trailing group shorter than the per-bank schema
+        let mut ais = bank_ais(bank_a_pk, &bank_a);
+        ais.extend(bank_ais(bank_b_pk, &bank_b).into_iter().take(4));
+        let mut bankruptcy_ais: &'static [AccountInfo<'static>] = Box::leak(ais.into_boxed_slice());
+
+        assert!(take_bankruptcy_bank_ais(&mut bankruptcy_ais).is_ok());
+        assert_eq!(
+            take_bankruptcy_bank_ais(&mut bankruptcy_ais).unwrap_err(),
+            MarginfiError::MissingPythOrBankAccount.into()
+        );
+    }
+}
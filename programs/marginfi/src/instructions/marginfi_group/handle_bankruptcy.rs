@@ -1,21 +1,28 @@
 use crate::{
     bank_signer, check,
     constants::{
-        INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_SEED,
-        PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG, ZERO_AMOUNT_THRESHOLD,
+        GROUP_INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED,
+        LIQUIDITY_VAULT_SEED, PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG, ZERO_AMOUNT_THRESHOLD,
     },
     debug,
-    events::{AccountEventHeader, LendingPoolBankHandleBankruptcyEvent},
+    events::{
+        AccountEventHeader, LendingAccountSweepResidualAssetsEvent,
+        LendingPoolBankHandleBankruptcyEvent,
+    },
     math_error,
     prelude::MarginfiError,
     state::{
-        marginfi_account::{BankAccountWrapper, MarginfiAccount, RiskEngine, DISABLED_FLAG},
+        marginfi_account::{
+            BalanceSide, BankAccountWrapper, LendingAccount, MarginfiAccount, RiskEngine,
+            DISABLED_FLAG,
+        },
         marginfi_group::{Bank, BankVaultType, MarginfiGroup},
     },
-    utils, MarginfiResult,
+    utils::{self, GroupInsuranceFund},
+    MarginfiResult,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 use fixed::types::I80F48;
 use std::cmp::{max, min};
 
@@ -24,37 +31,218 @@ use std::cmp::{max, min};
 /// 2. Determine the amount of bad debt covered by the insurance fund and the amount socialized between depositors.
 /// 3. Cover the bad debt of the bankrupt account.
 /// 4. Transfer the insured amount from the insurance fund.
-/// 5. Socialize the loss between lenders if any.
+/// 5. Cover the remaining amount with the group insurance fund, if provided and holding the bank mint.
+/// 6. Socialize the loss between lenders if any.
+///
+/// Residual assets of the account, worth less than the group bankrupt threshold, are swept to the
+/// insurance fees of their banks if their banks are passed after the observation accounts.
+///
+/// Expected remaining account schema
+/// [
+///    mint (token 2022 banks only),
+///    group_insurance_fund_ais... (optional, see `utils::maybe_take_group_insurance_fund`),
+///    observation_ais...,
+///    residual_asset_bank_ais... (optional, writable, see `take_residual_asset_bank_ais`),
+///  ]
 pub fn lending_pool_handle_bankruptcy<'info>(
     mut ctx: Context<'_, '_, 'info, 'info, LendingPoolHandleBankruptcy<'info>>,
 ) -> MarginfiResult {
     let LendingPoolHandleBankruptcy {
         marginfi_account: marginfi_account_loader,
+        liquidity_vault,
         insurance_vault,
+        insurance_vault_authority,
         token_program,
         bank: bank_loader,
         marginfi_group: marginfi_group_loader,
-        ..
+        signer,
     } = ctx.accounts;
+    let clock = Clock::get()?;
     let bank = bank_loader.load()?;
+    let marginfi_group = marginfi_group_loader.load()?;
+    let bankrupt_threshold = marginfi_group.get_bankrupt_threshold();
     let maybe_bank_mint =
         utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
-
-    let clock = Clock::get()?;
+    let maybe_group_insurance_fund = utils::maybe_take_group_insurance_fund(
+        &mut ctx.remaining_accounts,
+        marginfi_group_loader.key(),
+        &marginfi_group,
+        &bank,
+    )?;
 
     if !bank.get_flag(PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG) {
         check!(
-            ctx.accounts.signer.key() == marginfi_group_loader.load()?.admin,
+            signer.key() == marginfi_group.admin,
             MarginfiError::Unauthorized
         );
     }
 
+    drop(marginfi_group);
+
     drop(bank);
 
     let mut marginfi_account = marginfi_account_loader.load_mut()?;
 
-    RiskEngine::new(&marginfi_account, ctx.remaining_accounts)?.check_account_bankrupt()?;
+    let observation_ais_len =
+        marginfi_account.get_remaining_accounts_len(ctx.remaining_accounts)?;
+    check!(
+        ctx.remaining_accounts.len() >= observation_ais_len,
+        MarginfiError::MissingPythOrBankAccount
+    );
+    let (observation_ais, mut residual_asset_bank_ais) =
+        ctx.remaining_accounts.split_at(observation_ais_len);
+
+    RiskEngine::new(&marginfi_account, observation_ais)?
+        .check_account_bankrupt(bankrupt_threshold)?;
+
+    let header = AccountEventHeader {
+        signer: Some(signer.key()),
+        marginfi_account: marginfi_account_loader.key(),
+        marginfi_account_authority: marginfi_account.authority,
+        marginfi_group: marginfi_account.group,
+    };
+
+    // Sweeping residual assets is opt-in, callers only passing the observation accounts leave
+    // them in the account
+    if !residual_asset_bank_ais.is_empty() {
+        let residual_asset_bank_ais = take_residual_asset_bank_ais(
+            &marginfi_account.lending_account,
+            &mut residual_asset_bank_ais,
+        )?;
+
+        sweep_residual_assets(
+            &mut marginfi_account,
+            residual_asset_bank_ais,
+            &clock,
+            &header,
+        )?;
+    }
+
+    let settlement = settle_bank_bad_debt(
+        &mut marginfi_account,
+        bank_loader,
+        liquidity_vault.to_account_info(),
+        insurance_vault,
+        insurance_vault_authority.to_account_info(),
+        maybe_group_insurance_fund.as_ref(),
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        ctx.remaining_accounts,
+        &clock,
+    )?;
+
+    marginfi_account.set_flag(DISABLED_FLAG);
+
+    emit!(LendingPoolBankHandleBankruptcyEvent {
+        header,
+        bank: bank_loader.key(),
+        mint: bank_loader.load()?.mint,
+        bad_debt: settlement.bad_debt.to_num::<f64>(),
+        covered_amount: settlement.covered_by_insurance.to_num::<f64>(),
+        group_insurance_covered_amount: settlement.covered_by_group_insurance.to_num::<f64>(),
+        socialized_amount: settlement.socialized_loss.to_num::<f64>(),
+        account_bad_debt: settlement.account_bad_debt.to_num::<f64>(),
+        bank_bad_debt_written_off: settlement.bank_bad_debt_written_off.to_num::<f64>(),
+    });
+
+    Ok(())
+}
+
+/// Take the banks of all asset balances of a bankrupt account, in balance order, from the
+/// remaining accounts. The banks must be writable, their residual assets are swept by
+/// `sweep_residual_assets`.
+pub(crate) fn take_residual_asset_bank_ais<'info>(
+    lending_account: &LendingAccount,
+    remaining_ais: &mut &'info [AccountInfo<'info>],
+) -> MarginfiResult<&'info [AccountInfo<'info>]> {
+    let asset_bank_pks = lending_account
+        .balances
+        .iter()
+        .filter(|balance| balance.active && !balance.is_empty(BalanceSide::Assets))
+        .map(|balance| balance.bank_pk)
+        .collect::<Vec<_>>();
+
+    check!(
+        remaining_ais.len() >= asset_bank_pks.len(),
+        MarginfiError::MissingPythOrBankAccount
+    );
+
+    let (bank_ais, remaining) = remaining_ais.split_at(asset_bank_pks.len());
+
+    for (bank_ai, bank_pk) in bank_ais.iter().zip(asset_bank_pks.iter()) {
+        check!(bank_ai.key.eq(bank_pk), MarginfiError::InvalidBankAccount);
+        check!(
+            bank_ai.is_writable,
+            MarginfiError::InvalidBankAccount,
+            "Residual asset banks must be writable"
+        );
+    }
+
+    *remaining_ais = remaining;
+
+    Ok(bank_ais)
+}
 
+/// Sweep all remaining assets of a bankrupt account into the outstanding insurance fees of their
+/// banks, see `take_residual_asset_bank_ais`.
+pub(crate) fn sweep_residual_assets<'info>(
+    marginfi_account: &mut MarginfiAccount,
+    residual_asset_bank_ais: &'info [AccountInfo<'info>],
+    clock: &Clock,
+    header: &AccountEventHeader,
+) -> MarginfiResult {
+    for bank_ai in residual_asset_bank_ais {
+        let bank_pk = *bank_ai.key;
+        let bank_loader = AccountLoader::<Bank>::try_from(bank_ai)?;
+        let mut bank = bank_loader.load_mut()?;
+
+        bank.accrue_interest(
+            clock.unix_timestamp,
+            #[cfg(not(feature = "client"))]
+            bank_pk,
+        )?;
+
+        let amount =
+            BankAccountWrapper::find(&bank_pk, &mut bank, &mut marginfi_account.lending_account)?
+                .sweep_assets_to_insurance(clock.unix_timestamp as u64)?;
+
+        emit!(LendingAccountSweepResidualAssetsEvent {
+            header: header.clone(),
+            bank: bank_pk,
+            mint: bank.mint,
+            amount: amount.to_num::<f64>(),
+        });
+    }
+
+    Ok(())
+}
+
+pub(crate) struct BadDebtSettlement {
+    pub bad_debt: I80F48,
+    pub covered_by_insurance: I80F48,
+    pub covered_by_group_insurance: I80F48,
+    pub socialized_loss: I80F48,
+    pub account_bad_debt: I80F48,
+    pub bank_bad_debt_written_off: I80F48,
+}
+
+/// Settle the bad debt of `marginfi_account` in a single bank.
+///
+/// The insurance fund of the bank covers as much of the bad debt as possible, followed by the
+/// group insurance fund if provided. The rest is socialized between the bank depositors.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn settle_bank_bad_debt<'info>(
+    marginfi_account: &mut MarginfiAccount,
+    bank_loader: &AccountLoader<'info, Bank>,
+    liquidity_vault: AccountInfo<'info>,
+    insurance_vault: &InterfaceAccount<'info, TokenAccount>,
+    insurance_vault_authority: AccountInfo<'info>,
+    maybe_group_insurance_fund: Option<&GroupInsuranceFund<'info>>,
+    maybe_bank_mint: Option<&InterfaceAccount<'info, Mint>>,
+    token_program: AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    clock: &Clock,
+) -> MarginfiResult<BadDebtSettlement> {
     let mut bank = bank_loader.load_mut()?;
 
     bank.accrue_interest(
@@ -83,25 +271,8 @@ pub fn lending_pool_handle_bankruptcy<'info>(
         MarginfiError::BalanceNotBadDebt
     );
 
-    let (covered_by_insurance, socialized_loss) = {
-        let available_insurance_fund: I80F48 = maybe_bank_mint
-            .as_ref()
-            .map(|mint| {
-                utils::calculate_post_fee_spl_deposit_amount(
-                    mint.to_account_info(),
-                    insurance_vault.amount,
-                    clock.epoch,
-                )
-            })
-            .transpose()?
-            .unwrap_or(insurance_vault.amount)
-            .into();
-
-        let covered_by_insurance = min(bad_debt, available_insurance_fund);
-        let socialized_loss = max(bad_debt - covered_by_insurance, I80F48::ZERO);
-
-        (covered_by_insurance, socialized_loss)
-    };
+    let (covered_by_insurance, socialized_loss) =
+        get_insurance_coverage(bad_debt, insurance_vault, maybe_bank_mint, clock)?;
 
     // Cover bad debt with insurance funds.
     let covered_by_insurance_rounded_up: u64 = covered_by_insurance
@@ -115,7 +286,6 @@ pub fn lending_pool_handle_bankruptcy<'info>(
     );
 
     let insurance_coverage_deposit_pre_fee = maybe_bank_mint
-        .as_ref()
         .map(|mint| {
             utils::calculate_pre_fee_spl_deposit_amount(
                 mint.to_account_info(),
@@ -128,19 +298,66 @@ pub fn lending_pool_handle_bankruptcy<'info>(
 
     bank.withdraw_spl_transfer(
         insurance_coverage_deposit_pre_fee,
-        ctx.accounts.insurance_vault.to_account_info(),
-        ctx.accounts.liquidity_vault.to_account_info(),
-        ctx.accounts.insurance_vault_authority.to_account_info(),
-        maybe_bank_mint.as_ref(),
-        token_program.to_account_info(),
+        insurance_vault.to_account_info(),
+        liquidity_vault.clone(),
+        insurance_vault_authority,
+        maybe_bank_mint,
+        token_program.clone(),
         bank_signer!(
             BankVaultType::Insurance,
             bank_loader.key(),
             bank.insurance_vault_authority_bump
         ),
-        ctx.remaining_accounts,
+        remaining_accounts,
     )?;
 
+    // Cover the remaining bad debt with the group insurance fund.
+    let (covered_by_group_insurance, socialized_loss) = match maybe_group_insurance_fund {
+        Some(group_insurance_fund) if socialized_loss > I80F48::ZERO => {
+            let (covered_by_group_insurance, socialized_loss) = get_insurance_coverage(
+                socialized_loss,
+                &group_insurance_fund.vault,
+                maybe_bank_mint,
+                clock,
+            )?;
+
+            let covered_by_group_insurance_rounded_up: u64 = covered_by_group_insurance
+                .checked_ceil()
+                .ok_or_else(math_error!())?
+                .checked_to_num()
+                .ok_or_else(math_error!())?;
+
+            let group_insurance_coverage_deposit_pre_fee = maybe_bank_mint
+                .map(|mint| {
+                    utils::calculate_pre_fee_spl_deposit_amount(
+                        mint.to_account_info(),
+                        covered_by_group_insurance_rounded_up,
+                        clock.epoch,
+                    )
+                })
+                .transpose()?
+                .unwrap_or(covered_by_group_insurance_rounded_up);
+
+            bank.withdraw_spl_transfer(
+                group_insurance_coverage_deposit_pre_fee,
+                group_insurance_fund.vault.to_account_info(),
+                liquidity_vault,
+                group_insurance_fund.vault_authority.clone(),
+                maybe_bank_mint,
+                token_program,
+                &[&[
+                    GROUP_INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(),
+                    &group_insurance_fund.marginfi_group.to_bytes(),
+                    &[group_insurance_fund.vault_authority_bump],
+                ]],
+                remaining_accounts,
+            )?;
+
+            (covered_by_group_insurance, socialized_loss)
+        }
+        _ => (I80F48::ZERO, socialized_loss),
+    };
+
     // Socialize bad debt among depositors.
     bank.socialize_loss(socialized_loss)?;
 
@@ -153,23 +370,48 @@ pub fn lending_pool_handle_bankruptcy<'info>(
     )?
     .repay(bad_debt)?;
 
-    marginfi_account.set_flag(DISABLED_FLAG);
+    // Record the written off bad debt for a later recovery.
+    let account_bad_debt = marginfi_account
+        .record_bad_debt(bank_loader.key(), bad_debt)?
+        .unwrap_or(bad_debt);
+    let bank_bad_debt_written_off = I80F48::from(bank.bad_debt_written_off)
+        .checked_add(bad_debt)
+        .ok_or_else(math_error!())?;
+    bank.bad_debt_written_off = bank_bad_debt_written_off.into();
+
+    Ok(BadDebtSettlement {
+        bad_debt,
+        covered_by_insurance,
+        covered_by_group_insurance,
+        socialized_loss,
+        account_bad_debt,
+        bank_bad_debt_written_off,
+    })
+}
 
-    emit!(LendingPoolBankHandleBankruptcyEvent {
-        header: AccountEventHeader {
-            signer: Some(ctx.accounts.signer.key()),
-            marginfi_account: marginfi_account_loader.key(),
-            marginfi_account_authority: marginfi_account.authority,
-            marginfi_group: marginfi_account.group,
-        },
-        bank: bank_loader.key(),
-        mint: bank.mint,
-        bad_debt: bad_debt.to_num::<f64>(),
-        covered_amount: covered_by_insurance.to_num::<f64>(),
-        socialized_amount: socialized_loss.to_num::<f64>(),
-    });
+/// Split `bad_debt` into the amount covered by `insurance_vault` and the uncovered remainder.
+fn get_insurance_coverage(
+    bad_debt: I80F48,
+    insurance_vault: &InterfaceAccount<TokenAccount>,
+    maybe_bank_mint: Option<&InterfaceAccount<Mint>>,
+    clock: &Clock,
+) -> MarginfiResult<(I80F48, I80F48)> {
+    let available_insurance_fund: I80F48 = maybe_bank_mint
+        .map(|mint| {
+            utils::calculate_post_fee_spl_deposit_amount(
+                mint.to_account_info(),
+                insurance_vault.amount,
+                clock.epoch,
+            )
+        })
+        .transpose()?
+        .unwrap_or(insurance_vault.amount)
+        .into();
 
-    Ok(())
+    let covered = min(bad_debt, available_insurance_fund);
+    let uncovered = max(bad_debt - covered, I80F48::ZERO);
+
+    Ok((covered, uncovered))
 }
 
 #[derive(Accounts)]
@@ -224,3 +466,96 @@ pub struct LendingPoolHandleBankruptcy<'info> {
 
     pub token_program: Interface<'info, TokenInterface>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::marginfi_account::Balance;
+    use fixed_macro::types::I80F48;
+
+    fn leaked_account_info(key: Pubkey, is_writable: bool) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            is_writable,
+            Box::leak(Box::new(0)),
+            Box::leak(Vec::new().into_boxed_slice()),
+            Box::leak(Box::new(Pubkey::default())),
+            false,
+            0,
+        )
+    }
+
+    #[test]
+    fn residual_asset_bank_accounts() {
+        let asset_bank_pks = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let liab_bank_pk = Pubkey::new_unique();
+
+        let mut lending_account = LendingAccount {
+            balances: [Balance::empty_deactivated(); 16],
+            _padding: [0; 8],
+        };
+        lending_account.balances[0] = Balance {
+            active: true,
+            bank_pk: asset_bank_pks[0],
+            asset_shares: I80F48!(100).into(),
+            ..Balance::empty_deactivated()
+        };
+        lending_account.balances[1] = Balance {
+            active: true,
+            bank_pk: liab_bank_pk,
+            liability_shares: I80F48!(100).into(),
+            ..Balance::empty_deactivated()
+        };
+        lending_account.balances[2] = Balance {
+            active: true,
+            bank_pk: asset_bank_pks[1],
+            asset_shares: I80F48!(1).into(),
+            ..Balance::empty_deactivated()
+        };
+
+        let take = |ais: Vec<AccountInfo<'static>>| {
+            let mut remaining_ais: &'static [AccountInfo<'static>] =
+                Box::leak(ais.into_boxed_slice());
+            take_residual_asset_bank_ais(&lending_account, &mut remaining_ais)
+                .map(|bank_ais| (bank_ais.len(), remaining_ais.len()))
+        };
+
+        // Asset banks in balance order, followed by other accounts
+        assert_eq!(
+            take(vec![
+                leaked_account_info(asset_bank_pks[0], true),
+                leaked_account_info(asset_bank_pks[1], true),
+                leaked_account_info(liab_bank_pk, true),
+            ])
+            .unwrap(),
+            (2, 1)
+        );
+
+        // Read only bank
+        assert_eq!(
+            take(vec![
+                leaked_account_info(asset_bank_pks[0], true),
+                leaked_account_info(asset_bank_pks[1], false),
+            ])
+            .unwrap_err(),
+            MarginfiError::InvalidBankAccount.into()
+        );
+
+        // Wrong order
+        assert_eq!(
+            take(vec![
+                leaked_account_info(asset_bank_pks[1], true),
+                leaked_account_info(asset_bank_pks[0], true),
+            ])
+            .unwrap_err(),
+            MarginfiError::InvalidBankAccount.into()
+        );
+
+        // Missing bank
+        assert_eq!(
+            take(vec![leaked_account_info(asset_bank_pks[0], true)]).unwrap_err(),
+            MarginfiError::MissingPythOrBankAccount.into()
+        );
+    }
+}
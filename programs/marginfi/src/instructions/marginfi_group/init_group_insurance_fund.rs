@@ -0,0 +1,81 @@
+use crate::{
+    check,
+    constants::{GROUP_INSURANCE_VAULT_AUTHORITY_SEED, GROUP_INSURANCE_VAULT_SEED},
+    events::{GroupEventHeader, MarginfiGroupInitInsuranceFundEvent},
+    prelude::*,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Initialize the group insurance fund vault holding `insurance_fund_mint`.
+///
+/// Admin only
+///
+/// The group insurance fund covers bad debt of banks with the same mint that can't be covered by
+/// the bank insurance vault. The mint of the fund can't be changed once initialized.
+pub fn lending_pool_init_group_insurance_fund(
+    ctx: Context<LendingPoolInitGroupInsuranceFund>,
+) -> MarginfiResult {
+    let mut marginfi_group = ctx.accounts.marginfi_group.load_mut()?;
+
+    check!(
+        !marginfi_group.is_insurance_fund_initialized(),
+        MarginfiError::GroupInsuranceFundAlreadyInitialized
+    );
+
+    marginfi_group.insurance_fund_mint = ctx.accounts.insurance_fund_mint.key();
+    marginfi_group.insurance_fund_vault = ctx.accounts.insurance_fund_vault.key();
+    marginfi_group.insurance_fund_vault_bump = ctx.bumps.insurance_fund_vault;
+    marginfi_group.insurance_fund_vault_authority_bump = ctx.bumps.insurance_fund_vault_authority;
+
+    emit!(MarginfiGroupInitInsuranceFundEvent {
+        header: GroupEventHeader {
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        mint: marginfi_group.insurance_fund_mint,
+        vault: marginfi_group.insurance_fund_vault,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolInitGroupInsuranceFund<'info> {
+    #[account(mut)]
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        address = marginfi_group.load()?.admin,
+    )]
+    pub admin: Signer<'info>,
+
+    pub insurance_fund_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: ⋐ ͡⋄ ω ͡⋄ ⋑
+    #[account(
+        seeds = [
+            GROUP_INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(),
+            marginfi_group.key().as_ref(),
+        ],
+        bump
+    )]
+    pub insurance_fund_vault_authority: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        token::mint = insurance_fund_mint,
+        token::authority = insurance_fund_vault_authority,
+        seeds = [
+            GROUP_INSURANCE_VAULT_SEED.as_bytes(),
+            marginfi_group.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub insurance_fund_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,489 @@
+use crate::{
+    bank_signer, check,
+    constants::{
+        INSURANCE_SHARE_MINT_SEED, INSURANCE_UNSTAKE_TICKET_SEED, INSURANCE_VAULT_AUTHORITY_SEED,
+        INSURANCE_VAULT_SEED,
+    },
+    events::{
+        GroupEventHeader, LendingPoolBankInitInsuranceStakingEvent,
+        LendingPoolBankInsuranceClaimUnstakeEvent, LendingPoolBankInsuranceRequestUnstakeEvent,
+        LendingPoolBankInsuranceStakeEvent,
+    },
+    math_error,
+    prelude::*,
+    state::{
+        insurance_staking::InsuranceUnstakeTicket,
+        marginfi_group::{Bank, BankVaultType},
+    },
+    utils,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    burn, mint_to, Burn, Mint, MintTo, TokenAccount, TokenInterface,
+};
+
+/// Initialize insurance staking for a bank by creating the insurance share mint.
+///
+/// Admin only
+///
+/// The insurance vault balance at initialization is assigned to protocol owned shares at a 1:1
+/// ratio, so stakers never get a claim on funds deposited before them.
+pub fn lending_pool_init_insurance_staking(
+    ctx: Context<LendingPoolInitInsuranceStaking>,
+    unstake_cooldown: u64,
+) -> MarginfiResult {
+    let mut bank = ctx.accounts.bank.load_mut()?;
+
+    check!(
+        !bank.is_insurance_staking_initialized(),
+        MarginfiError::InsuranceStakingAlreadyInitialized
+    );
+
+    bank.insurance_share_mint = ctx.accounts.insurance_share_mint.key();
+    bank.insurance_share_mint_bump = ctx.bumps.insurance_share_mint;
+    bank.insurance_protocol_shares = ctx.accounts.insurance_vault.amount;
+    bank.insurance_staker_shares = 0;
+    bank.insurance_pending_unstake_shares = 0;
+    bank.insurance_unstake_cooldown = unstake_cooldown;
+
+    emit!(LendingPoolBankInitInsuranceStakingEvent {
+        header: GroupEventHeader {
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        bank: ctx.accounts.bank.key(),
+        mint: bank.mint,
+        share_mint: bank.insurance_share_mint,
+        protocol_shares: bank.insurance_protocol_shares,
+        unstake_cooldown,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolInitInsuranceStaking<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        address = marginfi_group.load()?.admin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(
+        seeds = [
+            INSURANCE_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_bump
+    )]
+    pub insurance_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Seed constraint
+    #[account(
+        seeds = [
+            INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_authority_bump
+    )]
+    pub insurance_vault_authority: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        mint::decimals = bank.load()?.mint_decimals,
+        mint::authority = insurance_vault_authority,
+        mint::token_program = token_program,
+        seeds = [
+            INSURANCE_SHARE_MINT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub insurance_share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Stake `amount` tokens into the bank insurance vault in exchange for insurance shares.
+///
+/// Insurance shares earn the insurance fees collected into the vault and absorb the bad debt of
+/// the bank before its depositors.
+pub fn lending_pool_insurance_stake<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingPoolInsuranceStake<'info>>,
+    amount: u64,
+) -> MarginfiResult {
+    let LendingPoolInsuranceStake {
+        marginfi_group: marginfi_group_loader,
+        bank: bank_loader,
+        staker,
+        source_token_account,
+        insurance_vault,
+        insurance_vault_authority,
+        insurance_share_mint,
+        staker_share_account,
+        token_program,
+    } = ctx.accounts;
+
+    let mut bank = bank_loader.load_mut()?;
+    let clock = Clock::get()?;
+
+    check!(
+        bank.is_insurance_staking_initialized(),
+        MarginfiError::InsuranceStakingNotInitialized
+    );
+
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
+
+    let amount_received = maybe_bank_mint
+        .as_ref()
+        .map(|mint| {
+            utils::calculate_post_fee_spl_deposit_amount(
+                mint.to_account_info(),
+                amount,
+                clock.epoch,
+            )
+        })
+        .transpose()?
+        .unwrap_or(amount);
+
+    let shares = bank.get_insurance_shares_for_amount(amount_received, insurance_vault.amount)?;
+
+    check!(shares > 0, MarginfiError::InsuranceStakeTooSmall);
+
+    bank.insurance_staker_shares = bank
+        .insurance_staker_shares
+        .checked_add(shares)
+        .ok_or_else(math_error!())?;
+
+    bank.insurance_deposit_spl_transfer(
+        amount,
+        source_token_account.to_account_info(),
+        insurance_vault.to_account_info(),
+        staker.to_account_info(),
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        ctx.remaining_accounts,
+    )?;
+
+    mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            MintTo {
+                mint: insurance_share_mint.to_account_info(),
+                to: staker_share_account.to_account_info(),
+                authority: insurance_vault_authority.to_account_info(),
+            },
+            bank_signer!(
+                BankVaultType::Insurance,
+                bank_loader.key(),
+                bank.insurance_vault_authority_bump
+            ),
+        ),
+        shares,
+    )?;
+
+    emit!(LendingPoolBankInsuranceStakeEvent {
+        header: GroupEventHeader {
+            marginfi_group: marginfi_group_loader.key(),
+            signer: Some(staker.key())
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        amount: amount_received,
+        shares,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolInsuranceStake<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    pub staker: Signer<'info>,
+
+    /// CHECK: Token mint/authority are checked at transfer
+    #[account(mut)]
+    pub source_token_account: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            INSURANCE_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_bump
+    )]
+    pub insurance_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Seed constraint
+    #[account(
+        seeds = [
+            INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_authority_bump
+    )]
+    pub insurance_vault_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        address = bank.load()?.insurance_share_mint,
+    )]
+    pub insurance_share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Token mint is checked at mint_to
+    #[account(mut)]
+    pub staker_share_account: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Burn `shares` insurance shares and open the unstake ticket `ticket_index` of the staker,
+/// claimable once the bank unstake cooldown has passed.
+///
+/// The shares keep absorbing insurance vault losses during the cooldown, so stakers can't escape
+/// bad debt by unstaking right before it is settled.
+pub fn lending_pool_insurance_request_unstake(
+    ctx: Context<LendingPoolInsuranceRequestUnstake>,
+    shares: u64,
+    ticket_index: u16,
+) -> MarginfiResult {
+    let mut bank = ctx.accounts.bank.load_mut()?;
+    let clock = Clock::get()?;
+
+    check!(shares > 0, MarginfiError::InsuranceStakeTooSmall);
+
+    burn(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.insurance_share_mint.to_account_info(),
+                from: ctx.accounts.staker_share_account.to_account_info(),
+                authority: ctx.accounts.staker.to_account_info(),
+            },
+        ),
+        shares,
+    )?;
+
+    bank.insurance_staker_shares = bank
+        .insurance_staker_shares
+        .checked_sub(shares)
+        .ok_or_else(math_error!())?;
+    bank.insurance_pending_unstake_shares = bank
+        .insurance_pending_unstake_shares
+        .checked_add(shares)
+        .ok_or_else(math_error!())?;
+
+    let unlock_timestamp = clock
+        .unix_timestamp
+        .checked_add(
+            bank.insurance_unstake_cooldown
+                .try_into()
+                .map_err(|_| MarginfiError::MathError)?,
+        )
+        .ok_or_else(math_error!())?;
+
+    let mut unstake_ticket = ctx.accounts.unstake_ticket.load_init()?;
+
+    *unstake_ticket = InsuranceUnstakeTicket {
+        bank: ctx.accounts.bank.key(),
+        staker: ctx.accounts.staker.key(),
+        shares,
+        unlock_timestamp,
+        ticket_index,
+        bump: ctx.bumps.unstake_ticket,
+        _pad0: [0; 5],
+        _padding: [0; 8],
+    };
+
+    emit!(LendingPoolBankInsuranceRequestUnstakeEvent {
+        header: GroupEventHeader {
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+            signer: Some(ctx.accounts.staker.key())
+        },
+        bank: ctx.accounts.bank.key(),
+        mint: bank.mint,
+        shares,
+        unlock_timestamp,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(shares: u64, ticket_index: u16)]
+pub struct LendingPoolInsuranceRequestUnstake<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(mut)]
+    pub staker: Signer<'info>,
+
+    #[account(
+        mut,
+        address = bank.load()?.insurance_share_mint,
+    )]
+    pub insurance_share_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: Token mint/authority are checked at burn
+    #[account(mut)]
+    pub staker_share_account: AccountInfo<'info>,
+
+    #[account(
+        init,
+        payer = staker,
+        space = 8 + std::mem::size_of::<InsuranceUnstakeTicket>(),
+        seeds = [
+            INSURANCE_UNSTAKE_TICKET_SEED.as_bytes(),
+            bank.key().as_ref(),
+            staker.key().as_ref(),
+            &ticket_index.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub unstake_ticket: AccountLoader<'info, InsuranceUnstakeTicket>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Claim an unstake ticket after its cooldown, paying out the current value of its shares from
+/// the insurance vault and closing the ticket.
+pub fn lending_pool_insurance_claim_unstake<'info>(
+    mut ctx: Context<'_, '_, 'info, 'info, LendingPoolInsuranceClaimUnstake<'info>>,
+) -> MarginfiResult {
+    let LendingPoolInsuranceClaimUnstake {
+        marginfi_group: marginfi_group_loader,
+        bank: bank_loader,
+        staker,
+        unstake_ticket: unstake_ticket_loader,
+        insurance_vault,
+        insurance_vault_authority,
+        destination_token_account,
+        token_program,
+    } = ctx.accounts;
+
+    let mut bank = bank_loader.load_mut()?;
+    let unstake_ticket = unstake_ticket_loader.load()?;
+    let clock = Clock::get()?;
+
+    check!(
+        clock.unix_timestamp >= unstake_ticket.unlock_timestamp,
+        MarginfiError::InsuranceUnstakeCooldownActive
+    );
+
+    let shares = unstake_ticket.shares;
+    let amount = bank.get_insurance_amount_for_shares(shares, insurance_vault.amount)?;
+
+    bank.insurance_pending_unstake_shares = bank
+        .insurance_pending_unstake_shares
+        .checked_sub(shares)
+        .ok_or_else(math_error!())?;
+
+    let maybe_bank_mint =
+        utils::maybe_take_bank_mint(&mut ctx.remaining_accounts, &bank, token_program.key)?;
+
+    bank.withdraw_spl_transfer(
+        amount,
+        insurance_vault.to_account_info(),
+        destination_token_account.to_account_info(),
+        insurance_vault_authority.to_account_info(),
+        maybe_bank_mint.as_ref(),
+        token_program.to_account_info(),
+        bank_signer!(
+            BankVaultType::Insurance,
+            bank_loader.key(),
+            bank.insurance_vault_authority_bump
+        ),
+        ctx.remaining_accounts,
+    )?;
+
+    emit!(LendingPoolBankInsuranceClaimUnstakeEvent {
+        header: GroupEventHeader {
+            marginfi_group: marginfi_group_loader.key(),
+            signer: Some(staker.key())
+        },
+        bank: bank_loader.key(),
+        mint: bank.mint,
+        shares,
+        amount,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolInsuranceClaimUnstake<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+
+    #[account(mut)]
+    pub staker: Signer<'info>,
+
+    #[account(
+        mut,
+        close = staker,
+        seeds = [
+            INSURANCE_UNSTAKE_TICKET_SEED.as_bytes(),
+            bank.key().as_ref(),
+            staker.key().as_ref(),
+            &unstake_ticket.load()?.ticket_index.to_le_bytes(),
+        ],
+        bump = unstake_ticket.load()?.bump,
+    )]
+    pub unstake_ticket: AccountLoader<'info, InsuranceUnstakeTicket>,
+
+    #[account(
+        mut,
+        seeds = [
+            INSURANCE_VAULT_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_bump
+    )]
+    pub insurance_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Seed constraint
+    #[account(
+        seeds = [
+            INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(),
+            bank.key().as_ref(),
+        ],
+        bump = bank.load()?.insurance_vault_authority_bump
+    )]
+    pub insurance_vault_authority: AccountInfo<'info>,
+
+    /// CHECK: Token mint is checked at transfer
+    #[account(mut)]
+    pub destination_token_account: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
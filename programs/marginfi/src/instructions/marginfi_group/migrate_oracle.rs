@@ -0,0 +1,202 @@
+use crate::{
+    check,
+    constants::ORACLE_MIGRATION_MAX_PRICE_DEVIATION,
+    events::{GroupEventHeader, LendingPoolBankMigrateOracleEvent},
+    math_error,
+    prelude::*,
+    state::{
+        marginfi_group::{Bank, OracleConfig},
+        price::{OraclePriceFeedAdapter, OraclePriceType, OracleSetup, PriceAdapter},
+    },
+};
+use anchor_lang::prelude::*;
+use fixed::types::I80F48;
+
+/// Migrate a bank from a deprecated oracle (Pyth legacy, Switchboard V2) to a Pyth push or
+/// Switchboard pull oracle.
+///
+/// Admin only
+///
+/// The real time price of the new oracle must be within `ORACLE_MIGRATION_MAX_PRICE_DEVIATION`
+/// of the legacy oracle price, so the migration must happen while the legacy oracle is still live
+/// and before the program is built with the `no-legacy-oracles` feature.
+///
+/// Expected remaining account schema
+/// [
+///    legacy_oracle_ai,
+///    new_oracle_ais...,
+///  ]
+pub fn lending_pool_migrate_oracle<'info>(
+    ctx: Context<'_, '_, 'info, 'info, LendingPoolMigrateOracle<'info>>,
+    oracle: OracleConfig,
+) -> MarginfiResult {
+    let mut bank = ctx.accounts.bank.load_mut()?;
+    let clock = Clock::get()?;
+
+    check_oracle_migration_setup(bank.config.oracle_setup, oracle.setup)?;
+
+    let legacy_oracle_ais_len = bank.config.oracle_setup.get_oracle_ais_len();
+    check!(
+        ctx.remaining_accounts.len() == legacy_oracle_ais_len + oracle.setup.get_oracle_ais_len(),
+        MarginfiError::InvalidOracleAccount
+    );
+    let (legacy_oracle_ais, new_oracle_ais) =
+        ctx.remaining_accounts.split_at(legacy_oracle_ais_len);
+
+    let legacy_price =
+        OraclePriceFeedAdapter::try_from_bank_config(&bank.config, legacy_oracle_ais, &clock)?
+            .get_price_of_type(OraclePriceType::RealTime, None)?;
+
+    let mut new_config = bank.config;
+    new_config.oracle_setup = oracle.setup;
+    new_config.oracle_keys = oracle.keys;
+
+    new_config.validate_oracle_setup(new_oracle_ais, &bank.mint)?;
+
+    let new_price =
+        OraclePriceFeedAdapter::try_from_bank_config(&new_config, new_oracle_ais, &clock)?
+            .get_price_of_type(OraclePriceType::RealTime, None)?;
+
+    check_oracle_migration_price(legacy_price, new_price)?;
+
+    bank.config.oracle_setup = oracle.setup;
+    bank.config.oracle_keys = oracle.keys;
+
+    emit!(LendingPoolBankMigrateOracleEvent {
+        header: GroupEventHeader {
+            marginfi_group: ctx.accounts.marginfi_group.key(),
+            signer: Some(*ctx.accounts.admin.key)
+        },
+        bank: ctx.accounts.bank.key(),
+        mint: bank.mint,
+        oracle,
+        legacy_price: legacy_price.to_num::<f64>(),
+        new_price: new_price.to_num::<f64>(),
+    });
+
+    Ok(())
+}
+
+fn check_oracle_migration_setup(
+    legacy_setup: OracleSetup,
+    new_setup: OracleSetup,
+) -> MarginfiResult {
+    check!(
+        legacy_setup.is_legacy(),
+        MarginfiError::InvalidOracleSetup,
+        "Bank oracle is not deprecated"
+    );
+    check!(
+        matches!(
+            new_setup,
+            OracleSetup::PythPushOracle | OracleSetup::SwitchboardPull
+        ),
+        MarginfiError::InvalidOracleSetup,
+        "Banks can only be migrated to Pyth push or Switchboard pull oracles"
+    );
+
+    Ok(())
+}
+
+fn check_oracle_migration_price(legacy_price: I80F48, new_price: I80F48) -> MarginfiResult {
+    let max_price_deviation = legacy_price
+        .checked_mul(ORACLE_MIGRATION_MAX_PRICE_DEVIATION)
+        .ok_or_else(math_error!())?;
+    let price_deviation = new_price
+        .checked_sub(legacy_price)
+        .ok_or_else(math_error!())?
+        .abs();
+
+    check!(
+        legacy_price > I80F48::ZERO && price_deviation <= max_price_deviation,
+        MarginfiError::OraclePriceMismatch,
+        "Legacy oracle price {}, new oracle price {}",
+        legacy_price,
+        new_price
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct LendingPoolMigrateOracle<'info> {
+    pub marginfi_group: AccountLoader<'info, MarginfiGroup>,
+
+    #[account(
+        address = marginfi_group.load()?.admin,
+    )]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = bank.load()?.group == marginfi_group.key(),
+    )]
+    pub bank: AccountLoader<'info, Bank>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::marginfi_group::BankConfig;
+    use fixed_macro::types::I80F48;
+
+    #[test]
+    fn oracle_migration_setup() {
+        for legacy_setup in [OracleSetup::PythLegacy, OracleSetup::SwitchboardV2] {
+            assert!(
+                check_oracle_migration_setup(legacy_setup, OracleSetup::PythPushOracle).is_ok()
+            );
+            assert!(
+                check_oracle_migration_setup(legacy_setup, OracleSetup::SwitchboardPull).is_ok()
+            );
+            assert!(check_oracle_migration_setup(legacy_setup, OracleSetup::Fixed).is_err());
+            assert!(check_oracle_migration_setup(legacy_setup, OracleSetup::PythLegacy).is_err());
+        }
+
+        assert!(check_oracle_migration_setup(
+            OracleSetup::PythPushOracle,
+            OracleSetup::SwitchboardPull
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn oracle_migration_price() {
+        let max_deviation = I80F48!(100) * ORACLE_MIGRATION_MAX_PRICE_DEVIATION;
+
+        assert!(check_oracle_migration_price(I80F48!(100), I80F48!(100)).is_ok());
+        assert!(check_oracle_migration_price(I80F48!(100), I80F48!(100) + max_deviation).is_ok());
+        assert!(check_oracle_migration_price(I80F48!(100), I80F48!(100) - max_deviation).is_ok());
+        assert!(check_oracle_migration_price(
+            I80F48!(100),
+            I80F48!(100) + max_deviation + I80F48!(0.01)
+        )
+        .is_err());
+        assert!(check_oracle_migration_price(I80F48::ZERO, I80F48::ZERO).is_err());
+    }
+
+    /// Legacy oracles can only be read before the program is built with `no-legacy-oracles`
+    #[test]
+    fn legacy_oracle_feature_gate() {
+        let bank_config = BankConfig {
+            oracle_setup: OracleSetup::PythLegacy,
+            ..Default::default()
+        };
+
+        let load_err =
+            OraclePriceFeedAdapter::try_from_bank_config(&bank_config, &[], &Clock::default())
+                .err()
+                .unwrap();
+        let validate_err =
+            OraclePriceFeedAdapter::validate_bank_config(&bank_config, &[], &Pubkey::default())
+                .unwrap_err();
+
+        let expected_err: Error = if cfg!(feature = "no-legacy-oracles") {
+            MarginfiError::DeprecatedOracleSetup.into()
+        } else {
+            MarginfiError::InvalidOracleAccount.into()
+        };
+        assert_eq!(load_err, expected_err);
+        assert_eq!(validate_err, expected_err);
+    }
+}
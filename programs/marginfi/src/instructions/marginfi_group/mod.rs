@@ -1,15 +1,29 @@
 mod accrue_bank_interest;
 mod add_pool;
+mod check_oracle_price;
 mod collect_bank_fees;
 mod configure;
 mod configure_bank;
+mod emissions_campaign;
+mod fund_group_insurance;
+mod handle_bankruptcies;
 mod handle_bankruptcy;
+mod init_group_insurance_fund;
 mod initialize;
+mod insurance_staking;
+mod migrate_oracle;
 
 pub use accrue_bank_interest::*;
 pub use add_pool::*;
+pub use check_oracle_price::*;
 pub use collect_bank_fees::*;
 pub use configure::*;
 pub use configure_bank::*;
+pub use emissions_campaign::*;
+pub use fund_group_insurance::*;
+pub use handle_bankruptcies::*;
 pub use handle_bankruptcy::*;
+pub use init_group_insurance_fund::*;
 pub use initialize::*;
+pub use insurance_staking::*;
+pub use migrate_oracle::*;
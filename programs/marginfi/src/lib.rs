@@ -10,7 +10,7 @@ pub mod utils;
 use anchor_lang::prelude::*;
 use instructions::*;
 use prelude::*;
-use state::marginfi_group::{BankConfigCompact, BankConfigOpt};
+use state::marginfi_group::{BankConfigCompact, BankConfigOpt, OracleConfig, WrappedI80F48};
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "mainnet-beta")] {
@@ -87,6 +87,78 @@ pub mod marginfi {
         )
     }
 
+    /// Set the start and end time of the bank emissions.
+    pub fn lending_pool_update_emissions_schedule(
+        ctx: Context<LendingPoolUpdateEmissionsSchedule>,
+        start_time: i64,
+        end_time: i64,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_update_emissions_schedule(ctx, start_time, end_time)
+    }
+
+    /// Withdraw the undistributed bank emissions after the end of the emissions schedule.
+    pub fn lending_pool_reclaim_emissions(
+        ctx: Context<LendingPoolReclaimEmissions>,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_reclaim_emissions(ctx)
+    }
+
+    /// Create a partner emissions campaign on a bank, next to the bank's own emissions.
+    pub fn lending_pool_create_emissions_campaign(
+        ctx: Context<LendingPoolCreateEmissionsCampaign>,
+        emissions_flag: u64,
+        emissions_rate: u64,
+        total_emissions: u64,
+        start_time: i64,
+        end_time: i64,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_create_emissions_campaign(
+            ctx,
+            emissions_flag,
+            emissions_rate,
+            total_emissions,
+            start_time,
+            end_time,
+        )
+    }
+
+    /// Withdraw the unclaimed emissions of a campaign after its end.
+    pub fn lending_pool_reclaim_campaign_emissions(
+        ctx: Context<LendingPoolReclaimCampaignEmissions>,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_reclaim_campaign_emissions(ctx)
+    }
+
+    /// Move a bank from a deprecated oracle to a Pyth push or Switchboard pull oracle.
+    pub fn lending_pool_migrate_oracle<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolMigrateOracle<'info>>,
+        oracle: OracleConfig,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_migrate_oracle(ctx, oracle)
+    }
+
+    /// Check the oracle price of a bank against its price band, returns the real time price.
+    pub fn lending_pool_check_oracle_price<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolCheckOraclePrice<'info>>,
+    ) -> MarginfiResult<WrappedI80F48> {
+        marginfi_group::lending_pool_check_oracle_price(ctx)
+    }
+
+    /// Initialize the group insurance fund vault, used to cover bad debt of banks holding its mint.
+    pub fn lending_pool_init_group_insurance_fund(
+        ctx: Context<LendingPoolInitGroupInsuranceFund>,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_init_group_insurance_fund(ctx)
+    }
+
+    /// Move funds from a bank insurance vault to the group insurance fund.
+    pub fn lending_pool_fund_group_insurance<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolFundGroupInsurance<'info>>,
+        amount: u64,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_fund_group_insurance(ctx, amount)
+    }
+
     /// Handle bad debt of a bankrupt marginfi account for a given bank.
     pub fn lending_pool_handle_bankruptcy<'info>(
         ctx: Context<'_, '_, 'info, 'info, LendingPoolHandleBankruptcy<'info>>,
@@ -94,6 +166,13 @@ pub mod marginfi {
         marginfi_group::lending_pool_handle_bankruptcy(ctx)
     }
 
+    /// Handle bad debt of a bankrupt marginfi account for all of its liability banks at once.
+    pub fn lending_pool_handle_bankruptcies<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolHandleBankruptcies<'info>>,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_handle_bankruptcies(ctx)
+    }
+
     // User instructions
 
     /// Initialize a marginfi account for a given group
@@ -101,6 +180,24 @@ pub mod marginfi {
         marginfi_account::initialize_account(ctx)
     }
 
+    /// Initialize a marginfi account at a PDA derived from the group, authority and index
+    pub fn marginfi_account_initialize_pda(
+        ctx: Context<MarginfiAccountInitializePda>,
+        account_index: u16,
+    ) -> MarginfiResult {
+        marginfi_account::initialize_account_pda(ctx, account_index)
+    }
+
+    /// Grant a limited-permission delegate on a marginfi account, or remove it
+    pub fn marginfi_account_set_delegate(
+        ctx: Context<MarginfiAccountSetDelegate>,
+        delegate: Pubkey,
+        permissions: u64,
+        expiry: i64,
+    ) -> MarginfiResult {
+        marginfi_account::set_account_delegate(ctx, delegate, permissions, expiry)
+    }
+
     pub fn lending_account_deposit<'info>(
         ctx: Context<'_, '_, 'info, 'info, LendingAccountDeposit<'info>>,
         amount: u64,
@@ -116,6 +213,14 @@ pub mod marginfi {
         marginfi_account::lending_account_repay(ctx, amount, repay_all)
     }
 
+    /// Repay bad debt written off by a bankruptcy back to the bank insurance vault
+    pub fn lending_account_recover_bad_debt<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountRecoverBadDebt<'info>>,
+        amount: u64,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_recover_bad_debt(ctx, amount)
+    }
+
     pub fn lending_account_withdraw<'info>(
         ctx: Context<'_, '_, 'info, 'info, LendingAccountWithdraw<'info>>,
         amount: u64,
@@ -143,12 +248,51 @@ pub mod marginfi {
         marginfi_account::lending_account_withdraw_emissions(ctx)
     }
 
+    /// Withdraw the emissions of several balances, see the instruction for the remaining
+    /// account schema.
+    pub fn lending_account_withdraw_emissions_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountWithdrawEmissionsBatch<'info>>,
+        bumps: Vec<EmissionsBatchBumps>,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_withdraw_emissions_batch(ctx, bumps)
+    }
+
+    /// Set the emissions claim delegate and auto-compound mode of an account.
+    pub fn lending_account_configure_emissions(
+        ctx: Context<LendingAccountConfigureEmissions>,
+        claim_delegate: Option<Pubkey>,
+        auto_compound: Option<bool>,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_configure_emissions(ctx, claim_delegate, auto_compound)
+    }
+
+    /// Deposit the emissions of an auto-compounding account back into its balance.
+    pub fn lending_account_compound_emissions<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountCompoundEmissions<'info>>,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_compound_emissions(ctx)
+    }
+
     pub fn lending_account_settle_emissions(
         ctx: Context<LendingAccountSettleEmissions>,
     ) -> MarginfiResult {
         marginfi_account::lending_account_settle_emissions(ctx)
     }
 
+    /// Opt a marginfi account into an emissions campaign.
+    pub fn lending_account_init_campaign_position(
+        ctx: Context<LendingAccountInitCampaignPosition>,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_init_campaign_position(ctx)
+    }
+
+    /// Claim the accrued emissions of a campaign position.
+    pub fn lending_account_claim_campaign_emissions(
+        ctx: Context<LendingAccountClaimCampaignEmissions>,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_claim_campaign_emissions(ctx)
+    }
+
     /// Liquidate a lending account balance of an unhealthy marginfi account
     pub fn lending_account_liquidate<'info>(
         ctx: Context<'_, '_, 'info, 'info, LendingAccountLiquidate<'info>>,
@@ -157,8 +301,50 @@ pub mod marginfi {
         marginfi_account::lending_account_liquidate(ctx, asset_amount)
     }
 
-    pub fn lending_account_start_flashloan(
-        ctx: Context<LendingAccountStartFlashloan>,
+    /// Deleverage a borrower of a bank whose liabilities exceed its assets
+    pub fn lending_account_auto_deleverage<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountAutoDeleverage<'info>>,
+        liab_amount: u64,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_auto_deleverage(ctx, liab_amount)
+    }
+
+    /// Create a conditional order repaying a liability with collateral once the account
+    /// maintenance health crosses a trigger
+    pub fn lending_account_create_conditional_order(
+        ctx: Context<LendingAccountCreateConditionalOrder>,
+        order_index: u16,
+        trigger: u8,
+        trigger_health: WrappedI80F48,
+        liab_amount: u64,
+        keeper_fee_bps: u16,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_create_conditional_order(
+            ctx,
+            order_index,
+            trigger,
+            trigger_health,
+            liab_amount,
+            keeper_fee_bps,
+        )
+    }
+
+    pub fn lending_account_cancel_conditional_order(
+        ctx: Context<LendingAccountCancelConditionalOrder>,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_cancel_conditional_order(ctx)
+    }
+
+    /// Permissionless execution of a triggered conditional order by a keeper
+    pub fn lending_account_execute_conditional_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountExecuteConditionalOrder<'info>>,
+        liab_amount: u64,
+    ) -> MarginfiResult {
+        marginfi_account::lending_account_execute_conditional_order(ctx, liab_amount)
+    }
+
+    pub fn lending_account_start_flashloan<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingAccountStartFlashloan<'info>>,
         end_index: u64,
     ) -> MarginfiResult {
         marginfi_account::lending_account_start_flashloan(ctx, end_index)
@@ -183,6 +369,20 @@ pub mod marginfi {
         marginfi_group::lending_pool_collect_bank_fees(ctx)
     }
 
+    /// Book the outstanding group fees of a bank as a deposit of the group fee marginfi account
+    pub fn lending_pool_collect_bank_fees_as_deposit(
+        ctx: Context<LendingPoolCollectBankFeesAsDeposit>,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_collect_bank_fees_as_deposit(ctx)
+    }
+
+    /// Split the collected group fees of a bank between the group fee destinations
+    pub fn lending_pool_distribute_bank_fees<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolDistributeBankFees<'info>>,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_distribute_bank_fees(ctx)
+    }
+
     pub fn lending_pool_withdraw_fees<'info>(
         ctx: Context<'_, '_, 'info, 'info, LendingPoolWithdrawFees<'info>>,
         amount: u64,
@@ -197,6 +397,35 @@ pub mod marginfi {
         marginfi_group::lending_pool_withdraw_insurance(ctx, amount)
     }
 
+    /// Create the insurance share mint of a bank, letting external stakers join its insurance vault.
+    pub fn lending_pool_init_insurance_staking(
+        ctx: Context<LendingPoolInitInsuranceStaking>,
+        unstake_cooldown: u64,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_init_insurance_staking(ctx, unstake_cooldown)
+    }
+
+    pub fn lending_pool_insurance_stake<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolInsuranceStake<'info>>,
+        amount: u64,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_insurance_stake(ctx, amount)
+    }
+
+    pub fn lending_pool_insurance_request_unstake(
+        ctx: Context<LendingPoolInsuranceRequestUnstake>,
+        shares: u64,
+        ticket_index: u16,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_insurance_request_unstake(ctx, shares, ticket_index)
+    }
+
+    pub fn lending_pool_insurance_claim_unstake<'info>(
+        ctx: Context<'_, '_, 'info, 'info, LendingPoolInsuranceClaimUnstake<'info>>,
+    ) -> MarginfiResult {
+        marginfi_group::lending_pool_insurance_claim_unstake(ctx)
+    }
+
     pub fn set_account_flag(ctx: Context<SetAccountFlag>, flag: u64) -> MarginfiResult {
         marginfi_group::set_account_flag(ctx, flag)
     }
@@ -0,0 +1,235 @@
+use super::{
+    marginfi_account::{calc_amount, calc_value},
+    marginfi_group::WrappedI80F48,
+};
+use crate::{
+    assert_struct_align, assert_struct_size, check,
+    constants::CONDITIONAL_ORDER_MAX_KEEPER_FEE_BPS,
+    math_error,
+    prelude::{MarginfiError, MarginfiResult},
+};
+use anchor_lang::prelude::*;
+use fixed::types::I80F48;
+#[cfg(any(feature = "test", feature = "client"))]
+use type_layout::TypeLayout;
+
+/// Execute when the maintenance health of the account drops below `trigger_health` (stop-loss)
+pub const CONDITIONAL_ORDER_TRIGGER_HEALTH_BELOW: u8 = 0;
+/// Execute when the maintenance health of the account rises above `trigger_health` (take-profit)
+pub const CONDITIONAL_ORDER_TRIGGER_HEALTH_ABOVE: u8 = 1;
+
+assert_struct_size!(ConditionalOrder, 256);
+assert_struct_align!(ConditionalOrder, 8);
+/// Order of a marginfi account to repay a liability with one of its collaterals once its
+/// maintenance health crosses `trigger_health`.
+///
+/// Orders are executed by permissionless keepers, see `lending_account_execute_conditional_order`.
+/// The keeper repays the liability with its own tokens, the program does not swap the collateral.
+#[account(zero_copy(unsafe))]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(Debug, PartialEq, Eq, TypeLayout)
+)]
+#[derive(Default)]
+pub struct ConditionalOrder {
+    pub marginfi_account: Pubkey,
+    /// Bank of the collateral sold to repay the liability
+    pub asset_bank: Pubkey,
+    /// Bank of the liability repaid
+    pub liab_bank: Pubkey,
+    /// USD value of the maintenance health at which the order triggers, see `trigger`
+    pub trigger_health: WrappedI80F48,
+    /// Native liability amount left to repay by executions of the order
+    pub liab_amount_remaining: u64,
+    /// Fee paid in collateral to the keeper, in basis points of the repaid liability value
+    pub keeper_fee_bps: u16,
+    pub order_index: u16,
+    /// `CONDITIONAL_ORDER_TRIGGER_HEALTH_BELOW` or `CONDITIONAL_ORDER_TRIGGER_HEALTH_ABOVE`
+    pub trigger: u8,
+    pub bump: u8,
+    pub _pad0: [u8; 2],
+    pub _padding: [u64; 16],
+}
+
+impl ConditionalOrder {
+    pub fn validate(&self) -> MarginfiResult {
+        check!(
+            self.trigger == CONDITIONAL_ORDER_TRIGGER_HEALTH_BELOW
+                || self.trigger == CONDITIONAL_ORDER_TRIGGER_HEALTH_ABOVE,
+            MarginfiError::InvalidConditionalOrder,
+            "Invalid trigger"
+        );
+        check!(
+            self.asset_bank != self.liab_bank,
+            MarginfiError::InvalidConditionalOrder,
+            "Asset and liability bank cannot be the same"
+        );
+        check!(
+            self.liab_amount_remaining > 0,
+            MarginfiError::InvalidConditionalOrder,
+            "Liability amount must be positive"
+        );
+        check!(
+            self.keeper_fee_bps <= CONDITIONAL_ORDER_MAX_KEEPER_FEE_BPS,
+            MarginfiError::InvalidConditionalOrder,
+            "Keeper fee above {} bps",
+            CONDITIONAL_ORDER_MAX_KEEPER_FEE_BPS
+        );
+
+        Ok(())
+    }
+
+    pub fn is_triggered(&self, maintenance_health: I80F48) -> bool {
+        let trigger_health = I80F48::from(self.trigger_health);
+
+        match self.trigger {
+            CONDITIONAL_ORDER_TRIGGER_HEALTH_BELOW => maintenance_health < trigger_health,
+            CONDITIONAL_ORDER_TRIGGER_HEALTH_ABOVE => maintenance_health > trigger_health,
+            _ => false,
+        }
+    }
+
+    /// Liability amount repaid and collateral amount paid to the keeper for an execution
+    /// repaying up to `liab_amount`, capped at the account `liability` and at the amount left in
+    /// the order.
+    ///
+    /// Like in liquidations, the collateral is priced with its low price and the liability with
+    /// its high price, so oracle uncertainty is borne by the account and not by the keeper.
+    pub fn get_execution_amounts(
+        &self,
+        liab_amount: u64,
+        liability: I80F48,
+        liab_price_high: I80F48,
+        liab_mint_decimals: u8,
+        asset_price_low: I80F48,
+        asset_mint_decimals: u8,
+    ) -> MarginfiResult<(u64, I80F48)> {
+        let liab_amount = I80F48::from_num(liab_amount)
+            .min(I80F48::from_num(self.liab_amount_remaining))
+            .min(liability)
+            .checked_to_num::<u64>()
+            .ok_or_else(math_error!())?;
+
+        check!(
+            liab_amount > 0,
+            MarginfiError::InvalidConditionalOrder,
+            "Nothing to repay"
+        );
+
+        let asset_amount = self.get_keeper_asset_amount(calc_amount(
+            calc_value(
+                I80F48::from_num(liab_amount),
+                liab_price_high,
+                liab_mint_decimals,
+                None,
+            )?,
+            asset_price_low,
+            asset_mint_decimals,
+        )?)?;
+
+        Ok((liab_amount, asset_amount))
+    }
+
+    /// Collateral amount paid to the keeper for collateral worth `asset_amount` of repaid
+    /// liability, including the keeper fee.
+    pub fn get_keeper_asset_amount(&self, asset_amount: I80F48) -> MarginfiResult<I80F48> {
+        let keeper_fee = asset_amount
+            .checked_mul(I80F48::from_num(self.keeper_fee_bps))
+            .ok_or_else(math_error!())?
+            .checked_div(I80F48::from_num(10_000))
+            .ok_or_else(math_error!())?;
+
+        asset_amount
+            .checked_add(keeper_fee)
+            .ok_or_else(math_error!())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixed_macro::types::I80F48;
+
+    #[test]
+    fn conditional_order_trigger_and_fee() {
+        let mut order = ConditionalOrder {
+            asset_bank: Pubkey::new_unique(),
+            liab_bank: Pubkey::new_unique(),
+            trigger_health: I80F48!(100).into(),
+            liab_amount_remaining: 1_000,
+            keeper_fee_bps: 50,
+            trigger: CONDITIONAL_ORDER_TRIGGER_HEALTH_BELOW,
+            ..Default::default()
+        };
+        assert!(order.validate().is_ok());
+
+        assert!(order.is_triggered(I80F48!(99.9)));
+        assert!(!order.is_triggered(I80F48!(100)));
+
+        order.trigger = CONDITIONAL_ORDER_TRIGGER_HEALTH_ABOVE;
+        assert!(order.is_triggered(I80F48!(100.1)));
+        assert!(!order.is_triggered(I80F48!(100)));
+
+        assert_eq!(
+            order.get_keeper_asset_amount(I80F48!(2_000)).unwrap(),
+            I80F48!(2_010)
+        );
+
+        order.keeper_fee_bps = CONDITIONAL_ORDER_MAX_KEEPER_FEE_BPS + 1;
+        assert!(order.validate().is_err());
+
+        order.keeper_fee_bps = 50;
+        order.trigger = 2;
+        assert!(order.validate().is_err());
+        assert!(!order.is_triggered(I80F48!(0)));
+
+        order.trigger = CONDITIONAL_ORDER_TRIGGER_HEALTH_BELOW;
+        order.liab_bank = order.asset_bank;
+        assert!(order.validate().is_err());
+    }
+
+    #[test]
+    fn conditional_order_execution_amounts() {
+        let order = ConditionalOrder {
+            liab_amount_remaining: 1_000_000,
+            keeper_fee_bps: 50,
+            ..Default::default()
+        };
+
+        // 1 liability token at 2 buys half a collateral token at 4, plus the keeper fee
+        assert_eq!(
+            order
+                .get_execution_amounts(1_000_000, I80F48!(5_000_000), I80F48!(2), 6, I80F48!(4), 6)
+                .unwrap(),
+            (1_000_000, I80F48!(502_500))
+        );
+
+        // Mint decimals are accounted for
+        assert_eq!(
+            order
+                .get_execution_amounts(1_000_000, I80F48!(5_000_000), I80F48!(2), 6, I80F48!(4), 9)
+                .unwrap(),
+            (1_000_000, I80F48!(502_500_000))
+        );
+
+        // Capped at the amount left in the order and at the account liability
+        assert_eq!(
+            order
+                .get_execution_amounts(5_000_000, I80F48!(5_000_000), I80F48!(1), 6, I80F48!(1), 6)
+                .unwrap(),
+            (1_000_000, I80F48!(1_005_000))
+        );
+        assert_eq!(
+            order
+                .get_execution_amounts(1_000_000, I80F48!(800_000), I80F48!(1), 6, I80F48!(1), 6)
+                .unwrap(),
+            (800_000, I80F48!(804_000))
+        );
+
+        // Nothing left to repay
+        assert!(order
+            .get_execution_amounts(1_000_000, I80F48::ZERO, I80F48!(1), 6, I80F48!(1), 6)
+            .is_err());
+    }
+}
@@ -0,0 +1,390 @@
+use super::{
+    marginfi_account::{calc_emissions, Balance, BalanceSide},
+    marginfi_group::{Bank, WrappedI80F48},
+};
+use crate::{
+    assert_struct_align, assert_struct_size, check,
+    constants::{
+        EMISSIONS_CAMPAIGN_RECLAIM_DELAY, EMISSIONS_FLAG_BORROW_ACTIVE,
+        EMISSIONS_FLAG_LENDING_ACTIVE,
+    },
+    debug, math_error,
+    prelude::{MarginfiError, MarginfiResult},
+};
+use anchor_lang::prelude::*;
+use fixed::types::I80F48;
+use std::cmp::{max, min};
+#[cfg(any(feature = "test", feature = "client"))]
+use type_layout::TypeLayout;
+
+assert_struct_size!(EmissionsCampaign, 312);
+assert_struct_align!(EmissionsCampaign, 8);
+/// Emissions of a partner token to the lenders or borrowers of a bank, running next to the
+/// bank's own emissions and any other campaign.
+///
+/// Emissions use the same accounting as the bank emissions, and are checkpointed per marginfi
+/// account in an `EmissionsCampaignPosition`.
+#[account(zero_copy(unsafe))]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(Debug, PartialEq, Eq, TypeLayout)
+)]
+#[derive(Default)]
+pub struct EmissionsCampaign {
+    pub group: Pubkey,
+    pub bank: Pubkey,
+    /// Creator and funder of the campaign
+    pub authority: Pubkey,
+    pub emissions_mint: Pubkey,
+    /// `EMISSIONS_FLAG_LENDING_ACTIVE` or `EMISSIONS_FLAG_BORROW_ACTIVE`
+    pub emissions_flag: u64,
+    /// Number of emitted tokens (emissions_mint) per 1e(bank.mint_decimal) tokens (bank mint) (native amount) per 1 YEAR.
+    pub emissions_rate: u64,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub emissions_remaining: WrappedI80F48,
+    pub vault_bump: u8,
+    pub vault_authority_bump: u8,
+    pub _pad0: [u8; 6],
+    pub _padding: [u64; 16],
+}
+
+impl EmissionsCampaign {
+    pub fn validate_schedule(
+        emissions_flag: u64,
+        start_time: i64,
+        end_time: i64,
+        current_timestamp: i64,
+    ) -> MarginfiResult {
+        check!(
+            emissions_flag == EMISSIONS_FLAG_LENDING_ACTIVE
+                || emissions_flag == EMISSIONS_FLAG_BORROW_ACTIVE,
+            MarginfiError::InvalidEmissionsCampaign,
+            "Campaign must emit to either lenders or borrowers"
+        );
+        check!(
+            start_time < end_time && end_time > current_timestamp,
+            MarginfiError::InvalidEmissionsCampaign,
+            "Invalid campaign schedule"
+        );
+
+        Ok(())
+    }
+
+    /// Shares of `balance` on the side the campaign emits to, zero for the other side.
+    pub fn get_balance_shares(&self, balance: Option<&Balance>) -> I80F48 {
+        match balance.and_then(|b| b.get_side().map(|side| (b, side))) {
+            Some((balance, BalanceSide::Assets))
+                if self.emissions_flag == EMISSIONS_FLAG_LENDING_ACTIVE =>
+            {
+                balance.asset_shares.into()
+            }
+            Some((balance, BalanceSide::Liabilities))
+                if self.emissions_flag == EMISSIONS_FLAG_BORROW_ACTIVE =>
+            {
+                balance.liability_shares.into()
+            }
+            _ => I80F48::ZERO,
+        }
+    }
+
+    /// Accrue the emissions of `balance` since the last update of `position`, capped by the
+    /// emissions remaining in the campaign.
+    ///
+    /// Only the time between the campaign start and end is accounted for. Emissions accrue on the
+    /// smaller of the shares checkpointed at the last update and the current shares, so a deposit
+    /// only earns from the next update of the position and can not be flashed in before a claim.
+    pub fn accrue_position(
+        &mut self,
+        position: &mut EmissionsCampaignPosition,
+        bank: &Bank,
+        balance: Option<&Balance>,
+        current_timestamp: i64,
+    ) -> MarginfiResult {
+        let period_start = max(position.last_update, self.start_time);
+        let period_end = min(current_timestamp, self.end_time);
+
+        let current_shares = self.get_balance_shares(balance);
+        let shares = min(current_shares, I80F48::from(position.balance_shares));
+
+        if shares > I80F48::ZERO && period_end > period_start {
+            let balance_amount = if self.emissions_flag == EMISSIONS_FLAG_LENDING_ACTIVE {
+                bank.get_asset_amount(shares)?
+            } else {
+                bank.get_liability_amount(shares)?
+            };
+            let period = I80F48::from_num(
+                period_end
+                    .checked_sub(period_start)
+                    .ok_or_else(math_error!())?,
+            );
+            let emissions = calc_emissions(
+                period,
+                balance_amount,
+                bank.mint_decimals as usize,
+                I80F48::from_num(self.emissions_rate),
+            )?;
+            let emissions_real = min(emissions, I80F48::from(self.emissions_remaining));
+
+            debug!(
+                "Campaign emissions: {} ({} calculated) for period {}s",
+                emissions_real, emissions, period
+            );
+
+            position.emissions_outstanding = I80F48::from(position.emissions_outstanding)
+                .checked_add(emissions_real)
+                .ok_or_else(math_error!())?
+                .into();
+            self.emissions_remaining = I80F48::from(self.emissions_remaining)
+                .checked_sub(emissions_real)
+                .ok_or_else(math_error!())?
+                .into();
+        }
+
+        position.last_update = max(position.last_update, current_timestamp);
+        position.balance_shares = current_shares.into();
+
+        Ok(())
+    }
+
+    /// Take the whole token amount out of the remaining emissions once users had
+    /// `EMISSIONS_CAMPAIGN_RECLAIM_DELAY` after the end of the campaign to claim, capped by the
+    /// vault balance.
+    pub fn reclaim_remaining_emissions(
+        &mut self,
+        current_timestamp: i64,
+        vault_amount: u64,
+    ) -> MarginfiResult<u64> {
+        check!(
+            current_timestamp
+                >= self
+                    .end_time
+                    .checked_add(EMISSIONS_CAMPAIGN_RECLAIM_DELAY)
+                    .ok_or_else(math_error!())?,
+            MarginfiError::EmissionsNotEnded
+        );
+
+        let emissions_remaining = I80F48::from(self.emissions_remaining);
+        let reclaim_amount = emissions_remaining
+            .checked_floor()
+            .ok_or_else(math_error!())?
+            .checked_to_num::<u64>()
+            .ok_or_else(math_error!())?
+            .min(vault_amount);
+
+        self.emissions_remaining = emissions_remaining
+            .checked_sub(I80F48::from_num(reclaim_amount))
+            .ok_or_else(math_error!())?
+            .into();
+
+        Ok(reclaim_amount)
+    }
+}
+
+assert_struct_size!(EmissionsCampaignPosition, 160);
+assert_struct_align!(EmissionsCampaignPosition, 8);
+/// Emissions checkpoint of a marginfi account in an `EmissionsCampaign`.
+///
+/// Emissions accrue from the creation of the position, so accounts have to opt into a campaign.
+#[account(zero_copy(unsafe))]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(Debug, PartialEq, Eq, TypeLayout)
+)]
+#[derive(Default)]
+pub struct EmissionsCampaignPosition {
+    pub campaign: Pubkey,                     // 32
+    pub marginfi_account: Pubkey,             // 32
+    pub emissions_outstanding: WrappedI80F48, // 16
+    pub last_update: i64,                     // 8
+    pub bump: u8,                             // 1
+    pub _pad0: [u8; 7],                       // 7
+    /// Balance shares on the campaign side at `last_update`
+    pub balance_shares: WrappedI80F48, // 16
+    pub _padding: [u64; 6],                   // 48
+}
+
+impl EmissionsCampaignPosition {
+    /// Take the whole token amount out of the outstanding emissions, keeping the remainder.
+    pub fn settle_emissions_and_get_transfer_amount(&mut self) -> MarginfiResult<u64> {
+        let outstanding_emissions = I80F48::from(self.emissions_outstanding);
+        let outstanding_emissions_floored = outstanding_emissions
+            .checked_floor()
+            .ok_or_else(math_error!())?;
+
+        self.emissions_outstanding = outstanding_emissions
+            .checked_sub(outstanding_emissions_floored)
+            .ok_or_else(math_error!())?
+            .into();
+
+        outstanding_emissions_floored
+            .checked_to_num::<u64>()
+            .ok_or_else(math_error!())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixed_macro::types::I80F48;
+
+    #[test]
+    fn campaign_accrual_is_bounded_by_schedule_and_budget() {
+        let bank = Bank {
+            asset_share_value: I80F48::ONE.into(),
+            liability_share_value: I80F48::ONE.into(),
+            ..Default::default()
+        };
+        let balance = Balance {
+            active: true,
+            bank_pk: Pubkey::default(),
+            flags: 0,
+            _pad0: [0; 6],
+            asset_shares: I80F48!(1_000).into(),
+            liability_shares: I80F48::ZERO.into(),
+            emissions_outstanding: I80F48::ZERO.into(),
+            emissions_index_checkpoint: I80F48::ZERO.into(),
+        };
+        let year = 31_536_000;
+
+        let mut campaign = EmissionsCampaign {
+            emissions_flag: EMISSIONS_FLAG_LENDING_ACTIVE,
+            emissions_rate: 1_000,
+            start_time: 100,
+            end_time: 100 + year,
+            emissions_remaining: I80F48!(600_000).into(),
+            ..Default::default()
+        };
+        let mut position = EmissionsCampaignPosition::default();
+
+        // Nothing accrues before the start
+        campaign
+            .accrue_position(&mut position, &bank, Some(&balance), 100)
+            .unwrap();
+        assert_eq!(I80F48::from(position.emissions_outstanding), I80F48::ZERO);
+        assert_eq!(position.last_update, 100);
+
+        // Half a year accrues half of the yearly emissions
+        campaign
+            .accrue_position(&mut position, &bank, Some(&balance), 100 + year / 2)
+            .unwrap();
+        assert_eq!(
+            I80F48::from(position.emissions_outstanding),
+            I80F48!(500_000)
+        );
+
+        // Emissions stop at the end of the campaign, and are capped by the remaining budget
+        campaign
+            .accrue_position(&mut position, &bank, Some(&balance), 100 + 2 * year)
+            .unwrap();
+        assert_eq!(
+            I80F48::from(position.emissions_outstanding),
+            I80F48!(600_000)
+        );
+        assert_eq!(I80F48::from(campaign.emissions_remaining), I80F48::ZERO);
+
+        assert_eq!(
+            position.settle_emissions_and_get_transfer_amount().unwrap(),
+            600_000
+        );
+
+        // Borrow campaigns don't emit to lenders
+        let mut campaign = EmissionsCampaign {
+            emissions_flag: EMISSIONS_FLAG_BORROW_ACTIVE,
+            ..campaign
+        };
+        campaign.emissions_remaining = I80F48!(600_000).into();
+        let mut position = EmissionsCampaignPosition::default();
+        campaign
+            .accrue_position(&mut position, &bank, Some(&balance), 100 + year / 2)
+            .unwrap();
+        assert_eq!(I80F48::from(position.emissions_outstanding), I80F48::ZERO);
+    }
+
+    #[test]
+    fn campaign_accrual_ignores_flash_deposits() {
+        let bank = Bank {
+            asset_share_value: I80F48::ONE.into(),
+            ..Default::default()
+        };
+        let small_balance = Balance {
+            active: true,
+            asset_shares: I80F48!(1_000).into(),
+            ..Balance::empty_deactivated()
+        };
+        let large_balance = Balance {
+            asset_shares: I80F48!(1_000_000).into(),
+            ..small_balance
+        };
+        let year = 31_536_000;
+
+        let mut campaign = EmissionsCampaign {
+            emissions_flag: EMISSIONS_FLAG_LENDING_ACTIVE,
+            emissions_rate: 1_000,
+            end_time: 2 * year,
+            emissions_remaining: I80F48!(1_000_000_000).into(),
+            ..Default::default()
+        };
+        let mut position = EmissionsCampaignPosition::default();
+
+        campaign
+            .accrue_position(&mut position, &bank, Some(&small_balance), 0)
+            .unwrap();
+
+        // A deposit right before the claim only earns on the checkpointed balance
+        campaign
+            .accrue_position(&mut position, &bank, Some(&large_balance), year)
+            .unwrap();
+        assert_eq!(
+            I80F48::from(position.emissions_outstanding),
+            I80F48!(1_000_000)
+        );
+        assert_eq!(I80F48::from(position.balance_shares), I80F48!(1_000_000));
+
+        // Withdrawing before the next claim only earns on the remaining balance
+        campaign
+            .accrue_position(&mut position, &bank, Some(&small_balance), 2 * year)
+            .unwrap();
+        assert_eq!(
+            I80F48::from(position.emissions_outstanding),
+            I80F48!(2_000_000)
+        );
+    }
+
+    #[test]
+    fn campaign_reclaim() {
+        let mut campaign = EmissionsCampaign {
+            end_time: 1_000,
+            emissions_remaining: I80F48!(500.5).into(),
+            ..Default::default()
+        };
+
+        // Users have time to claim after the end of the campaign
+        assert_eq!(
+            campaign
+                .reclaim_remaining_emissions(1_000 + EMISSIONS_CAMPAIGN_RECLAIM_DELAY - 1, 800)
+                .unwrap_err(),
+            MarginfiError::EmissionsNotEnded.into()
+        );
+
+        assert_eq!(
+            campaign
+                .reclaim_remaining_emissions(1_000 + EMISSIONS_CAMPAIGN_RECLAIM_DELAY, 800)
+                .unwrap(),
+            500
+        );
+        assert_eq!(I80F48::from(campaign.emissions_remaining), I80F48!(0.5));
+
+        // Capped by the vault balance
+        campaign.emissions_remaining = I80F48!(500).into();
+        assert_eq!(
+            campaign
+                .reclaim_remaining_emissions(1_000 + EMISSIONS_CAMPAIGN_RECLAIM_DELAY, 300)
+                .unwrap(),
+            300
+        );
+        assert_eq!(I80F48::from(campaign.emissions_remaining), I80F48!(200));
+    }
+}
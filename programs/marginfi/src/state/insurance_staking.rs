@@ -0,0 +1,29 @@
+use crate::{assert_struct_align, assert_struct_size};
+use anchor_lang::prelude::*;
+#[cfg(any(feature = "test", feature = "client"))]
+use type_layout::TypeLayout;
+
+assert_struct_size!(InsuranceUnstakeTicket, 152);
+assert_struct_align!(InsuranceUnstakeTicket, 8);
+/// Pending unstake of insurance vault shares.
+///
+/// The shares are burned when the unstake is requested and keep absorbing insurance vault losses
+/// until the ticket is claimed, after `Bank::insurance_unstake_cooldown` seconds.
+/// A staker can have several pending tickets per bank, told apart by `ticket_index`.
+#[account(zero_copy(unsafe))]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(Debug, PartialEq, Eq, TypeLayout)
+)]
+pub struct InsuranceUnstakeTicket {
+    pub bank: Pubkey,          // 32
+    pub staker: Pubkey,        // 32
+    pub shares: u64,           // 8
+    pub unlock_timestamp: i64, // 8
+    /// Index used to derive the ticket address, any unused index can be picked by the staker.
+    pub ticket_index: u16, // 2
+    pub bump: u8,              // 1
+    pub _pad0: [u8; 5],        // 5
+    pub _padding: [u64; 8],    // 64
+}
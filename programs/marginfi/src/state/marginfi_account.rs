@@ -5,8 +5,8 @@ use super::{
 use crate::{
     assert_struct_align, assert_struct_size, check,
     constants::{
-        BANKRUPT_THRESHOLD, EMISSIONS_FLAG_BORROW_ACTIVE, EMISSIONS_FLAG_LENDING_ACTIVE,
-        EMPTY_BALANCE_THRESHOLD, EXP_10_I80F48, MIN_EMISSIONS_START_TIME, SECONDS_PER_YEAR,
+        EMISSIONS_FLAG_BORROW_ACTIVE, EMISSIONS_FLAG_LENDING_ACTIVE, EMPTY_BALANCE_THRESHOLD,
+        EXP_10_I80F48, MARGINFI_ACCOUNT_SEED, MIN_EMISSIONS_START_TIME, SECONDS_PER_YEAR,
         ZERO_AMOUNT_THRESHOLD,
     },
     debug, math_error,
@@ -31,6 +31,7 @@ assert_struct_align!(MarginfiAccount, 8);
     any(feature = "test", feature = "client"),
     derive(Debug, PartialEq, Eq, TypeLayout)
 )]
+#[derive(Default)]
 pub struct MarginfiAccount {
     pub group: Pubkey,                   // 32
     pub authority: Pubkey,               // 32
@@ -42,13 +43,101 @@ pub struct MarginfiAccount {
     /// - DISABLED_FLAG = 1 << 0 = 1 - This flag indicates that the account is disabled,
     /// and no further actions can be taken on it.
     pub account_flags: u64, // 8
-    pub _padding: [u64; 63],             // 504
+    /// Bad debt written off by bankruptcy handling, per bank.
+    /// Cleared as the bad debt is recovered, see `lending_account_recover_bad_debt`.
+    pub bad_debt_records: [BadDebtRecord; MAX_BAD_DEBT_RECORDS], // 192
+    /// Can withdraw emissions to token accounts of the account authority,
+    /// `Pubkey::default()` if not set.
+    pub emissions_claim_delegate: Pubkey, // 32
+    /// Index used to derive the account address, see `find_marginfi_account_pda`.
+    /// Only meaningful if `PDA_ACCOUNT_FLAG` is set.
+    pub account_index: u16, // 2
+    pub pda_bump: u8,                    // 1
+    pub _pad0: [u8; 5],                  // 5
+    /// Operator allowed to act on the account within `delegate_permissions`,
+    /// `Pubkey::default()` if not set.
+    pub delegate: Pubkey, // 32
+    /// Bitmask of `DELEGATE_PERMISSION_*` flags granted to `delegate`.
+    pub delegate_permissions: u64, // 8
+    /// Unix timestamp after which the delegate can no longer act, 0 if it never expires.
+    pub delegate_expiry: i64, // 8
+    /// Liability shares of each balance when the ongoing flashloan started, see
+    /// `MarginfiAccount::snapshot_flashloan_liabilities`.
+    pub flashloan_liability_shares: [u64; MAX_LENDING_ACCOUNT_BALANCES], // 128
+    pub _padding: [u64; 12],             // 96
+}
+
+pub const MAX_BAD_DEBT_RECORDS: usize = 4;
+
+assert_struct_size!(BadDebtRecord, 48);
+assert_struct_align!(BadDebtRecord, 8);
+#[zero_copy(unsafe)]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(Debug, PartialEq, Eq, TypeLayout)
+)]
+#[derive(Default)]
+pub struct BadDebtRecord {
+    pub bank_pk: Pubkey,
+    /// Native token amount of bad debt written off and not recovered yet
+    pub amount: WrappedI80F48,
+}
+
+impl BadDebtRecord {
+    pub fn is_empty(&self) -> bool {
+        self.bank_pk == Pubkey::default()
+    }
 }
 
 pub const DISABLED_FLAG: u64 = 1 << 0;
 pub const IN_FLASHLOAN_FLAG: u64 = 1 << 1;
 pub const FLASHLOAN_ENABLED_FLAG: u64 = 1 << 2;
 pub const TRANSFER_AUTHORITY_ALLOWED_FLAG: u64 = 1 << 3;
+pub const EMISSIONS_AUTO_COMPOUND_FLAG: u64 = 1 << 4;
+pub const PDA_ACCOUNT_FLAG: u64 = 1 << 5;
+/// The ongoing flashloan was started through CPI, see `lending_account_start_flashloan`.
+pub const IN_CPI_FLASHLOAN_FLAG: u64 = 1 << 6;
+
+/// `Balance::emissions_index_checkpoint` holds a bank emissions index checkpoint, and not the
+/// legacy emissions `last_update` timestamp.
+pub const BALANCE_EMISSIONS_INDEX_FLAG: u8 = 1 << 0;
+/// The balance liability shares at the start of the ongoing flashloan are in
+/// `MarginfiAccount::flashloan_liability_shares`, balances opened during the flashloan start from 0.
+pub const BALANCE_FLASHLOAN_SNAPSHOT_FLAG: u8 = 1 << 1;
+
+/// Deposit into the account from token accounts of the delegate.
+pub const DELEGATE_PERMISSION_DEPOSIT: u64 = 1 << 0;
+/// Repay liabilities of the account from token accounts of the delegate.
+pub const DELEGATE_PERMISSION_REPAY: u64 = 1 << 1;
+/// Withdraw from the account to token accounts of the account authority.
+pub const DELEGATE_PERMISSION_WITHDRAW: u64 = 1 << 2;
+/// Withdraw emissions of the account to token accounts of the account authority.
+pub const DELEGATE_PERMISSION_CLAIM_EMISSIONS: u64 = 1 << 3;
+pub const DELEGATE_PERMISSIONS_ALL: u64 = DELEGATE_PERMISSION_DEPOSIT
+    | DELEGATE_PERMISSION_REPAY
+    | DELEGATE_PERMISSION_WITHDRAW
+    | DELEGATE_PERMISSION_CLAIM_EMISSIONS;
+
+/// Address of the PDA marginfi account of `authority` in `group` with index `account_index`.
+///
+/// The address stays derived from the authority that created the account, also after an
+/// authority transfer.
+pub fn find_marginfi_account_pda(
+    group: &Pubkey,
+    authority: &Pubkey,
+    account_index: u16,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            MARGINFI_ACCOUNT_SEED.as_bytes(),
+            group.as_ref(),
+            authority.as_ref(),
+            &account_index.to_le_bytes(),
+        ],
+        &crate::ID,
+    )
+}
 
 impl MarginfiAccount {
     /// Set the initial data for the marginfi account.
@@ -57,13 +146,42 @@ impl MarginfiAccount {
         self.group = group;
     }
 
-    pub fn get_remaining_accounts_len(&self) -> usize {
+    /// Set the initial data for a marginfi account derived with `find_marginfi_account_pda`.
+    pub fn initialize_pda(
+        &mut self,
+        group: Pubkey,
+        authority: Pubkey,
+        account_index: u16,
+        pda_bump: u8,
+    ) {
+        self.initialize(group, authority);
+        self.account_index = account_index;
+        self.pda_bump = pda_bump;
+        self.set_flag(PDA_ACCOUNT_FLAG);
+    }
+
+    /// Number of remaining accounts used by the health check of this account: the bank of each
+    /// active balance followed by the oracle accounts required by the bank oracle setup.
+    ///
+    /// Banks are looked up by key in `remaining_ais` to read their oracle setup.
+    pub fn get_remaining_accounts_len<'info>(
+        &self,
+        remaining_ais: &'info [AccountInfo<'info>],
+    ) -> MarginfiResult<usize> {
         self.lending_account
             .balances
             .iter()
             .filter(|b| b.active)
-            .count()
-            * 2 // TODO: Make account count oracle setup specific
+            .try_fold(0, |len, balance| -> MarginfiResult<usize> {
+                let bank_ai = remaining_ais
+                    .iter()
+                    .find(|ai| ai.key.eq(&balance.bank_pk))
+                    .ok_or(MarginfiError::MissingPythOrBankAccount)?;
+                let bank_al = AccountLoader::<Bank>::try_from(bank_ai)?;
+                let oracle_ais_len = bank_al.load()?.config.oracle_setup.get_oracle_ais_len();
+
+                Ok(len + 1 + oracle_ais_len)
+            })
     }
 
     pub fn set_flag(&mut self, flag: u64) {
@@ -80,6 +198,223 @@ impl MarginfiAccount {
         self.account_flags & flag != 0
     }
 
+    /// The authority, or any signer if the account is in a flashloan started through CPI, see
+    /// `lending_account_end_flashloan`.
+    pub fn can_end_flashloan(&self, signer: &Pubkey) -> bool {
+        self.authority == *signer || self.get_flag(IN_CPI_FLASHLOAN_FLAG)
+    }
+
+    pub fn clear_flashloan(&mut self) {
+        self.unset_flag(IN_FLASHLOAN_FLAG | IN_CPI_FLASHLOAN_FLAG);
+
+        self.flashloan_liability_shares = [0; MAX_LENDING_ACCOUNT_BALANCES];
+        for balance in self.lending_account.balances.iter_mut() {
+            balance.flags &= !BALANCE_FLASHLOAN_SNAPSHOT_FLAG;
+        }
+    }
+
+    /// Record the liability shares of the active balances when a flashloan starts, the flashloan
+    /// fee is charged on their net increase when it ends.
+    ///
+    /// Shares are rounded up, so a rounded snapshot never charges the fee on shares that were not
+    /// borrowed. Balances closed during the flashloan lose their snapshot, so a slot reused for
+    /// another bank starts from 0.
+    pub fn snapshot_flashloan_liabilities(&mut self) -> MarginfiResult {
+        for (balance, snapshot) in self
+            .lending_account
+            .balances
+            .iter_mut()
+            .zip(self.flashloan_liability_shares.iter_mut())
+        {
+            *snapshot = if balance.active {
+                balance.flags |= BALANCE_FLASHLOAN_SNAPSHOT_FLAG;
+                I80F48::from(balance.liability_shares)
+                    .checked_ceil()
+                    .and_then(|shares| shares.checked_to_num())
+                    .ok_or_else(math_error!())?
+            } else {
+                0
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Liability shares the balance at `index` gained since the start of the flashloan, see
+    /// `MarginfiAccount::snapshot_flashloan_liabilities`.
+    pub fn get_flashloan_liability_shares_increase(&self, index: usize) -> MarginfiResult<I80F48> {
+        let balance = &self.lending_account.balances[index];
+        if !balance.active {
+            return Ok(I80F48::ZERO);
+        }
+
+        let snapshot = if balance.flags & BALANCE_FLASHLOAN_SNAPSHOT_FLAG != 0 {
+            I80F48::from_num(self.flashloan_liability_shares[index])
+        } else {
+            I80F48::ZERO
+        };
+
+        Ok(max(
+            I80F48::from(balance.liability_shares)
+                .checked_sub(snapshot)
+                .ok_or_else(math_error!())?,
+            I80F48::ZERO,
+        ))
+    }
+
+    /// Set the account delegate, `Pubkey::default()` removes it.
+    pub fn set_delegate(
+        &mut self,
+        delegate: Pubkey,
+        permissions: u64,
+        expiry: i64,
+        current_timestamp: i64,
+    ) -> MarginfiResult {
+        if delegate == Pubkey::default() {
+            self.delegate = Pubkey::default();
+            self.delegate_permissions = 0;
+            self.delegate_expiry = 0;
+            return Ok(());
+        }
+
+        check!(
+            delegate != self.authority
+                && permissions != 0
+                && permissions & !DELEGATE_PERMISSIONS_ALL == 0,
+            MarginfiError::InvalidDelegateConfig
+        );
+        check!(
+            expiry == 0 || expiry > current_timestamp,
+            MarginfiError::InvalidDelegateConfig,
+            "Delegate expiry is in the past"
+        );
+
+        self.delegate = delegate;
+        self.delegate_permissions = permissions;
+        self.delegate_expiry = expiry;
+
+        Ok(())
+    }
+
+    /// The authority, the emissions claim delegate if one is set, or the delegate if it holds
+    /// `DELEGATE_PERMISSION_CLAIM_EMISSIONS` and has not expired.
+    pub fn can_withdraw_emissions(&self, signer: &Pubkey, current_timestamp: i64) -> bool {
+        (self.emissions_claim_delegate != Pubkey::default()
+            && self.emissions_claim_delegate == *signer)
+            || self.can_act(
+                signer,
+                DELEGATE_PERMISSION_CLAIM_EMISSIONS,
+                current_timestamp,
+            )
+    }
+
+    /// The authority, or the delegate if it holds `permission` and has not expired.
+    pub fn can_act(&self, signer: &Pubkey, permission: u64, current_timestamp: i64) -> bool {
+        self.authority == *signer
+            || (self.delegate != Pubkey::default()
+                && self.delegate == *signer
+                && self.delegate_permissions & permission == permission
+                && (self.delegate_expiry == 0 || current_timestamp < self.delegate_expiry))
+    }
+
+    /// Record `amount` of bad debt written off in `bank_pk`.
+    ///
+    /// Returns the total bad debt recorded for the bank. If all record slots are taken by other
+    /// banks the bad debt is only tracked at the bank level and `None` is returned.
+    pub fn record_bad_debt(
+        &mut self,
+        bank_pk: Pubkey,
+        amount: I80F48,
+    ) -> MarginfiResult<Option<I80F48>> {
+        let record = match self
+            .bad_debt_records
+            .iter()
+            .position(|record| record.bank_pk == bank_pk)
+            .or_else(|| {
+                self.bad_debt_records
+                    .iter()
+                    .position(|record| record.is_empty())
+            }) {
+            Some(index) => &mut self.bad_debt_records[index],
+            None => {
+                msg!("No bad debt record slot left for bank {}", bank_pk);
+                return Ok(None);
+            }
+        };
+
+        let total = I80F48::from(record.amount)
+            .checked_add(amount)
+            .ok_or_else(math_error!())?;
+
+        record.bank_pk = bank_pk;
+        record.amount = total.into();
+
+        Ok(Some(total))
+    }
+
+    pub fn get_bad_debt_record_mut(&mut self, bank_pk: &Pubkey) -> Option<&mut BadDebtRecord> {
+        self.bad_debt_records
+            .iter_mut()
+            .find(|record| !record.is_empty() && record.bank_pk.eq(bank_pk))
+    }
+
+    /// Bad debt recorded for `bank_pk` in native tokens, rounded up. 0 if none is recorded.
+    pub fn get_recoverable_bad_debt(&self, bank_pk: &Pubkey) -> MarginfiResult<u64> {
+        match self
+            .bad_debt_records
+            .iter()
+            .find(|record| !record.is_empty() && record.bank_pk.eq(bank_pk))
+        {
+            Some(record) => I80F48::from(record.amount)
+                .checked_ceil()
+                .ok_or_else(math_error!())?
+                .checked_to_num()
+                .ok_or_else(math_error!()),
+            None => Ok(0),
+        }
+    }
+
+    /// Reduce the bad debt recorded for `bank_pk` by `amount_received` tokens repaid to the bank
+    /// insurance vault, clearing the record once fully repaid.
+    ///
+    /// Returns the remaining bad debt.
+    pub fn recover_bad_debt(
+        &mut self,
+        bank: &mut Bank,
+        bank_pk: &Pubkey,
+        amount_received: I80F48,
+    ) -> MarginfiResult<I80F48> {
+        let record = self
+            .get_bad_debt_record_mut(bank_pk)
+            .ok_or(MarginfiError::BadDebtRecordNotFound)?;
+        let recorded_bad_debt = I80F48::from(record.amount);
+        let amount_recovered = amount_received.min(recorded_bad_debt);
+
+        let remaining_bad_debt = recorded_bad_debt
+            .checked_sub(amount_recovered)
+            .ok_or_else(math_error!())?;
+
+        if remaining_bad_debt < ZERO_AMOUNT_THRESHOLD {
+            record.bank_pk = Pubkey::default();
+            record.amount = I80F48::ZERO.into();
+        } else {
+            record.amount = remaining_bad_debt.into();
+        }
+
+        bank.bad_debt_recovered = I80F48::from(bank.bad_debt_recovered)
+            .checked_add(amount_recovered)
+            .ok_or_else(math_error!())?
+            .into();
+
+        Ok(remaining_bad_debt)
+    }
+
+    pub fn has_bad_debt_records(&self) -> bool {
+        self.bad_debt_records
+            .iter()
+            .any(|record| !record.is_empty())
+    }
+
     pub fn set_new_account_authority_checked(&mut self, new_authority: Pubkey) -> MarginfiResult {
         // check if new account authority flag is set
         if !self.get_flag(TRANSFER_AUTHORITY_ALLOWED_FLAG) || self.get_flag(DISABLED_FLAG) {
@@ -93,6 +428,11 @@ impl MarginfiAccount {
         // unset flag after updating the account authority
         self.unset_flag(TRANSFER_AUTHORITY_ALLOWED_FLAG);
 
+        // the delegate was granted by the previous authority
+        self.delegate = Pubkey::default();
+        self.delegate_permissions = 0;
+        self.delegate_expiry = 0;
+
         msg!(
             "Transferred account authority from {:?} to {:?} in group {:?}",
             old_authority,
@@ -172,24 +512,26 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
             .filter(|balance| balance.active)
             .collect::<Vec<_>>();
 
-        debug!("Expecting {} remaining accounts", active_balances.len() * 2);
+        debug!(
+            "Expecting at least {} remaining accounts",
+            active_balances.len()
+        );
         debug!("Got {} remaining accounts", remaining_ais.len());
 
         check!(
-            active_balances.len() * 2 <= remaining_ais.len(),
+            active_balances.len() <= remaining_ais.len(),
             MarginfiError::MissingPythOrBankAccount
         );
 
         let clock = Clock::get()?;
+        let mut account_index = 0;
 
         active_balances
             .iter()
-            .enumerate()
-            .map(|(i, balance)| {
-                let bank_index = i * 2;
-                let oracle_ai_idx = bank_index + 1;
-
-                let bank_ai = remaining_ais.get(bank_index).unwrap();
+            .map(|balance| {
+                let bank_ai = remaining_ais
+                    .get(account_index)
+                    .ok_or(MarginfiError::MissingPythOrBankAccount)?;
 
                 check!(
                     balance.bank_pk.eq(bank_ai.key),
@@ -197,10 +539,23 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
                 );
 
                 let price_adapter = {
-                    let oracle_ais = &remaining_ais[oracle_ai_idx..oracle_ai_idx + 1];
                     let bank_al = AccountLoader::<Bank>::try_from(bank_ai)?;
                     let bank = bank_al.load()?;
 
+                    // Oracle accounts directly follow the bank, their count depends on the oracle setup
+                    let oracle_ais_start = account_index + 1;
+                    let oracle_ais_end =
+                        oracle_ais_start + bank.config.oracle_setup.get_oracle_ais_len();
+
+                    check!(
+                        oracle_ais_end <= remaining_ais.len(),
+                        MarginfiError::MissingPythOrBankAccount
+                    );
+
+                    account_index = oracle_ais_end;
+
+                    let oracle_ais = &remaining_ais[oracle_ais_start..oracle_ais_end];
+
                     Box::new(OraclePriceFeedAdapter::try_from_bank_config(
                         &bank.config,
                         oracle_ais,
@@ -311,6 +666,7 @@ impl<'info> BankAccountWithPriceFeed<'_, 'info> {
         bank: &Bank,
     ) -> MarginfiResult<I80F48> {
         let price_feed = self.try_get_price_feed()?;
+
         let liability_weight = bank
             .config
             .get_weight(requirement_type, BalanceSide::Liabilities);
@@ -500,7 +856,7 @@ impl<'info> RiskEngine<'_, 'info> {
     }
 
     pub fn get_account_health(
-        &'info self,
+        &self,
         requirement_type: RiskRequirementType,
     ) -> MarginfiResult<I80F48> {
         let (total_weighted_assets, total_weighted_liabilities) =
@@ -644,9 +1000,9 @@ impl<'info> RiskEngine<'_, 'info> {
         Ok(account_health)
     }
 
-    /// Check that the account is in a bankrupt state.
-    /// Account needs to be insolvent and total value of assets need to be below the bankruptcy threshold.
-    pub fn check_account_bankrupt(&self) -> MarginfiResult {
+    /// Check that the account has more liabilities than assets and that its equity weighted
+    /// assets are below `bankrupt_threshold` (USD), see `MarginfiGroup::get_bankrupt_threshold`.
+    pub fn check_account_bankrupt(&self, bankrupt_threshold: I80F48) -> MarginfiResult {
         let (total_assets, total_liabilities) =
             self.get_account_health_components(RiskRequirementType::Equity)?;
 
@@ -666,7 +1022,7 @@ impl<'info> RiskEngine<'_, 'info> {
             MarginfiError::AccountNotBankrupt
         );
         check!(
-            total_assets < BANKRUPT_THRESHOLD && total_liabilities > ZERO_AMOUNT_THRESHOLD,
+            total_assets < bankrupt_threshold && total_liabilities > ZERO_AMOUNT_THRESHOLD,
             MarginfiError::AccountNotBankrupt
         );
 
@@ -715,6 +1071,7 @@ assert_struct_align!(LendingAccount, 8);
     any(feature = "test", feature = "client"),
     derive(Debug, PartialEq, Eq, TypeLayout)
 )]
+#[derive(Default)]
 pub struct LendingAccount {
     pub balances: [Balance; MAX_LENDING_ACCOUNT_BALANCES], // 104 * 16 = 1664
     pub _padding: [u64; 8],                                // 8 * 8 = 64
@@ -747,15 +1104,22 @@ assert_struct_align!(Balance, 8);
     any(feature = "test", feature = "client"),
     derive(Debug, PartialEq, Eq, TypeLayout)
 )]
+#[derive(Default)]
 pub struct Balance {
     pub active: bool,
     pub bank_pk: Pubkey,
-    pub _pad0: [u8; 7],
+    /// - BALANCE_EMISSIONS_INDEX_FLAG: 1
+    /// - BALANCE_FLASHLOAN_SNAPSHOT_FLAG: 2
+    pub flags: u8,
+    pub _pad0: [u8; 6],
     pub asset_shares: WrappedI80F48,
     pub liability_shares: WrappedI80F48,
     pub emissions_outstanding: WrappedI80F48,
-    pub last_update: u64,
-    pub _padding: [u64; 1],
+    /// Bank emissions index of the balance side at the last emissions claim.
+    ///
+    /// Without `BALANCE_EMISSIONS_INDEX_FLAG`, holds the legacy emissions `last_update` timestamp
+    /// in its first 8 bytes.
+    pub emissions_index_checkpoint: WrappedI80F48,
 }
 
 impl Balance {
@@ -818,16 +1182,29 @@ impl Balance {
         }
     }
 
+    /// Last emissions update of a balance accruing legacy emissions.
+    fn get_legacy_emissions_last_update(&self) -> u64 {
+        let mut last_update = [0; 8];
+        last_update.copy_from_slice(&self.emissions_index_checkpoint.value[..8]);
+        u64::from_le_bytes(last_update)
+    }
+
+    fn set_legacy_emissions_last_update(&mut self, last_update: u64) {
+        let mut value = [0; 16];
+        value[..8].copy_from_slice(&last_update.to_le_bytes());
+        self.emissions_index_checkpoint = WrappedI80F48 { value };
+    }
+
     pub fn empty_deactivated() -> Self {
         Balance {
             active: false,
             bank_pk: Pubkey::default(),
-            _pad0: [0; 7],
+            flags: 0,
+            _pad0: [0; 6],
             asset_shares: WrappedI80F48::from(I80F48::ZERO),
             liability_shares: WrappedI80F48::from(I80F48::ZERO),
             emissions_outstanding: WrappedI80F48::from(I80F48::ZERO),
-            last_update: 0,
-            _padding: [0; 1],
+            emissions_index_checkpoint: WrappedI80F48::from(I80F48::ZERO),
         }
     }
 }
@@ -882,12 +1259,12 @@ impl<'a> BankAccountWrapper<'a> {
                 lending_account.balances[empty_index] = Balance {
                     active: true,
                     bank_pk: *bank_pk,
-                    _pad0: [0; 7],
+                    flags: 0,
+                    _pad0: [0; 6],
                     asset_shares: I80F48::ZERO.into(),
                     liability_shares: I80F48::ZERO.into(),
                     emissions_outstanding: I80F48::ZERO.into(),
-                    last_update: Clock::get()?.unix_timestamp as u64,
-                    _padding: [0; 1],
+                    emissions_index_checkpoint: I80F48::ZERO.into(),
                 };
 
                 Ok(Self {
@@ -947,6 +1324,14 @@ impl<'a> BankAccountWrapper<'a> {
         self.decrease_balance_internal(amount, BalanceDecreaseType::BypassBorrowLimit)
     }
 
+    /// Add a fee to the liability and book it in the bank fees, see `Bank::collect_borrow_fee`.
+    ///
+    /// Bypasses the borrow limit, the borrow that incurred the fee already passed it.
+    pub fn charge_borrow_fee(&mut self, fee: I80F48) -> MarginfiResult {
+        self.decrease_balance_internal(fee, BalanceDecreaseType::BypassBorrowLimit)?;
+        self.bank.collect_borrow_fee(fee)
+    }
+
     /// Withdraw existing asset in full - will error if there is no asset.
     pub fn withdraw_all(&mut self) -> MarginfiResult<u64> {
         self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
@@ -996,6 +1381,39 @@ impl<'a> BankAccountWrapper<'a> {
             .ok_or_else(math_error!())?)
     }
 
+    /// Remove all assets of a bankrupt account from the balance and book them as outstanding
+    /// insurance fees of the bank, to be moved to the insurance vault by `collect_bank_fees`.
+    /// The balance is closed, unless it still has emissions to withdraw.
+    ///
+    /// Returns the swept asset amount.
+    pub fn sweep_assets_to_insurance(&mut self, current_timestamp: u64) -> MarginfiResult<I80F48> {
+        self.claim_emissions(current_timestamp)?;
+
+        let balance = &mut self.balance;
+        let bank = &mut self.bank;
+
+        let asset_shares: I80F48 = balance.asset_shares.into();
+        let asset_amount = bank.get_asset_amount(asset_shares)?;
+
+        debug!("Sweeping {} to insurance", asset_amount);
+
+        balance.change_asset_shares(-asset_shares)?;
+        bank.change_asset_shares(-asset_shares, false)?;
+
+        bank.collected_insurance_fees_outstanding = {
+            asset_amount
+                .checked_add(bank.collected_insurance_fees_outstanding.into())
+                .ok_or_else(math_error!())?
+                .into()
+        };
+
+        if I80F48::from(balance.emissions_outstanding) < I80F48::ONE {
+            balance.close()?;
+        }
+
+        Ok(asset_amount)
+    }
+
     /// Repay existing liability in full - will error if there is no liability.
     pub fn repay_all(&mut self) -> MarginfiResult<u64> {
         self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
@@ -1133,6 +1551,8 @@ impl<'a> BankAccountWrapper<'a> {
         balance.change_liability_shares(-liability_shares_decrease)?;
         bank.change_liability_shares(-liability_shares_decrease, true)?;
 
+        self.update_emissions_checkpoint();
+
         Ok(())
     }
 
@@ -1199,11 +1619,79 @@ impl<'a> BankAccountWrapper<'a> {
 
         bank.check_utilization_ratio()?;
 
+        self.update_emissions_checkpoint();
+
         Ok(())
     }
 
     /// Claim any unclaimed emissions and add them to the outstanding emissions amount.
+    ///
+    /// Emissions are the balance shares times the growth of the bank emissions index of the
+    /// balance side since the last claim.
+    ///
+    /// Banks that did not activate the emissions indices yet accrue the legacy per balance
+    /// emissions. Balances last updated before the activation first accrue their legacy emissions
+    /// up to the activation, then accrue from the indices, which start at zero at the activation.
     pub fn claim_emissions(&mut self, current_timestamp: u64) -> MarginfiResult {
+        if !self.bank.is_emissions_index_active() {
+            self.claim_legacy_emissions(current_timestamp, self.bank.emissions_rate)?;
+            self.balance
+                .set_legacy_emissions_last_update(current_timestamp);
+
+            return Ok(());
+        }
+
+        if self.balance.flags & BALANCE_EMISSIONS_INDEX_FLAG == 0 {
+            self.claim_legacy_emissions(
+                self.bank.emissions_index_activated_at as u64,
+                self.bank.emissions_legacy_rate,
+            )?;
+            self.balance.emissions_index_checkpoint = I80F48::ZERO.into();
+            self.balance.flags |= BALANCE_EMISSIONS_INDEX_FLAG;
+        }
+
+        self.bank.update_emissions_index(current_timestamp as i64)?;
+
+        if let Some(side) = self.balance.get_side() {
+            let shares: I80F48 = match side {
+                BalanceSide::Assets => self.balance.asset_shares,
+                BalanceSide::Liabilities => self.balance.liability_shares,
+            }
+            .into();
+            let index = self.bank.get_emissions_index(side);
+            let index_delta = max(
+                index
+                    .checked_sub(self.balance.emissions_index_checkpoint.into())
+                    .ok_or_else(math_error!())?,
+                I80F48::ZERO,
+            );
+            let emissions = shares.checked_mul(index_delta).ok_or_else(math_error!())?;
+
+            debug!(
+                "Outstanding emissions: {}, claimed: {}",
+                I80F48::from(self.balance.emissions_outstanding),
+                emissions
+            );
+
+            self.balance.emissions_outstanding = {
+                I80F48::from(self.balance.emissions_outstanding)
+                    .checked_add(emissions)
+                    .ok_or_else(math_error!())?
+            }
+            .into();
+            self.balance.emissions_index_checkpoint = index.into();
+        }
+
+        Ok(())
+    }
+
+    /// Accrue the legacy per balance emissions, `emissions_rate` emitted tokens per
+    /// 1e(bank.mint_decimal) tokens per year, from the balance last update to `end_timestamp`.
+    fn claim_legacy_emissions(
+        &mut self,
+        end_timestamp: u64,
+        emissions_rate: u64,
+    ) -> MarginfiResult {
         if let Some(balance_amount) = match (
             self.balance.get_side(),
             self.bank.get_flag(EMISSIONS_FLAG_LENDING_ACTIVE),
@@ -1219,22 +1707,18 @@ impl<'a> BankAccountWrapper<'a> {
             ),
             _ => None,
         } {
-            let last_update = if self.balance.last_update < MIN_EMISSIONS_START_TIME {
-                current_timestamp
+            let last_update = self.balance.get_legacy_emissions_last_update();
+            let last_update = if last_update < MIN_EMISSIONS_START_TIME {
+                end_timestamp
             } else {
-                self.balance.last_update
+                last_update
             };
-            let period = I80F48::from_num(
-                current_timestamp
-                    .checked_sub(last_update)
-                    .ok_or_else(math_error!())?,
-            );
-            let emissions_rate = I80F48::from_num(self.bank.emissions_rate);
+            let period = I80F48::from_num(end_timestamp.saturating_sub(last_update));
             let emissions = calc_emissions(
                 period,
                 balance_amount,
                 self.bank.mint_decimals as usize,
-                emissions_rate,
+                I80F48::from_num(emissions_rate),
             )?;
 
             let emissions_real = min(emissions, I80F48::from(self.bank.emissions_remaining));
@@ -1248,11 +1732,6 @@ impl<'a> BankAccountWrapper<'a> {
                 );
             }
 
-            debug!(
-                "Outstanding emissions: {}",
-                I80F48::from(self.balance.emissions_outstanding)
-            );
-
             self.balance.emissions_outstanding = {
                 I80F48::from(self.balance.emissions_outstanding)
                     .checked_add(emissions_real)
@@ -1267,11 +1746,24 @@ impl<'a> BankAccountWrapper<'a> {
             .into();
         }
 
-        self.balance.last_update = current_timestamp;
-
         Ok(())
     }
 
+    /// Move the emissions checkpoint to the index of the current balance side, after a balance
+    /// change that might have switched sides.
+    ///
+    /// Legacy emissions only track the last update, which is set by `claim_emissions`.
+    fn update_emissions_checkpoint(&mut self) {
+        if !self.bank.is_emissions_index_active() {
+            return;
+        }
+
+        if let Some(side) = self.balance.get_side() {
+            self.balance.emissions_index_checkpoint = self.bank.get_emissions_index(side).into();
+            self.balance.flags |= BALANCE_EMISSIONS_INDEX_FLAG;
+        }
+    }
+
     /// Claim any outstanding emissions, and return the max amount that can be withdrawn.
     pub fn settle_emissions_and_get_transfer_amount(&mut self) -> MarginfiResult<u64> {
         self.claim_emissions(Clock::get()?.unix_timestamp as u64)?;
@@ -1353,7 +1845,7 @@ impl<'a> BankAccountWrapper<'a> {
 /// # Returns
 ///
 /// The calculated emissions value.
-fn calc_emissions(
+pub(crate) fn calc_emissions(
     period: I80F48,
     balance_amount: I80F48,
     mint_decimals: usize,
@@ -1412,17 +1904,17 @@ mod test {
                 balances: [Balance {
                     active: true,
                     bank_pk: bank_pk.into(),
-                    _pad0: [0; 7],
+                    flags: 0,
+                    _pad0: [0; 6],
                     asset_shares: WrappedI80F48::default(),
                     liability_shares: WrappedI80F48::default(),
                     emissions_outstanding: WrappedI80F48::default(),
-                    last_update: 0,
-                    _padding: [0_u64],
+                    emissions_index_checkpoint: WrappedI80F48::default(),
                 }; 16],
                 _padding: [0; 8],
             },
             account_flags: TRANSFER_AUTHORITY_ALLOWED_FLAG,
-            _padding: [0; 63],
+            ..Default::default()
         };
 
         assert!(acc.get_flag(TRANSFER_AUTHORITY_ALLOWED_FLAG));
@@ -1433,6 +1925,312 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_record_bad_debt() {
+        let mut acc = MarginfiAccount {
+            group: Pubkey::new_unique(),
+            authority: Pubkey::new_unique(),
+            ..Default::default()
+        };
+
+        assert!(!acc.has_bad_debt_records());
+
+        let bank_pk = Pubkey::new_unique();
+
+        assert_eq!(
+            acc.record_bad_debt(bank_pk, I80F48!(100)).unwrap(),
+            Some(I80F48!(100))
+        );
+        assert_eq!(
+            acc.record_bad_debt(bank_pk, I80F48!(50.5)).unwrap(),
+            Some(I80F48!(150.5))
+        );
+        assert!(acc.has_bad_debt_records());
+        assert_eq!(
+            I80F48::from(acc.get_bad_debt_record_mut(&bank_pk).unwrap().amount),
+            I80F48!(150.5)
+        );
+
+        for _ in 1..MAX_BAD_DEBT_RECORDS {
+            assert!(acc
+                .record_bad_debt(Pubkey::new_unique(), I80F48!(1))
+                .unwrap()
+                .is_some());
+        }
+
+        // All slots are taken, the bad debt is only tracked by the bank
+        let other_bank_pk = Pubkey::new_unique();
+        assert_eq!(
+            acc.record_bad_debt(other_bank_pk, I80F48!(1)).unwrap(),
+            None
+        );
+        assert!(acc.get_bad_debt_record_mut(&other_bank_pk).is_none());
+
+        // Existing records can still grow
+        assert_eq!(
+            acc.record_bad_debt(bank_pk, I80F48!(1)).unwrap(),
+            Some(I80F48!(151.5))
+        );
+
+        // Bankrupt accounts are disabled, recovery still works
+        acc.set_flag(DISABLED_FLAG);
+        let mut bank = Bank::default();
+
+        assert_eq!(acc.get_recoverable_bad_debt(&bank_pk).unwrap(), 152);
+        assert_eq!(acc.get_recoverable_bad_debt(&other_bank_pk).unwrap(), 0);
+        assert!(acc
+            .recover_bad_debt(&mut bank, &other_bank_pk, I80F48!(1))
+            .is_err());
+
+        assert_eq!(
+            acc.recover_bad_debt(&mut bank, &bank_pk, I80F48!(100))
+                .unwrap(),
+            I80F48!(51.5)
+        );
+        assert_eq!(I80F48::from(bank.bad_debt_recovered), I80F48!(100));
+
+        // Overpayments are not counted as recovered
+        assert_eq!(
+            acc.recover_bad_debt(&mut bank, &bank_pk, I80F48!(52))
+                .unwrap(),
+            I80F48::ZERO
+        );
+        assert_eq!(I80F48::from(bank.bad_debt_recovered), I80F48!(151.5));
+        assert_eq!(acc.get_recoverable_bad_debt(&bank_pk).unwrap(), 0);
+        assert!(acc.get_bad_debt_record_mut(&bank_pk).is_none());
+    }
+
+    #[test]
+    fn test_account_delegate() {
+        let authority = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let mut acc = MarginfiAccount {
+            group: Pubkey::new_unique(),
+            authority,
+            account_flags: TRANSFER_AUTHORITY_ALLOWED_FLAG,
+            ..Default::default()
+        };
+
+        assert!(acc.can_act(&authority, DELEGATE_PERMISSION_WITHDRAW, 100));
+        assert!(!acc.can_act(&delegate, DELEGATE_PERMISSION_REPAY, 100));
+
+        // Invalid configs
+        assert!(acc.set_delegate(delegate, 0, 0, 100).is_err());
+        assert!(acc.set_delegate(delegate, 1 << 10, 0, 100).is_err());
+        assert!(acc
+            .set_delegate(delegate, DELEGATE_PERMISSION_REPAY, 100, 100)
+            .is_err());
+        assert!(acc
+            .set_delegate(authority, DELEGATE_PERMISSION_REPAY, 0, 100)
+            .is_err());
+
+        acc.set_delegate(
+            delegate,
+            DELEGATE_PERMISSION_REPAY | DELEGATE_PERMISSION_DEPOSIT,
+            200,
+            100,
+        )
+        .unwrap();
+        assert!(acc.can_act(&delegate, DELEGATE_PERMISSION_REPAY, 100));
+        assert!(acc.can_act(&delegate, DELEGATE_PERMISSION_DEPOSIT, 199));
+        assert!(!acc.can_act(&delegate, DELEGATE_PERMISSION_WITHDRAW, 100));
+        assert!(!acc.can_act(&delegate, DELEGATE_PERMISSION_REPAY, 200));
+        assert!(!acc.can_act(&Pubkey::new_unique(), DELEGATE_PERMISSION_REPAY, 100));
+
+        // Emissions claiming is a permission like the others
+        assert!(!acc.can_act(&delegate, DELEGATE_PERMISSION_CLAIM_EMISSIONS, 100));
+        acc.set_delegate(delegate, DELEGATE_PERMISSION_CLAIM_EMISSIONS, 0, 100)
+            .unwrap();
+        assert!(acc.can_act(&delegate, DELEGATE_PERMISSION_CLAIM_EMISSIONS, 100));
+        assert!(!acc.can_act(&delegate, DELEGATE_PERMISSION_REPAY, 100));
+        assert!(acc.can_withdraw_emissions(&delegate, 100));
+
+        // The emissions claim delegate only needs to be set
+        let claim_delegate = Pubkey::new_unique();
+        assert!(!acc.can_withdraw_emissions(&claim_delegate, 100));
+        acc.emissions_claim_delegate = claim_delegate;
+        assert!(acc.can_withdraw_emissions(&claim_delegate, 100));
+        assert!(!acc.can_act(&claim_delegate, DELEGATE_PERMISSION_CLAIM_EMISSIONS, 100));
+
+        // The delegate does not survive an authority transfer
+        acc.set_new_account_authority_checked(Pubkey::new_unique())
+            .unwrap();
+        assert!(!acc.can_act(&delegate, DELEGATE_PERMISSION_REPAY, 100));
+        assert_eq!(acc.delegate, Pubkey::default());
+    }
+
+    #[test]
+    fn test_cpi_flashloan_end_signer() {
+        let authority = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let mut acc = MarginfiAccount {
+            group: Pubkey::new_unique(),
+            authority,
+            ..Default::default()
+        };
+
+        acc.set_flag(IN_FLASHLOAN_FLAG);
+        assert!(acc.can_end_flashloan(&authority));
+        assert!(!acc.can_end_flashloan(&other));
+
+        // The authority of CPI flashloan accounts is usually a PDA of the caller program
+        acc.set_flag(IN_CPI_FLASHLOAN_FLAG);
+        assert!(acc.can_end_flashloan(&authority));
+        assert!(acc.can_end_flashloan(&other));
+
+        acc.clear_flashloan();
+        assert!(!acc.get_flag(IN_FLASHLOAN_FLAG));
+        assert!(!acc.can_end_flashloan(&other));
+    }
+
+    #[test]
+    fn test_flashloan_liability_snapshot() {
+        let bank_a = Pubkey::new_unique();
+        let bank_b = Pubkey::new_unique();
+        let mut acc = MarginfiAccount::default();
+        acc.lending_account.balances[0] = Balance {
+            active: true,
+            bank_pk: bank_a,
+            liability_shares: I80F48!(100.5).into(),
+            ..Balance::empty_deactivated()
+        };
+
+        acc.set_flag(IN_FLASHLOAN_FLAG);
+        acc.snapshot_flashloan_liabilities().unwrap();
+        assert_eq!(acc.flashloan_liability_shares[0], 101);
+
+        // Borrowed and repaid within the flashloan
+        acc.lending_account.balances[0].liability_shares = I80F48!(500).into();
+        acc.lending_account.balances[0].liability_shares = I80F48!(100.5).into();
+        assert_eq!(
+            acc.get_flashloan_liability_shares_increase(0).unwrap(),
+            I80F48::ZERO
+        );
+
+        // Net borrow, and a balance opened within the flashloan
+        acc.lending_account.balances[0].liability_shares = I80F48!(151).into();
+        acc.lending_account.balances[1] = Balance {
+            active: true,
+            bank_pk: bank_b,
+            liability_shares: I80F48!(20).into(),
+            ..Balance::empty_deactivated()
+        };
+        assert_eq!(
+            acc.get_flashloan_liability_shares_increase(0).unwrap(),
+            I80F48!(50)
+        );
+        assert_eq!(
+            acc.get_flashloan_liability_shares_increase(1).unwrap(),
+            I80F48!(20)
+        );
+
+        // A slot closed and reused for another bank does not keep the snapshot
+        acc.lending_account.balances[0] = Balance {
+            active: true,
+            bank_pk: bank_b,
+            liability_shares: I80F48!(30).into(),
+            ..Balance::empty_deactivated()
+        };
+        assert_eq!(
+            acc.get_flashloan_liability_shares_increase(0).unwrap(),
+            I80F48!(30)
+        );
+
+        acc.clear_flashloan();
+        assert_eq!(
+            acc.flashloan_liability_shares,
+            [0; MAX_LENDING_ACCOUNT_BALANCES]
+        );
+        assert!(acc
+            .lending_account
+            .balances
+            .iter()
+            .all(|balance| balance.flags & BALANCE_FLASHLOAN_SNAPSHOT_FLAG == 0));
+    }
+
+    #[test]
+    fn test_sweep_assets_to_insurance() {
+        let bank_pk = Pubkey::new_unique();
+        let mut bank = Bank {
+            asset_share_value: I80F48!(2).into(),
+            total_asset_shares: I80F48!(150).into(),
+            collected_insurance_fees_outstanding: I80F48!(10).into(),
+            ..Default::default()
+        };
+        let mut lending_account = LendingAccount {
+            balances: [Balance::empty_deactivated(); MAX_LENDING_ACCOUNT_BALANCES],
+            _padding: [0; 8],
+        };
+        lending_account.balances[0] = Balance {
+            active: true,
+            bank_pk,
+            asset_shares: I80F48!(50).into(),
+            ..Balance::empty_deactivated()
+        };
+
+        let amount = BankAccountWrapper::find(&bank_pk, &mut bank, &mut lending_account)
+            .unwrap()
+            .sweep_assets_to_insurance(0)
+            .unwrap();
+
+        assert_eq!(amount, I80F48!(100));
+        assert_eq!(I80F48::from(bank.total_asset_shares), I80F48!(100));
+        assert_eq!(
+            I80F48::from(bank.collected_insurance_fees_outstanding),
+            I80F48!(110)
+        );
+        assert!(!lending_account.balances[0].active);
+
+        // Balances with emissions left to withdraw stay open
+        lending_account.balances[0] = Balance {
+            active: true,
+            bank_pk,
+            asset_shares: I80F48!(50).into(),
+            emissions_outstanding: I80F48!(5).into(),
+            ..Balance::empty_deactivated()
+        };
+
+        BankAccountWrapper::find(&bank_pk, &mut bank, &mut lending_account)
+            .unwrap()
+            .sweep_assets_to_insurance(0)
+            .unwrap();
+
+        assert!(lending_account.balances[0].active);
+        assert!(lending_account.balances[0].is_empty(BalanceSide::Assets));
+    }
+
+    #[test]
+    fn test_find_marginfi_account_pda() {
+        let group = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+
+        let (address, bump) = find_marginfi_account_pda(&group, &authority, 0);
+        assert_eq!(
+            Pubkey::create_program_address(
+                &[
+                    MARGINFI_ACCOUNT_SEED.as_bytes(),
+                    group.as_ref(),
+                    authority.as_ref(),
+                    &0u16.to_le_bytes(),
+                    &[bump],
+                ],
+                &crate::ID,
+            )
+            .unwrap(),
+            address
+        );
+
+        assert_ne!(find_marginfi_account_pda(&group, &authority, 1).0, address);
+        assert_ne!(
+            find_marginfi_account_pda(&group, &Pubkey::new_unique(), 0).0,
+            address
+        );
+        assert_ne!(
+            find_marginfi_account_pda(&Pubkey::new_unique(), &authority, 0).0,
+            address
+        );
+    }
+
     #[test]
     fn test_calc_emissions() {
         let balance_amount: u64 = 106153222432271169;
@@ -1538,4 +2336,111 @@ mod test {
             assert!(emissions_new - emissions < I80F48::from_num(0.00000001));
         }
     }
+
+    #[test]
+    fn test_emissions_deposit_claim_deposit() {
+        let bank_pk = Pubkey::new_unique();
+        let mut bank = Bank {
+            flags: EMISSIONS_FLAG_LENDING_ACTIVE,
+            emissions_rate: 10,
+            emissions_remaining: I80F48!(1_000).into(),
+            asset_share_value: I80F48::ONE.into(),
+            total_asset_shares: I80F48!(100).into(),
+            ..Default::default()
+        };
+        bank.activate_emissions_index(1_000);
+        let mut balance = Balance {
+            active: true,
+            bank_pk,
+            asset_shares: I80F48!(100).into(),
+            ..Balance::empty_deactivated()
+        };
+
+        let mut wrapper = BankAccountWrapper {
+            balance: &mut balance,
+            bank: &mut bank,
+        };
+        wrapper.claim_emissions(1_010).unwrap();
+        assert_eq!(
+            I80F48::from(wrapper.balance.emissions_outstanding),
+            I80F48!(100)
+        );
+        assert_ne!(wrapper.balance.flags & BALANCE_EMISSIONS_INDEX_FLAG, 0);
+
+        // A deposit claims first, then moves the checkpoint
+        wrapper.claim_emissions(1_010).unwrap();
+        wrapper.balance.asset_shares = I80F48!(200).into();
+        wrapper.bank.total_asset_shares = I80F48!(200).into();
+        wrapper.update_emissions_checkpoint();
+        assert_eq!(
+            I80F48::from(wrapper.balance.emissions_outstanding),
+            I80F48!(100)
+        );
+
+        // The deposited shares only earn from the deposit on
+        wrapper.claim_emissions(1_020).unwrap();
+        assert_eq!(
+            I80F48::from(wrapper.balance.emissions_outstanding),
+            I80F48!(200)
+        );
+        assert_eq!(I80F48::from(bank.emissions_remaining), I80F48!(800));
+    }
+
+    #[test]
+    fn test_legacy_emissions_migration() {
+        let bank_pk = Pubkey::new_unique();
+        let start = MIN_EMISSIONS_START_TIME + 1_000;
+        // 100 tokens over a hundredth of a year emit `emissions_rate` tokens
+        let period = 315_360;
+        let mut bank = Bank {
+            flags: EMISSIONS_FLAG_LENDING_ACTIVE,
+            emissions_rate: 100,
+            emissions_remaining: I80F48!(1_000).into(),
+            asset_share_value: I80F48::ONE.into(),
+            total_asset_shares: I80F48!(100).into(),
+            ..Default::default()
+        };
+        let mut balance = Balance {
+            active: true,
+            bank_pk,
+            asset_shares: I80F48!(100).into(),
+            ..Balance::empty_deactivated()
+        };
+        balance.set_legacy_emissions_last_update(start);
+
+        // Banks set up before the emissions indices keep the legacy emissions
+        let mut wrapper = BankAccountWrapper {
+            balance: &mut balance,
+            bank: &mut bank,
+        };
+        wrapper.claim_emissions(start + period).unwrap();
+        wrapper.update_emissions_checkpoint();
+        assert_eq!(
+            I80F48::from(wrapper.balance.emissions_outstanding),
+            I80F48!(100)
+        );
+        assert_eq!(
+            wrapper.balance.get_legacy_emissions_last_update(),
+            start + period
+        );
+        assert_eq!(wrapper.balance.flags, 0);
+
+        let activated_at = start + 2 * period;
+        wrapper.bank.activate_emissions_index(activated_at as i64);
+        wrapper.bank.emissions_rate = 10;
+
+        // The legacy emissions are settled up to the activation at the legacy rate, then the
+        // balance accrues from the indices
+        wrapper.claim_emissions(activated_at + 10).unwrap();
+        assert_eq!(
+            I80F48::from(wrapper.balance.emissions_outstanding),
+            I80F48!(300)
+        );
+        assert_eq!(
+            I80F48::from(wrapper.balance.emissions_index_checkpoint),
+            I80F48::ONE
+        );
+        assert_ne!(wrapper.balance.flags & BALANCE_EMISSIONS_INDEX_FLAG, 0);
+        assert_eq!(I80F48::from(bank.emissions_remaining), I80F48!(700));
+    }
 }
@@ -8,9 +8,12 @@ use crate::events::{GroupEventHeader, LendingPoolBankAccrueInterestEvent};
 use crate::{
     assert_struct_align, assert_struct_size, check,
     constants::{
-        EMISSION_FLAGS, FEE_VAULT_AUTHORITY_SEED, FEE_VAULT_SEED, GROUP_FLAGS,
-        INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED, LIQUIDITY_VAULT_AUTHORITY_SEED,
-        LIQUIDITY_VAULT_SEED, MAX_ORACLE_KEYS, MAX_PYTH_ORACLE_AGE, MAX_SWB_ORACLE_AGE,
+        ADL_INITIAL_BORROWER_THRESHOLD_RATIO, ADL_ROUND_DURATION, ADL_TARGET_UTILIZATION_RATIO,
+        ADL_THRESHOLD_HALVING_PERIOD, BANKRUPT_THRESHOLD, EMISSIONS_FLAG_BORROW_ACTIVE,
+        EMISSIONS_FLAG_LENDING_ACTIVE, EMISSION_FLAGS, FEE_VAULT_AUTHORITY_SEED, FEE_VAULT_SEED,
+        GROUP_FLAGS, INSURANCE_VAULT_AUTHORITY_SEED, INSURANCE_VAULT_SEED,
+        INSURANCE_VIRTUAL_SHARES, LIQUIDITY_VAULT_AUTHORITY_SEED, LIQUIDITY_VAULT_SEED,
+        MAX_ORACLE_KEYS, MAX_PYTH_ORACLE_AGE, MAX_SWB_ORACLE_AGE,
         PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG, PYTH_ID, SECONDS_PER_YEAR,
         TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE,
     },
@@ -44,8 +47,40 @@ use type_layout::TypeLayout;
 #[derive(Default)]
 pub struct MarginfiGroup {
     pub admin: Pubkey,
-    pub _padding_0: [[u64; 2]; 32],
-    pub _padding_1: [[u64; 2]; 32],
+    /// Mint of the group insurance fund, `Pubkey::default()` if the fund is not initialized.
+    ///
+    /// The group insurance fund covers bad debt of banks with the same mint that their own
+    /// insurance vault can't cover, before the remaining loss is socialized.
+    pub insurance_fund_mint: Pubkey,
+    pub insurance_fund_vault: Pubkey,
+    pub insurance_fund_vault_bump: u8,
+    pub insurance_fund_vault_authority_bump: u8,
+    pub _pad0: [u8; 14],
+    /// Accounts with equity weighted assets below this USD value can be handled as bankrupt, 0
+    /// falls back to `BANKRUPT_THRESHOLD`.
+    ///
+    /// Should be set around the cost of liquidating the residual assets, below which no
+    /// liquidator will close the account. Residual assets are swept to the insurance vaults when
+    /// the bankruptcy is handled.
+    pub bankrupt_threshold: WrappedI80F48,
+    /// Split of the collected group fees of every bank, applied by `lending_pool_distribute_bank_fees`.
+    ///
+    /// Unused destinations are zeroed, shares of used destinations add up to 100%.
+    pub fee_distribution: [FeeDestination; MAX_FEE_DESTINATIONS],
+    /// Protocol owned marginfi account receiving the group fees of every bank as deposits, see
+    /// `lending_pool_collect_bank_fees_as_deposit`. `Pubkey::default()` if not configured.
+    pub fee_marginfi_account: Pubkey,
+    /// Programs allowed to start and end flashloans through CPI, unused entries are
+    /// `Pubkey::default()`.
+    ///
+    /// A CPI flashloan must still be ended by a top level `lending_account_end_flashloan`
+    /// instruction later in the same transaction, where the account is health checked.
+    pub flashloan_cpi_callers: [Pubkey; MAX_FLASHLOAN_CPI_CALLERS],
+    /// Authorities of accounts exempt from the bank flashloan fee, e.g. whitelisted liquidators.
+    /// Unused entries are `Pubkey::default()`.
+    pub flashloan_fee_exempt_authorities: [Pubkey; MAX_FLASHLOAN_FEE_EXEMPT_AUTHORITIES],
+    pub _padding_0: [[u64; 2]; 6],
+    pub _padding_1: [[u64; 2]; 24],
 }
 
 impl MarginfiGroup {
@@ -55,6 +90,28 @@ impl MarginfiGroup {
     pub fn configure(&mut self, config: &GroupConfig) -> MarginfiResult {
         set_if_some!(self.admin, config.admin);
 
+        if let Some(bankrupt_threshold) = config.bankrupt_threshold {
+            check!(
+                I80F48::from(bankrupt_threshold) >= I80F48::ZERO,
+                MarginfiError::InvalidConfig
+            );
+            self.bankrupt_threshold = bankrupt_threshold;
+        }
+
+        if let Some(fee_distribution) = config.fee_distribution {
+            validate_fee_distribution(&fee_distribution)?;
+            self.fee_distribution = fee_distribution;
+        }
+
+        set_if_some!(self.fee_marginfi_account, config.fee_marginfi_account);
+
+        set_if_some!(self.flashloan_cpi_callers, config.flashloan_cpi_callers);
+
+        set_if_some!(
+            self.flashloan_fee_exempt_authorities,
+            config.flashloan_fee_exempt_authorities
+        );
+
         Ok(())
     }
 
@@ -65,12 +122,153 @@ impl MarginfiGroup {
     pub fn set_initial_configuration(&mut self, admin_pk: Pubkey) {
         self.admin = admin_pk;
     }
+
+    pub fn get_bankrupt_threshold(&self) -> I80F48 {
+        let bankrupt_threshold = I80F48::from(self.bankrupt_threshold);
+
+        if bankrupt_threshold == I80F48::ZERO {
+            BANKRUPT_THRESHOLD
+        } else {
+            bankrupt_threshold
+        }
+    }
+
+    pub fn is_insurance_fund_initialized(&self) -> bool {
+        self.insurance_fund_mint != Pubkey::default()
+    }
+
+    /// Only banks holding the fund mint can top up the fund and be covered by it.
+    pub fn is_insurance_fund_mint(&self, bank: &Bank) -> bool {
+        self.is_insurance_fund_initialized() && self.insurance_fund_mint == bank.mint
+    }
+
+    pub fn is_fee_marginfi_account_configured(&self) -> bool {
+        self.fee_marginfi_account != Pubkey::default()
+    }
+
+    pub fn is_fee_distribution_configured(&self) -> bool {
+        self.fee_distribution.iter().any(|d| !d.is_empty())
+    }
+
+    /// Share of `amount` going to each fee destination, rounded down.
+    pub fn get_fee_distribution_amounts(
+        &self,
+        amount: u64,
+    ) -> MarginfiResult<[u64; MAX_FEE_DESTINATIONS]> {
+        let mut amounts = [0u64; MAX_FEE_DESTINATIONS];
+
+        for (destination, destination_amount) in
+            self.fee_distribution.iter().zip(amounts.iter_mut())
+        {
+            if !destination.is_empty() {
+                *destination_amount = destination.get_share(amount)?;
+            }
+        }
+
+        Ok(amounts)
+    }
+
+    pub fn is_flashloan_cpi_caller(&self, program_id: &Pubkey) -> bool {
+        *program_id != Pubkey::default() && self.flashloan_cpi_callers.contains(program_id)
+    }
+
+    pub fn is_flashloan_fee_exempt(&self, authority: &Pubkey) -> bool {
+        *authority != Pubkey::default() && self.flashloan_fee_exempt_authorities.contains(authority)
+    }
+}
+
+pub const MAX_FEE_DESTINATIONS: usize = 4;
+pub const MAX_FLASHLOAN_CPI_CALLERS: usize = 4;
+pub const MAX_FLASHLOAN_FEE_EXEMPT_AUTHORITIES: usize = 4;
+
+/// Fees are sent to a token account of `owner` for the bank mint.
+pub const FEE_DESTINATION_TOKEN_ACCOUNT: u8 = 1;
+/// Fees are sent to the insurance vault of the bank.
+pub const FEE_DESTINATION_INSURANCE_VAULT: u8 = 2;
+
+assert_struct_size!(FeeDestination, 40);
+#[zero_copy]
+#[repr(C)]
+#[cfg_attr(
+    any(feature = "test", feature = "client"),
+    derive(PartialEq, Eq, TypeLayout)
+)]
+#[derive(Default, Debug, AnchorSerialize, AnchorDeserialize)]
+pub struct FeeDestination {
+    /// Owner of the destination token accounts, unused for the insurance vault destination.
+    pub owner: Pubkey,
+    pub share_bps: u16,
+    /// `FEE_DESTINATION_TOKEN_ACCOUNT` or `FEE_DESTINATION_INSURANCE_VAULT`, 0 if unused.
+    pub destination_type: u8,
+    pub _pad0: [u8; 5],
+}
+
+impl FeeDestination {
+    pub fn is_empty(&self) -> bool {
+        self.destination_type == 0
+    }
+
+    /// Share of `amount` going to this destination, rounded down.
+    pub fn get_share(&self, amount: u64) -> MarginfiResult<u64> {
+        (amount as u128)
+            .checked_mul(self.share_bps as u128)
+            .and_then(|v| v.checked_div(10_000))
+            .and_then(|v| v.try_into().ok())
+            .ok_or_else(math_error!())
+    }
+}
+
+fn validate_fee_distribution(
+    fee_distribution: &[FeeDestination; MAX_FEE_DESTINATIONS],
+) -> MarginfiResult {
+    let mut total_share_bps: u32 = 0;
+
+    for destination in fee_distribution.iter() {
+        match destination.destination_type {
+            0 => {
+                check!(
+                    destination.owner == Pubkey::default() && destination.share_bps == 0,
+                    MarginfiError::InvalidConfig,
+                    "Unused fee destinations must be zeroed"
+                );
+                continue;
+            }
+            FEE_DESTINATION_TOKEN_ACCOUNT => check!(
+                destination.owner != Pubkey::default(),
+                MarginfiError::InvalidConfig,
+                "Fee destination owner not set"
+            ),
+            FEE_DESTINATION_INSURANCE_VAULT => {}
+            _ => return err!(MarginfiError::InvalidConfig),
+        }
+
+        check!(destination.share_bps > 0, MarginfiError::InvalidConfig);
+        total_share_bps += destination.share_bps as u32;
+    }
+
+    check!(
+        total_share_bps == 0 || total_share_bps == 10_000,
+        MarginfiError::InvalidConfig,
+        "Fee destination shares must add up to 10000 bps"
+    );
+
+    Ok(())
 }
 
 #[cfg_attr(any(feature = "test", feature = "client"), derive(TypeLayout))]
 #[derive(AnchorSerialize, AnchorDeserialize, Default, Debug, Clone)]
 pub struct GroupConfig {
     pub admin: Option<Pubkey>,
+    /// USD value, see `MarginfiGroup::bankrupt_threshold`
+    pub bankrupt_threshold: Option<WrappedI80F48>,
+    /// See `MarginfiGroup::fee_distribution`, pass all zeroed destinations to disable
+    pub fee_distribution: Option<[FeeDestination; MAX_FEE_DESTINATIONS]>,
+    /// See `MarginfiGroup::fee_marginfi_account`, pass `Pubkey::default()` to disable
+    pub fee_marginfi_account: Option<Pubkey>,
+    /// See `MarginfiGroup::flashloan_cpi_callers`, replaces the whole whitelist
+    pub flashloan_cpi_callers: Option<[Pubkey; MAX_FLASHLOAN_CPI_CALLERS]>,
+    /// See `MarginfiGroup::flashloan_fee_exempt_authorities`, replaces the whole list
+    pub flashloan_fee_exempt_authorities: Option<[Pubkey; MAX_FLASHLOAN_FEE_EXEMPT_AUTHORITIES]>,
 }
 
 /// Load and validate a pyth price feed account.
@@ -98,6 +296,10 @@ pub struct InterestRateConfigCompact {
     pub insurance_ir_fee: WrappedI80F48,
     pub protocol_fixed_fee_apr: WrappedI80F48,
     pub protocol_ir_fee: WrappedI80F48,
+
+    pub origination_fee_bps: u16,
+    pub origination_fee_insurance_share_bps: u16,
+    pub flashloan_fee_bps: u16,
 }
 
 impl From<InterestRateConfigCompact> for InterestRateConfig {
@@ -110,7 +312,11 @@ impl From<InterestRateConfigCompact> for InterestRateConfig {
             insurance_ir_fee: ir_config.insurance_ir_fee,
             protocol_fixed_fee_apr: ir_config.protocol_fixed_fee_apr,
             protocol_ir_fee: ir_config.protocol_ir_fee,
-            _padding: [[0; 2]; 8],
+            origination_fee_bps: ir_config.origination_fee_bps,
+            origination_fee_insurance_share_bps: ir_config.origination_fee_insurance_share_bps,
+            flashloan_fee_bps: ir_config.flashloan_fee_bps,
+            _pad0: [0; 10],
+            _padding: [[0; 2]; 7],
         }
     }
 }
@@ -125,6 +331,9 @@ impl From<InterestRateConfig> for InterestRateConfigCompact {
             insurance_ir_fee: ir_config.insurance_ir_fee,
             protocol_fixed_fee_apr: ir_config.protocol_fixed_fee_apr,
             protocol_ir_fee: ir_config.protocol_ir_fee,
+            origination_fee_bps: ir_config.origination_fee_bps,
+            origination_fee_insurance_share_bps: ir_config.origination_fee_insurance_share_bps,
+            flashloan_fee_bps: ir_config.flashloan_fee_bps,
         }
     }
 }
@@ -148,7 +357,18 @@ pub struct InterestRateConfig {
     pub protocol_fixed_fee_apr: WrappedI80F48,
     pub protocol_ir_fee: WrappedI80F48,
 
-    pub _padding: [[u64; 2]; 8], // 16 * 8 = 128 bytes
+    /// One-time fee charged on borrows and added to the liability, in basis points
+    pub origination_fee_bps: u16,
+    /// Part of the origination fee going to the insurance fund, the rest goes to the group,
+    /// in basis points
+    pub origination_fee_insurance_share_bps: u16,
+    /// Fee charged when a flashloan ends on the net liability increase of each balance since it
+    /// started, added to the liability, in basis points. Split with the insurance fund like the
+    /// origination fee.
+    pub flashloan_fee_bps: u16,
+    pub _pad0: [u8; 10],
+
+    pub _padding: [[u64; 2]; 7], // 16 * 7 = 112 bytes
 }
 
 impl InterestRateConfig {
@@ -240,10 +460,56 @@ impl InterestRateConfig {
         check!(plateau_ir > I80F48::ZERO, MarginfiError::InvalidConfig);
         check!(max_ir > I80F48::ZERO, MarginfiError::InvalidConfig);
         check!(plateau_ir < max_ir, MarginfiError::InvalidConfig);
+        check!(
+            self.origination_fee_bps <= 10_000,
+            MarginfiError::InvalidConfig
+        );
+        check!(
+            self.origination_fee_insurance_share_bps <= 10_000,
+            MarginfiError::InvalidConfig
+        );
+        check!(
+            self.flashloan_fee_bps <= 10_000,
+            MarginfiError::InvalidConfig
+        );
 
         Ok(())
     }
 
+    /// Origination fee charged on a borrow of `amount`
+    pub fn get_origination_fee(&self, amount: I80F48) -> MarginfiResult<I80F48> {
+        amount
+            .checked_mul(I80F48::from_num(self.origination_fee_bps))
+            .ok_or_else(math_error!())?
+            .checked_div(I80F48::from_num(10_000))
+            .ok_or_else(math_error!())
+    }
+
+    /// Flashloan fee charged on a net borrow of `amount` over a flashloan
+    pub fn get_flashloan_fee(&self, amount: I80F48) -> MarginfiResult<I80F48> {
+        amount
+            .checked_mul(I80F48::from_num(self.flashloan_fee_bps))
+            .ok_or_else(math_error!())?
+            .checked_div(I80F48::from_num(10_000))
+            .ok_or_else(math_error!())
+    }
+
+    /// Fee charged on a net borrow of `amount` when a flashloan ends. Borrows within a flashloan
+    /// pay no origination fee, so it is at least the origination fee, exempt accounts pay only
+    /// the origination fee.
+    pub fn get_flashloan_end_fee(
+        &self,
+        amount: I80F48,
+        is_flashloan_fee_exempt: bool,
+    ) -> MarginfiResult<I80F48> {
+        let origination_fee = self.get_origination_fee(amount)?;
+        if is_flashloan_fee_exempt {
+            return Ok(origination_fee);
+        }
+
+        Ok(origination_fee.max(self.get_flashloan_fee(amount)?))
+    }
+
     pub fn update(&mut self, ir_config: &InterestRateConfigOpt) {
         set_if_some!(
             self.optimal_utilization_rate,
@@ -261,6 +527,12 @@ impl InterestRateConfig {
             ir_config.protocol_fixed_fee_apr
         );
         set_if_some!(self.protocol_ir_fee, ir_config.protocol_ir_fee);
+        set_if_some!(self.origination_fee_bps, ir_config.origination_fee_bps);
+        set_if_some!(
+            self.origination_fee_insurance_share_bps,
+            ir_config.origination_fee_insurance_share_bps
+        );
+        set_if_some!(self.flashloan_fee_bps, ir_config.flashloan_fee_bps);
     }
 }
 
@@ -278,6 +550,10 @@ pub struct InterestRateConfigOpt {
     pub insurance_ir_fee: Option<WrappedI80F48>,
     pub protocol_fixed_fee_apr: Option<WrappedI80F48>,
     pub protocol_ir_fee: Option<WrappedI80F48>,
+
+    pub origination_fee_bps: Option<u16>,
+    pub origination_fee_insurance_share_bps: Option<u16>,
+    pub flashloan_fee_bps: Option<u16>,
 }
 
 assert_struct_size!(Bank, 1856);
@@ -336,13 +612,59 @@ pub struct Bank {
     /// - PERMISSIONLESS_BAD_DEBT_SETTLEMENT: 4
     ///
     pub flags: u64,
-    /// Emissions APR.
-    /// Number of emitted tokens (emissions_mint) per 1e(bank.mint_decimal) tokens (bank mint) (native amount) per 1 YEAR.
+    /// Number of emitted tokens (emissions_mint) (native amount) per second, split pro-rata
+    /// between the shares of the active side. If both sides are active, the rate is split evenly
+    /// between lenders and borrowers.
     pub emissions_rate: u64,
     pub emissions_remaining: WrappedI80F48,
     pub emissions_mint: Pubkey,
 
-    pub _padding_0: [[u64; 2]; 28],
+    /// Mint of the insurance vault share tokens, `Pubkey::default()` if insurance staking is not
+    /// initialized.
+    ///
+    /// The staker share count is tracked by `insurance_staker_shares` and not by the mint supply,
+    /// shares burned outside of `lending_pool_insurance_request_unstake` are forfeited.
+    pub insurance_share_mint: Pubkey,
+    /// Insurance vault shares owned by the protocol, covering the vault balance that predates
+    /// insurance staking. Only these shares can be withdrawn by the admin.
+    pub insurance_protocol_shares: u64,
+    /// Insurance vault shares burned by stakers waiting for their unstake cooldown to end.
+    pub insurance_pending_unstake_shares: u64,
+    /// Time in seconds between an unstake request and its claim.
+    pub insurance_unstake_cooldown: u64,
+    pub insurance_share_mint_bump: u8,
+    pub _pad3: [u8; 7],
+
+    /// Cumulative native amount of bad debt written off by bankruptcy handling.
+    pub bad_debt_written_off: WrappedI80F48,
+    /// Cumulative native amount of written off bad debt repaid to the insurance vault.
+    pub bad_debt_recovered: WrappedI80F48,
+
+    /// Cumulative emissions (native amount) per asset share.
+    pub emissions_asset_index: WrappedI80F48,
+    /// Cumulative emissions (native amount) per liability share.
+    pub emissions_liability_index: WrappedI80F48,
+    /// Last time the emissions indices were updated.
+    pub emissions_last_update: i64,
+    /// Emissions are only distributed from this time, 0 for no scheduled start.
+    pub emissions_start_time: i64,
+    /// Emissions are only distributed until this time, 0 for no scheduled end.
+    pub emissions_end_time: i64,
+    /// Time the bank emissions moved to the emissions indices, 0 while emissions still accrue per
+    /// balance with `emissions_rate` in the legacy unit, see `Bank::activate_emissions_index`.
+    pub emissions_index_activated_at: i64,
+
+    /// Insurance vault shares minted to stakers and not yet burned by an unstake request.
+    pub insurance_staker_shares: u64,
+    /// Start of the current auto deleveraging round, 0 if the bank is not being deleveraged.
+    pub adl_round_started_at: i64,
+
+    /// Emissions rate in the legacy unit (emitted tokens per 1e(bank.mint_decimal) tokens per year)
+    /// when the emissions indices were activated, for balances last updated before.
+    pub emissions_legacy_rate: u64,
+    pub _pad5: [u8; 8],
+
+    pub _padding_0: [[u64; 2]; 16],
     pub _padding_1: [[u64; 2]; 32], // 16 * 2 * 32 = 1024B
 }
 
@@ -520,6 +842,76 @@ impl Bank {
         Ok(())
     }
 
+    /// Total liabilities over total assets.
+    ///
+    /// Is `I80F48::MAX` if the bank has liabilities but no assets left, e.g. after socializing a loss.
+    pub fn get_utilization_ratio(&self) -> MarginfiResult<I80F48> {
+        let total_assets = self.get_asset_amount(self.total_asset_shares.into())?;
+        let total_liabilities = self.get_liability_amount(self.total_liability_shares.into())?;
+
+        if total_assets == I80F48::ZERO {
+            return Ok(if total_liabilities == I80F48::ZERO {
+                I80F48::ZERO
+            } else {
+                I80F48::MAX
+            });
+        }
+
+        total_liabilities
+            .checked_div(total_assets)
+            .ok_or_else(math_error!())
+    }
+
+    /// Amount of liabilities to repay to bring the utilization ratio down to
+    /// `ADL_TARGET_UTILIZATION_RATIO`. Zero if the bank is not over-utilized.
+    pub fn get_auto_deleverage_amount(&self) -> MarginfiResult<I80F48> {
+        let total_assets = self.get_asset_amount(self.total_asset_shares.into())?;
+        let total_liabilities = self.get_liability_amount(self.total_liability_shares.into())?;
+
+        if total_liabilities <= total_assets {
+            return Ok(I80F48::ZERO);
+        }
+
+        let target_liabilities = total_assets
+            .checked_mul(ADL_TARGET_UTILIZATION_RATIO)
+            .ok_or_else(math_error!())?;
+
+        total_liabilities
+            .checked_sub(target_liabilities)
+            .ok_or_else(math_error!())
+    }
+
+    /// Start an auto deleveraging round, unless one started less than `ADL_ROUND_DURATION` ago.
+    pub fn start_auto_deleverage_round(&mut self, current_timestamp: i64) {
+        if self.adl_round_started_at == 0
+            || current_timestamp - self.adl_round_started_at > ADL_ROUND_DURATION
+        {
+            self.adl_round_started_at = current_timestamp;
+        }
+    }
+
+    /// Minimum liability amount of a borrower to be auto deleveraged.
+    ///
+    /// Starts at `ADL_INITIAL_BORROWER_THRESHOLD_RATIO` of the total liabilities and is halved every
+    /// `ADL_THRESHOLD_HALVING_PERIOD` of the round, so the largest borrowers are deleveraged first.
+    pub fn get_auto_deleverage_borrower_threshold(
+        &self,
+        current_timestamp: i64,
+    ) -> MarginfiResult<I80F48> {
+        let halvings =
+            (current_timestamp - self.adl_round_started_at).max(0) / ADL_THRESHOLD_HALVING_PERIOD;
+
+        if halvings >= 64 {
+            return Ok(I80F48::ZERO);
+        }
+
+        let total_liabilities = self.get_liability_amount(self.total_liability_shares.into())?;
+
+        total_liabilities
+            .checked_mul(ADL_INITIAL_BORROWER_THRESHOLD_RATIO >> halvings as u32)
+            .ok_or_else(math_error!())
+    }
+
     pub fn configure(&mut self, config: &BankConfigOpt) -> MarginfiResult {
         set_if_some!(self.config.asset_weight_init, config.asset_weight_init);
         set_if_some!(self.config.asset_weight_maint, config.asset_weight_maint);
@@ -554,6 +946,14 @@ impl Bank {
 
         set_if_some!(self.config.oracle_max_age, config.oracle_max_age);
 
+        set_if_some!(self.config.fixed_price, config.fixed_price);
+
+        set_if_some!(self.config.oracle_max_slot_age, config.oracle_max_slot_age);
+
+        set_if_some!(self.config.oracle_min_price, config.oracle_min_price);
+
+        set_if_some!(self.config.oracle_max_price, config.oracle_max_price);
+
         if let Some(flag) = config.permissionless_bad_debt_settlement {
             self.update_flag(flag, PERMISSIONLESS_BAD_DEBT_SETTLEMENT_FLAG);
         }
@@ -655,6 +1055,37 @@ impl Bank {
         Ok(())
     }
 
+    /// Book a borrow fee (origination or flashloan fee) as outstanding insurance and group fees,
+    /// split according to `origination_fee_insurance_share_bps`.
+    pub fn collect_borrow_fee(&mut self, fee: I80F48) -> MarginfiResult {
+        let insurance_fee = fee
+            .checked_mul(I80F48::from_num(
+                self.config
+                    .interest_rate_config
+                    .origination_fee_insurance_share_bps,
+            ))
+            .ok_or_else(math_error!())?
+            .checked_div(I80F48::from_num(10_000))
+            .ok_or_else(math_error!())?;
+        let group_fee = fee.checked_sub(insurance_fee).ok_or_else(math_error!())?;
+
+        self.collected_insurance_fees_outstanding = {
+            insurance_fee
+                .checked_add(self.collected_insurance_fees_outstanding.into())
+                .ok_or_else(math_error!())?
+                .into()
+        };
+
+        self.collected_group_fees_outstanding = {
+            group_fee
+                .checked_add(self.collected_group_fees_outstanding.into())
+                .ok_or_else(math_error!())?
+                .into()
+        };
+
+        Ok(())
+    }
+
     pub fn deposit_spl_transfer<'info>(
         &self,
         amount: u64,
@@ -670,8 +1101,55 @@ impl Bank {
             MarginfiError::InvalidTransfer
         );
 
+        Self::user_spl_transfer(
+            amount,
+            from,
+            to,
+            authority,
+            maybe_mint,
+            program,
+            remaining_accounts,
+        )
+    }
+
+    /// Transfer user funds into the insurance vault, used by insurance stakers.
+    pub fn insurance_deposit_spl_transfer<'info>(
+        &self,
+        amount: u64,
+        from: AccountInfo<'info>,
+        to: AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        maybe_mint: Option<&InterfaceAccount<'info, Mint>>,
+        program: AccountInfo<'info>,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> MarginfiResult {
+        check!(
+            to.key.eq(&self.insurance_vault),
+            MarginfiError::InvalidTransfer
+        );
+
+        Self::user_spl_transfer(
+            amount,
+            from,
+            to,
+            authority,
+            maybe_mint,
+            program,
+            remaining_accounts,
+        )
+    }
+
+    fn user_spl_transfer<'info>(
+        amount: u64,
+        from: AccountInfo<'info>,
+        to: AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        maybe_mint: Option<&InterfaceAccount<'info, Mint>>,
+        program: AccountInfo<'info>,
+        remaining_accounts: &[AccountInfo<'info>],
+    ) -> MarginfiResult {
         debug!(
-            "deposit_spl_transfer: amount: {} from {} to {}, auth {}",
+            "user_spl_transfer: amount: {} from {} to {}, auth {}",
             amount, from.key, to.key, authority.key
         );
 
@@ -777,6 +1255,109 @@ impl Bank {
         Ok(())
     }
 
+    pub fn is_insurance_staking_initialized(&self) -> bool {
+        self.insurance_share_mint != Pubkey::default()
+    }
+
+    /// Total insurance vault shares including protocol owned shares, shares pending unstake and
+    /// `INSURANCE_VIRTUAL_SHARES`.
+    fn get_insurance_total_shares(&self) -> MarginfiResult<u64> {
+        Ok(self
+            .insurance_staker_shares
+            .checked_add(self.insurance_protocol_shares)
+            .ok_or_else(math_error!())?
+            .checked_add(self.insurance_pending_unstake_shares)
+            .ok_or_else(math_error!())?
+            .checked_add(INSURANCE_VIRTUAL_SHARES)
+            .ok_or_else(math_error!())?)
+    }
+
+    /// Shares minted for `amount` tokens deposited into an insurance vault holding
+    /// `vault_amount` tokens before the deposit.
+    pub fn get_insurance_shares_for_amount(
+        &self,
+        amount: u64,
+        vault_amount: u64,
+    ) -> MarginfiResult<u64> {
+        mul_div_floor(
+            amount,
+            self.get_insurance_total_shares()?,
+            vault_amount
+                .checked_add(INSURANCE_VIRTUAL_SHARES)
+                .ok_or_else(math_error!())?,
+        )
+    }
+
+    /// Tokens owed for `shares` of an insurance vault holding `vault_amount` tokens.
+    pub fn get_insurance_amount_for_shares(
+        &self,
+        shares: u64,
+        vault_amount: u64,
+    ) -> MarginfiResult<u64> {
+        let amount = mul_div_floor(
+            shares,
+            vault_amount
+                .checked_add(INSURANCE_VIRTUAL_SHARES)
+                .ok_or_else(math_error!())?,
+            self.get_insurance_total_shares()?,
+        )?;
+
+        // The virtual tokens don't exist
+        Ok(amount.min(vault_amount))
+    }
+
+    /// Mint protocol owned insurance shares for `amount` tokens of group fees sent to an insurance
+    /// vault holding `vault_amount` tokens before the transfer, so that group fees don't accrue to
+    /// stakers. No-op if insurance staking is not initialized.
+    pub fn deposit_protocol_insurance(&mut self, amount: u64, vault_amount: u64) -> MarginfiResult {
+        if !self.is_insurance_staking_initialized() {
+            return Ok(());
+        }
+
+        let shares = self.get_insurance_shares_for_amount(amount, vault_amount)?;
+
+        self.insurance_protocol_shares = self
+            .insurance_protocol_shares
+            .checked_add(shares)
+            .ok_or_else(math_error!())?;
+
+        Ok(())
+    }
+
+    /// Burn the protocol owned insurance shares backing an admin withdrawal of `amount` tokens
+    /// from the insurance vault. No-op if insurance staking is not initialized.
+    pub fn withdraw_protocol_insurance(
+        &mut self,
+        amount: u64,
+        vault_amount: u64,
+    ) -> MarginfiResult {
+        if !self.is_insurance_staking_initialized() {
+            return Ok(());
+        }
+
+        check!(
+            vault_amount > 0,
+            MarginfiError::InsuranceWithdrawExceedsProtocolShares
+        );
+
+        let shares = mul_div_ceil(
+            amount,
+            self.get_insurance_total_shares()?,
+            vault_amount
+                .checked_add(INSURANCE_VIRTUAL_SHARES)
+                .ok_or_else(math_error!())?,
+        )?;
+
+        check!(
+            shares <= self.insurance_protocol_shares,
+            MarginfiError::InsuranceWithdrawExceedsProtocolShares
+        );
+
+        self.insurance_protocol_shares -= shares;
+
+        Ok(())
+    }
+
     pub fn assert_operational_mode(
         &self,
         is_asset_or_liability_amount_increasing: Option<bool>,
@@ -803,6 +1384,139 @@ impl Bank {
         (self.flags & flag) == flag
     }
 
+    pub fn get_emissions_index(&self, side: BalanceSide) -> I80F48 {
+        match side {
+            BalanceSide::Assets => self.emissions_asset_index.into(),
+            BalanceSide::Liabilities => self.emissions_liability_index.into(),
+        }
+    }
+
+    /// Distribute the emissions since the last update to the shares of the active sides, capped
+    /// by the remaining emissions. Only the time within the emissions schedule is accounted for.
+    ///
+    /// Emissions are not distributed while a side has no shares, and stay in `emissions_remaining`.
+    pub fn update_emissions_index(&mut self, current_timestamp: i64) -> MarginfiResult {
+        if !self.is_emissions_index_active() || current_timestamp <= self.emissions_last_update {
+            return Ok(());
+        }
+
+        let period_start = self.emissions_last_update.max(self.emissions_start_time);
+        let period_end = if self.emissions_end_time == 0 {
+            current_timestamp
+        } else {
+            current_timestamp.min(self.emissions_end_time)
+        };
+        self.emissions_last_update = current_timestamp;
+
+        if period_end <= period_start {
+            return Ok(());
+        }
+
+        let period = period_end
+            .checked_sub(period_start)
+            .ok_or_else(math_error!())?;
+
+        let total_asset_shares = I80F48::from(self.total_asset_shares);
+        let total_liability_shares = I80F48::from(self.total_liability_shares);
+        let lending_active = self.get_flag(EMISSIONS_FLAG_LENDING_ACTIVE);
+        let borrow_active = self.get_flag(EMISSIONS_FLAG_BORROW_ACTIVE);
+
+        let side_emissions = {
+            let emissions = I80F48::from_num(self.emissions_rate)
+                .checked_mul(I80F48::from_num(period))
+                .ok_or_else(math_error!())?;
+
+            if lending_active && borrow_active {
+                emissions
+                    .checked_div(I80F48::from_num(2))
+                    .ok_or_else(math_error!())?
+            } else {
+                emissions
+            }
+        };
+
+        let mut emissions_remaining = I80F48::from(self.emissions_remaining);
+
+        for (active, total_shares, index) in [
+            (
+                lending_active,
+                total_asset_shares,
+                &mut self.emissions_asset_index,
+            ),
+            (
+                borrow_active,
+                total_liability_shares,
+                &mut self.emissions_liability_index,
+            ),
+        ] {
+            if !active || total_shares <= I80F48::ZERO {
+                continue;
+            }
+
+            let emissions = side_emissions.min(emissions_remaining);
+
+            if emissions != side_emissions {
+                msg!(
+                    "Emissions capped: {} ({} calculated) for period {}s",
+                    emissions,
+                    side_emissions,
+                    period
+                );
+            }
+
+            *index = I80F48::from(*index)
+                .checked_add(
+                    emissions
+                        .checked_div(total_shares)
+                        .ok_or_else(math_error!())?,
+                )
+                .ok_or_else(math_error!())?
+                .into();
+            emissions_remaining = emissions_remaining
+                .checked_sub(emissions)
+                .ok_or_else(math_error!())?;
+        }
+
+        self.emissions_remaining = emissions_remaining.into();
+
+        Ok(())
+    }
+
+    pub fn validate_emissions_schedule(start_time: i64, end_time: i64) -> MarginfiResult {
+        check!(
+            start_time >= 0 && end_time >= 0 && (end_time == 0 || end_time > start_time),
+            MarginfiError::InvalidEmissionsSchedule
+        );
+
+        Ok(())
+    }
+
+    /// Emissions accrue through the emissions indices, see `Bank::activate_emissions_index`.
+    pub fn is_emissions_index_active(&self) -> bool {
+        self.emissions_index_activated_at != 0
+    }
+
+    /// Move the bank emissions to the emissions indices, from then on `emissions_rate` is in
+    /// emitted tokens per second.
+    ///
+    /// Banks set up before the emissions indices keep accruing emissions per balance with the
+    /// legacy rate until the admin sets a new rate. The legacy rate is kept to settle the balances
+    /// last updated before the activation, see `BankAccountWrapper::claim_emissions`.
+    pub fn activate_emissions_index(&mut self, current_timestamp: i64) {
+        if self.is_emissions_index_active() {
+            return;
+        }
+
+        self.emissions_legacy_rate = self.emissions_rate;
+        self.emissions_index_activated_at = current_timestamp;
+        self.emissions_last_update = current_timestamp;
+    }
+
+    /// Emissions have a scheduled end, and it has passed.
+    pub fn is_emissions_ended(&self, current_timestamp: i64) -> bool {
+        self.emissions_end_time != 0 && current_timestamp >= self.emissions_end_time
+    }
+
     pub(crate) fn override_emissions_flag(&mut self, flag: u64) {
         assert!(Self::verify_emissions_flags(flag));
         self.flags = flag;
@@ -992,13 +1706,34 @@ pub struct BankConfigCompact {
 
     /// Time window in seconds for the oracle price feed to be considered live.
     pub oracle_max_age: u16,
+
+    /// Price of one UI token used when `oracle_setup` is `OracleSetup::Fixed`.
+    pub fixed_price: WrappedI80F48,
+
+    /// Time window in slots for the oracle price feed to be considered live, 0 disables the check.
+    pub oracle_max_slot_age: u16,
+
+    /// Lower bound of the oracle price, prices below it are rejected. 0 disables the bound.
+    ///
+    /// Value is the USD price of one UI token with `ORACLE_PRICE_BAND_DECIMALS` decimals,
+    /// for example value 1_500_000_000 -> $1.5
+    pub oracle_min_price: u64,
+
+    /// Upper bound of the oracle price, prices above it are rejected. 0 disables the bound.
+    ///
+    /// Same format as `oracle_min_price`
+    pub oracle_max_price: u64,
+
+    /// Second oracle key, the SPL stake pool of `OracleSetup::StakePoolPythPush` banks.
+    /// `Pubkey::default()` for other oracle setups.
+    pub secondary_oracle_key: Pubkey,
 }
 
 impl From<BankConfigCompact> for BankConfig {
     fn from(config: BankConfigCompact) -> Self {
         let keys = [
             config.oracle_key,
-            Pubkey::default(),
+            config.secondary_oracle_key,
             Pubkey::default(),
             Pubkey::default(),
             Pubkey::default(),
@@ -1019,7 +1754,11 @@ impl From<BankConfigCompact> for BankConfig {
             _pad1: [0; 7],
             total_asset_value_init_limit: config.total_asset_value_init_limit,
             oracle_max_age: config.oracle_max_age,
-            _padding: [0; 38],
+            oracle_max_slot_age: config.oracle_max_slot_age,
+            _pad2: [0; 4],
+            fixed_price: config.fixed_price,
+            oracle_min_price: config.oracle_min_price,
+            oracle_max_price: config.oracle_max_price,
         }
     }
 }
@@ -1041,6 +1780,11 @@ impl From<BankConfig> for BankConfigCompact {
             _pad0: [0; 7],
             total_asset_value_init_limit: config.total_asset_value_init_limit,
             oracle_max_age: config.oracle_max_age,
+            fixed_price: config.fixed_price,
+            oracle_max_slot_age: config.oracle_max_slot_age,
+            oracle_min_price: config.oracle_min_price,
+            oracle_max_price: config.oracle_max_price,
+            secondary_oracle_key: config.oracle_keys[1],
         }
     }
 }
@@ -1055,6 +1799,9 @@ assert_struct_align!(BankConfig, 8);
 )]
 #[derive(Debug)]
 /// TODO: Convert weights to (u64, u64) to avoid precision loss (maybe?)
+///
+/// Note: the padding of the bank config is used up by `oracle_min_price` and `oracle_max_price`,
+/// new bank settings have to be added to the `Bank` padding instead.
 pub struct BankConfig {
     pub asset_weight_init: WrappedI80F48,
     pub asset_weight_maint: WrappedI80F48,
@@ -1092,7 +1839,27 @@ pub struct BankConfig {
     /// Time window in seconds for the oracle price feed to be considered live.
     pub oracle_max_age: u16,
 
-    pub _padding: [u8; 38],
+    /// Time window in slots for the oracle price feed to be considered live, 0 disables the check.
+    ///
+    /// Only supported by Pyth push and Switchboard pull based oracle setups. When set, both the
+    /// slot and the seconds (`oracle_max_age`) windows have to pass.
+    pub oracle_max_slot_age: u16,
+
+    pub _pad2: [u8; 4], // 2x u16 + 4 = 8
+
+    /// Price of one UI token used when `oracle_setup` is `OracleSetup::Fixed`, ignored otherwise.
+    pub fixed_price: WrappedI80F48,
+
+    /// Lower bound of the oracle price, prices below it are rejected. 0 disables the bound.
+    ///
+    /// Value is the USD price of one UI token with `ORACLE_PRICE_BAND_DECIMALS` decimals,
+    /// for example value 1_500_000_000 -> $1.5
+    pub oracle_min_price: u64,
+
+    /// Upper bound of the oracle price, prices above it are rejected. 0 disables the bound.
+    ///
+    /// Same format as `oracle_min_price`
+    pub oracle_max_price: u64,
 }
 
 impl Default for BankConfig {
@@ -1113,7 +1880,11 @@ impl Default for BankConfig {
             _pad1: [0; 7],
             total_asset_value_init_limit: TOTAL_ASSET_VALUE_INIT_LIMIT_INACTIVE,
             oracle_max_age: 0,
-            _padding: [0; 38],
+            oracle_max_slot_age: 0,
+            _pad2: [0; 4],
+            fixed_price: I80F48::ZERO.into(),
+            oracle_min_price: 0,
+            oracle_max_price: 0,
         }
     }
 }
@@ -1179,6 +1950,13 @@ impl BankConfig {
             check!(asset_maint_w == I80F48::ZERO, MarginfiError::InvalidConfig);
         }
 
+        if self.oracle_min_price != 0 && self.oracle_max_price != 0 {
+            check!(
+                self.oracle_min_price <= self.oracle_max_price,
+                MarginfiError::InvalidConfig
+            );
+        }
+
         Ok(())
     }
 
@@ -1192,8 +1970,8 @@ impl BankConfig {
         self.borrow_limit != u64::MAX
     }
 
-    pub fn validate_oracle_setup(&self, ais: &[AccountInfo]) -> MarginfiResult {
-        OraclePriceFeedAdapter::validate_bank_config(self, ais)?;
+    pub fn validate_oracle_setup(&self, ais: &[AccountInfo], bank_mint: &Pubkey) -> MarginfiResult {
+        OraclePriceFeedAdapter::validate_bank_config(self, ais, bank_mint)?;
         Ok(())
     }
 
@@ -1205,13 +1983,29 @@ impl BankConfig {
     pub fn get_oracle_max_age(&self) -> u64 {
         match (self.oracle_max_age, self.oracle_setup) {
             (0, OracleSetup::SwitchboardV2) => MAX_SWB_ORACLE_AGE,
-            (0, OracleSetup::PythLegacy | OracleSetup::PythPushOracle) => MAX_PYTH_ORACLE_AGE,
+            (
+                0,
+                OracleSetup::PythLegacy
+                | OracleSetup::PythPushOracle
+                | OracleSetup::StakePoolPythPush,
+            ) => MAX_PYTH_ORACLE_AGE,
             (n, _) => n as u64,
         }
     }
 
+    #[inline]
+    pub fn get_oracle_max_slot_age(&self) -> Option<u64> {
+        match self.oracle_max_slot_age {
+            0 => None,
+            n => Some(n as u64),
+        }
+    }
+
     pub fn get_pyth_push_oracle_feed_id(&self) -> Option<&FeedId> {
-        if matches!(self.oracle_setup, OracleSetup::PythPushOracle) {
+        if matches!(
+            self.oracle_setup,
+            OracleSetup::PythPushOracle | OracleSetup::StakePoolPythPush
+        ) {
             let bytes: &[u8; 32] = self.oracle_keys[0].as_ref().try_into().unwrap();
             Some(bytes)
         } else {
@@ -1220,6 +2014,31 @@ impl BankConfig {
     }
 }
 
+/// `a * b / c` rounded down, computed in u128 to avoid intermediate overflow.
+fn mul_div_floor(a: u64, b: u64, c: u64) -> MarginfiResult<u64> {
+    Ok((a as u128)
+        .checked_mul(b as u128)
+        .ok_or_else(math_error!())?
+        .checked_div(c as u128)
+        .ok_or_else(math_error!())?
+        .try_into()
+        .map_err(|_| MarginfiError::MathError)?)
+}
+
+/// `a * b / c` rounded up, computed in u128 to avoid intermediate overflow.
+fn mul_div_ceil(a: u64, b: u64, c: u64) -> MarginfiResult<u64> {
+    let c = c as u128;
+    Ok((a as u128)
+        .checked_mul(b as u128)
+        .ok_or_else(math_error!())?
+        .checked_add(c.checked_sub(1).ok_or_else(math_error!())?)
+        .ok_or_else(math_error!())?
+        .checked_div(c)
+        .ok_or_else(math_error!())?
+        .try_into()
+        .map_err(|_| MarginfiError::MathError)?)
+}
+
 #[zero_copy]
 #[repr(C, align(8))]
 #[cfg_attr(
@@ -1279,6 +2098,14 @@ pub struct BankConfigOpt {
     pub oracle_max_age: Option<u16>,
 
     pub permissionless_bad_debt_settlement: Option<bool>,
+
+    pub fixed_price: Option<WrappedI80F48>,
+
+    pub oracle_max_slot_age: Option<u16>,
+
+    pub oracle_min_price: Option<u64>,
+
+    pub oracle_max_price: Option<u64>,
 }
 
 #[cfg_attr(
@@ -1648,4 +2475,412 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn origination_fee_split() {
+        let mut bank = Bank::default();
+        bank.config.interest_rate_config.origination_fee_bps = 50;
+        bank.config
+            .interest_rate_config
+            .origination_fee_insurance_share_bps = 2_000;
+
+        let fee = bank
+            .config
+            .interest_rate_config
+            .get_origination_fee(I80F48!(1_000))
+            .unwrap();
+        assert_eq!(fee, I80F48!(5));
+
+        bank.collect_borrow_fee(fee).unwrap();
+        assert_eq!(
+            I80F48::from(bank.collected_insurance_fees_outstanding),
+            I80F48!(1)
+        );
+        assert_eq!(
+            I80F48::from(bank.collected_group_fees_outstanding),
+            I80F48!(4)
+        );
+    }
+
+    #[test]
+    fn flashloan_fee_exemption() {
+        let liquidator = Pubkey::new_unique();
+        let mut group = MarginfiGroup::default();
+        assert!(!group.is_flashloan_fee_exempt(&Pubkey::default()));
+
+        group
+            .configure(&GroupConfig {
+                flashloan_fee_exempt_authorities: Some([
+                    liquidator,
+                    Pubkey::default(),
+                    Pubkey::default(),
+                    Pubkey::default(),
+                ]),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(group.is_flashloan_fee_exempt(&liquidator));
+        assert!(!group.is_flashloan_fee_exempt(&Pubkey::new_unique()));
+        assert!(!group.is_flashloan_fee_exempt(&Pubkey::default()));
+
+        let mut ir_config = InterestRateConfig {
+            optimal_utilization_rate: I80F48!(0.5).into(),
+            plateau_interest_rate: I80F48!(0.1).into(),
+            max_interest_rate: I80F48!(1).into(),
+            flashloan_fee_bps: 9,
+            ..Default::default()
+        };
+        assert!(ir_config.validate().is_ok());
+        assert_eq!(
+            ir_config.get_flashloan_fee(I80F48!(10_000)).unwrap(),
+            I80F48!(9)
+        );
+
+        // Borrows within a flashloan pay at least the origination fee
+        assert_eq!(
+            ir_config
+                .get_flashloan_end_fee(I80F48!(10_000), false)
+                .unwrap(),
+            I80F48!(9)
+        );
+        ir_config.origination_fee_bps = 20;
+        assert_eq!(
+            ir_config
+                .get_flashloan_end_fee(I80F48!(10_000), false)
+                .unwrap(),
+            I80F48!(20)
+        );
+        assert_eq!(
+            ir_config
+                .get_flashloan_end_fee(I80F48!(10_000), true)
+                .unwrap(),
+            I80F48!(20)
+        );
+        ir_config.origination_fee_bps = 0;
+        assert_eq!(
+            ir_config
+                .get_flashloan_end_fee(I80F48!(10_000), true)
+                .unwrap(),
+            I80F48!(0)
+        );
+
+        ir_config.flashloan_fee_bps = 10_001;
+        assert!(ir_config.validate().is_err());
+    }
+
+    #[test]
+    fn fee_distribution_validation() {
+        let treasury = FeeDestination {
+            owner: Pubkey::new_unique(),
+            share_bps: 7_000,
+            destination_type: FEE_DESTINATION_TOKEN_ACCOUNT,
+            _pad0: [0; 5],
+        };
+        let insurance = FeeDestination {
+            share_bps: 3_000,
+            destination_type: FEE_DESTINATION_INSURANCE_VAULT,
+            ..Default::default()
+        };
+
+        let mut group = MarginfiGroup::default();
+        let mut config = GroupConfig {
+            fee_distribution: Some([
+                treasury,
+                insurance,
+                FeeDestination::default(),
+                FeeDestination::default(),
+            ]),
+            ..Default::default()
+        };
+
+        group.configure(&config).unwrap();
+        assert!(group.is_fee_distribution_configured());
+        assert_eq!(group.fee_distribution[0].get_share(1_001).unwrap(), 700);
+        assert_eq!(group.fee_distribution[1].get_share(1_001).unwrap(), 300);
+        assert_eq!(
+            group.get_fee_distribution_amounts(1_001).unwrap(),
+            [700, 300, 0, 0]
+        );
+
+        // Shares must add up to 100%
+        config.fee_distribution.as_mut().unwrap()[1].share_bps = 2_000;
+        assert!(group.configure(&config).is_err());
+
+        // Token account destinations need an owner
+        config.fee_distribution.as_mut().unwrap()[1].share_bps = 3_000;
+        config.fee_distribution.as_mut().unwrap()[0].owner = Pubkey::default();
+        assert!(group.configure(&config).is_err());
+
+        // All zeroed destinations disable the distribution
+        config.fee_distribution = Some([FeeDestination::default(); MAX_FEE_DESTINATIONS]);
+        group.configure(&config).unwrap();
+        assert!(!group.is_fee_distribution_configured());
+    }
+
+    #[test]
+    fn auto_deleverage_amount() {
+        let mut bank = Bank {
+            asset_share_value: I80F48::ONE.into(),
+            liability_share_value: I80F48::ONE.into(),
+            total_asset_shares: I80F48!(1_000).into(),
+            total_liability_shares: I80F48!(800).into(),
+            ..Default::default()
+        };
+
+        assert_eq!(bank.get_utilization_ratio().unwrap(), I80F48!(0.8));
+        assert_eq!(bank.get_auto_deleverage_amount().unwrap(), I80F48::ZERO);
+
+        // Socialized loss halves the value of deposits
+        bank.asset_share_value = I80F48!(0.5).into();
+
+        assert_eq!(bank.get_utilization_ratio().unwrap(), I80F48!(1.6));
+        assert_eq!(
+            bank.get_auto_deleverage_amount().unwrap(),
+            I80F48!(800) - I80F48!(500) * ADL_TARGET_UTILIZATION_RATIO
+        );
+
+        // All deposits are wiped out
+        bank.asset_share_value = I80F48::ZERO.into();
+
+        assert_eq!(bank.get_utilization_ratio().unwrap(), I80F48::MAX);
+        assert_eq!(bank.get_auto_deleverage_amount().unwrap(), I80F48!(800));
+    }
+
+    #[test]
+    fn auto_deleverage_borrower_threshold() {
+        let mut bank = Bank {
+            liability_share_value: I80F48::ONE.into(),
+            total_liability_shares: I80F48!(1_000).into(),
+            ..Default::default()
+        };
+
+        bank.start_auto_deleverage_round(1_000);
+        assert_eq!(bank.adl_round_started_at, 1_000);
+
+        // Only the largest borrowers at the start of the round
+        assert_eq!(
+            bank.get_auto_deleverage_borrower_threshold(1_000).unwrap(),
+            I80F48!(1_000) * ADL_INITIAL_BORROWER_THRESHOLD_RATIO
+        );
+        assert_eq!(
+            bank.get_auto_deleverage_borrower_threshold(1_000 + ADL_THRESHOLD_HALVING_PERIOD - 1)
+                .unwrap(),
+            I80F48!(1_000) * ADL_INITIAL_BORROWER_THRESHOLD_RATIO
+        );
+
+        // Smaller borrowers as the round goes on
+        assert_eq!(
+            bank.get_auto_deleverage_borrower_threshold(1_000 + 2 * ADL_THRESHOLD_HALVING_PERIOD)
+                .unwrap(),
+            I80F48!(1_000) * (ADL_INITIAL_BORROWER_THRESHOLD_RATIO >> 2)
+        );
+        assert_eq!(
+            bank.get_auto_deleverage_borrower_threshold(1_000 + 64 * ADL_THRESHOLD_HALVING_PERIOD)
+                .unwrap(),
+            I80F48::ZERO
+        );
+
+        // A round in progress is not restarted
+        bank.start_auto_deleverage_round(1_000 + ADL_ROUND_DURATION);
+        assert_eq!(bank.adl_round_started_at, 1_000);
+
+        // A stale round is restarted at the initial threshold
+        bank.start_auto_deleverage_round(1_001 + ADL_ROUND_DURATION);
+        assert_eq!(bank.adl_round_started_at, 1_001 + ADL_ROUND_DURATION);
+        assert_eq!(
+            bank.get_auto_deleverage_borrower_threshold(1_001 + ADL_ROUND_DURATION)
+                .unwrap(),
+            I80F48!(1_000) * ADL_INITIAL_BORROWER_THRESHOLD_RATIO
+        );
+    }
+
+    #[test]
+    fn emissions_index_accrual() {
+        let mut bank = Bank {
+            flags: EMISSIONS_FLAG_LENDING_ACTIVE,
+            emissions_rate: 10,
+            emissions_remaining: I80F48!(1_000).into(),
+            total_asset_shares: I80F48!(100).into(),
+            total_liability_shares: I80F48!(50).into(),
+            ..Default::default()
+        };
+
+        // Nothing is distributed before the emissions indices are activated
+        bank.update_emissions_index(1_000).unwrap();
+        assert_eq!(bank.emissions_last_update, 0);
+        assert_eq!(I80F48::from(bank.emissions_remaining), I80F48!(1_000));
+
+        bank.activate_emissions_index(1_000);
+        assert_eq!(bank.emissions_last_update, 1_000);
+        assert_eq!(bank.emissions_legacy_rate, 10);
+        assert_eq!(I80F48::from(bank.emissions_asset_index), I80F48::ZERO);
+
+        bank.update_emissions_index(1_010).unwrap();
+        assert_eq!(I80F48::from(bank.emissions_asset_index), I80F48!(1));
+        assert_eq!(I80F48::from(bank.emissions_liability_index), I80F48::ZERO);
+        assert_eq!(I80F48::from(bank.emissions_remaining), I80F48!(900));
+
+        // Both sides share the rate evenly
+        bank.flags = EMISSIONS_FLAG_LENDING_ACTIVE | EMISSIONS_FLAG_BORROW_ACTIVE;
+        bank.update_emissions_index(1_020).unwrap();
+        assert_eq!(I80F48::from(bank.emissions_asset_index), I80F48!(1.5));
+        assert_eq!(I80F48::from(bank.emissions_liability_index), I80F48!(1));
+        assert_eq!(I80F48::from(bank.emissions_remaining), I80F48!(800));
+
+        // Distribution is capped by the remaining emissions
+        bank.emissions_rate = 1_000;
+        bank.update_emissions_index(1_021).unwrap();
+        assert_eq!(I80F48::from(bank.emissions_asset_index), I80F48!(6.5));
+        assert_eq!(I80F48::from(bank.emissions_liability_index), I80F48!(7));
+        assert_eq!(I80F48::from(bank.emissions_remaining), I80F48::ZERO);
+    }
+
+    #[test]
+    fn emissions_schedule() {
+        let mut bank = Bank {
+            flags: EMISSIONS_FLAG_LENDING_ACTIVE,
+            emissions_rate: 10,
+            emissions_remaining: I80F48!(1_000).into(),
+            total_asset_shares: I80F48!(100).into(),
+            emissions_last_update: 1_000,
+            emissions_index_activated_at: 1_000,
+            emissions_start_time: 1_010,
+            emissions_end_time: 1_030,
+            ..Default::default()
+        };
+
+        assert!(Bank::validate_emissions_schedule(1_010, 1_030).is_ok());
+        assert!(Bank::validate_emissions_schedule(1_010, 0).is_ok());
+        assert!(Bank::validate_emissions_schedule(1_030, 1_010).is_err());
+
+        // Nothing is distributed before the start
+        bank.update_emissions_index(1_010).unwrap();
+        assert_eq!(I80F48::from(bank.emissions_asset_index), I80F48::ZERO);
+
+        // Distribution stops at the end
+        bank.update_emissions_index(1_100).unwrap();
+        assert_eq!(I80F48::from(bank.emissions_asset_index), I80F48!(2));
+        assert_eq!(I80F48::from(bank.emissions_remaining), I80F48!(800));
+        assert!(bank.is_emissions_ended(1_100));
+
+        bank.update_emissions_index(1_200).unwrap();
+        assert_eq!(I80F48::from(bank.emissions_remaining), I80F48!(800));
+    }
+
+    #[test]
+    fn protocol_insurance_deposit() {
+        let mut bank = Bank {
+            insurance_share_mint: Pubkey::new_unique(),
+            insurance_protocol_shares: 1_000_000_000,
+            insurance_staker_shares: 500_000_000,
+            ..Default::default()
+        };
+
+        // Group fees sent to the insurance vault are owned by the protocol
+        bank.deposit_protocol_insurance(300_000_000, 1_500_000_000)
+            .unwrap();
+        assert_eq!(bank.insurance_protocol_shares, 1_300_000_000);
+
+        // Stakers don't earn them
+        assert_eq!(
+            bank.get_insurance_amount_for_shares(500_000_000, 1_800_000_000)
+                .unwrap(),
+            500_000_000
+        );
+
+        // No shares before insurance staking is initialized
+        bank.insurance_share_mint = Pubkey::default();
+        bank.deposit_protocol_insurance(300_000_000, 1_800_000_000)
+            .unwrap();
+        assert_eq!(bank.insurance_protocol_shares, 1_300_000_000);
+    }
+
+    #[test]
+    fn insurance_share_accounting() {
+        let mut bank = Bank {
+            insurance_share_mint: Pubkey::new_unique(),
+            insurance_protocol_shares: 1_000_000_000,
+            ..Default::default()
+        };
+
+        // Vault holds 1_000 protocol owned tokens, staking 500 mints 500 shares
+        let shares = bank
+            .get_insurance_shares_for_amount(500_000_000, 1_000_000_000)
+            .unwrap();
+        assert_eq!(shares, 500_000_000);
+        bank.insurance_staker_shares = shares;
+
+        // Fees double the vault value, new stakers get about half as many shares
+        assert_eq!(
+            bank.get_insurance_shares_for_amount(500_000_000, 3_000_000_000)
+                .unwrap(),
+            250_083_305
+        );
+
+        // Bad debt halves the vault value, shares are worth about half
+        assert_eq!(
+            bank.get_insurance_amount_for_shares(500_000_000, 750_000_000)
+                .unwrap(),
+            250_166_555
+        );
+
+        // Shares pending unstake still count towards the total
+        bank.insurance_staker_shares = 0;
+        bank.insurance_pending_unstake_shares = 500_000_000;
+        assert_eq!(
+            bank.get_insurance_amount_for_shares(500_000_000, 1_500_000_000)
+                .unwrap(),
+            500_000_000
+        );
+
+        // Admin can only withdraw the protocol owned part of the vault
+        assert!(bank
+            .withdraw_protocol_insurance(1_001_000_000, 1_500_000_000)
+            .is_err());
+        bank.withdraw_protocol_insurance(500_000_000, 1_500_000_000)
+            .unwrap();
+        assert_eq!(bank.insurance_protocol_shares, 500_000_000);
+
+        // Shares of a depleted vault are worthless, new stakers aren't diluted by them
+        assert_eq!(bank.get_insurance_amount_for_shares(500, 0).unwrap(), 0);
+        assert_eq!(
+            bank.get_insurance_shares_for_amount(1_000_000_000, 0)
+                .unwrap(),
+            1_001_000_000_000
+        );
+    }
+
+    #[test]
+    fn insurance_share_inflation() {
+        let mut bank = Bank {
+            insurance_share_mint: Pubkey::new_unique(),
+            ..Default::default()
+        };
+
+        // First staker stakes 1 token and donates 1_000 to the vault
+        let attacker_shares = bank.get_insurance_shares_for_amount(1, 0).unwrap();
+        assert_eq!(attacker_shares, 1);
+        bank.insurance_staker_shares = attacker_shares;
+        let vault_amount = 1 + 1_000_000_000;
+
+        // The next staker still gets shares worth about their stake
+        let victim_shares = bank
+            .get_insurance_shares_for_amount(1_000_000_000, vault_amount)
+            .unwrap();
+        assert_eq!(victim_shares, 999_001);
+        bank.insurance_staker_shares += victim_shares;
+        let vault_amount = vault_amount + 1_000_000_000;
+
+        assert_eq!(
+            bank.get_insurance_amount_for_shares(victim_shares, vault_amount)
+                .unwrap(),
+            999_999_500
+        );
+        // The donation is lost to the virtual shares
+        assert_eq!(
+            bank.get_insurance_amount_for_shares(attacker_shares, vault_amount)
+                .unwrap(),
+            1_000
+        );
+    }
 }
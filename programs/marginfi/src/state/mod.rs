@@ -1,3 +1,6 @@
+pub mod conditional_order;
+pub mod emissions_campaign;
+pub mod insurance_staking;
 pub mod marginfi_account;
 pub mod marginfi_group;
 pub mod price;
@@ -15,8 +15,9 @@ pub use pyth_sdk_solana;
 use crate::{
     check,
     constants::{
-        CONF_INTERVAL_MULTIPLE, EXP_10, EXP_10_I80F48, MAX_CONF_INTERVAL,
-        MIN_PYTH_PUSH_VERIFICATION_LEVEL, PYTH_ID, STD_DEV_MULTIPLE, SWITCHBOARD_PULL_ID,
+        CONF_INTERVAL_MULTIPLE, EXP_10, EXP_10_I80F48, MAX_CONF_INTERVAL, MAX_STAKE_POOL_EPOCH_AGE,
+        MIN_PYTH_PUSH_VERIFICATION_LEVEL, ORACLE_PRICE_BAND_DECIMALS, PYTH_ID, SPL_STAKE_POOL_ID,
+        STD_DEV_MULTIPLE, SWITCHBOARD_PULL_ID,
     },
     debug, math_error,
     prelude::*,
@@ -35,6 +36,29 @@ pub enum OracleSetup {
     SwitchboardV2,
     PythPushOracle,
     SwitchboardPull,
+    Fixed,
+    /// SOL Pyth push feed (`oracle_keys[0]`) scaled by the exchange rate of an SPL stake pool
+    /// (`oracle_keys[1]`), used for stake pool LSTs.
+    ///
+    /// Only SPL stake pools are supported, Marinade (mSOL) uses its own state account and is out
+    /// of scope of this setup.
+    StakePoolPythPush,
+}
+
+impl OracleSetup {
+    /// Number of oracle accounts expected to follow the bank account in remaining accounts.
+    pub fn get_oracle_ais_len(&self) -> usize {
+        match self {
+            OracleSetup::Fixed => 0,
+            OracleSetup::StakePoolPythPush => 2,
+            _ => 1,
+        }
+    }
+
+    /// Pyth legacy and Switchboard V2 target oracle programs that are being shut down.
+    pub fn is_legacy(&self) -> bool {
+        matches!(self, OracleSetup::PythLegacy | OracleSetup::SwitchboardV2)
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
@@ -63,11 +87,37 @@ pub trait PriceAdapter {
 
 #[enum_dispatch(PriceAdapter)]
 #[cfg_attr(feature = "client", derive(Clone))]
-pub enum OraclePriceFeedAdapter {
+pub enum OraclePriceFeed {
     PythLegacy(PythLegacyPriceFeed),
     SwitchboardV2(SwitchboardV2PriceFeed),
     PythPushOracle(PythPushOraclePriceFeed),
     SwitchboardPull(SwitchboardPullPriceFeed),
+    Fixed(FixedPriceFeed),
+    StakePoolPythPush(StakePoolPythPushPriceFeed),
+}
+
+/// Oracle price feed of a bank, prices of every type outside of the bank price band are rejected.
+#[cfg_attr(feature = "client", derive(Clone))]
+pub struct OraclePriceFeedAdapter {
+    price_feed: OraclePriceFeed,
+    /// See `BankConfig::oracle_min_price`
+    oracle_min_price: u64,
+    /// See `BankConfig::oracle_max_price`
+    oracle_max_price: u64,
+}
+
+impl PriceAdapter for OraclePriceFeedAdapter {
+    fn get_price_of_type(
+        &self,
+        oracle_price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        let price = self.price_feed.get_price_of_type(oracle_price_type, bias)?;
+
+        self.check_price_band(price)?;
+
+        Ok(price)
+    }
 }
 
 impl OraclePriceFeedAdapter {
@@ -90,8 +140,13 @@ impl OraclePriceFeedAdapter {
         clock: &Clock,
         max_age: u64,
     ) -> MarginfiResult<Self> {
-        match bank_config.oracle_setup {
-            OracleSetup::None => Err(MarginfiError::OracleNotSetup.into()),
+        check!(
+            !(cfg!(feature = "no-legacy-oracles") && bank_config.oracle_setup.is_legacy()),
+            MarginfiError::DeprecatedOracleSetup
+        );
+
+        let price_feed = match bank_config.oracle_setup {
+            OracleSetup::None => return Err(MarginfiError::OracleNotSetup.into()),
             OracleSetup::PythLegacy => {
                 check!(ais.len() == 1, MarginfiError::InvalidOracleAccount);
                 check!(
@@ -101,9 +156,11 @@ impl OraclePriceFeedAdapter {
 
                 let account_info = &ais[0];
 
-                Ok(OraclePriceFeedAdapter::PythLegacy(
-                    PythLegacyPriceFeed::load_checked(account_info, clock.unix_timestamp, max_age)?,
-                ))
+                OraclePriceFeed::PythLegacy(PythLegacyPriceFeed::load_checked(
+                    account_info,
+                    clock.unix_timestamp,
+                    max_age,
+                )?)
             }
             OracleSetup::SwitchboardV2 => {
                 check!(ais.len() == 1, MarginfiError::InvalidOracleAccount);
@@ -112,9 +169,11 @@ impl OraclePriceFeedAdapter {
                     MarginfiError::InvalidOracleAccount
                 );
 
-                Ok(OraclePriceFeedAdapter::SwitchboardV2(
-                    SwitchboardV2PriceFeed::load_checked(&ais[0], clock.unix_timestamp, max_age)?,
-                ))
+                OraclePriceFeed::SwitchboardV2(SwitchboardV2PriceFeed::load_checked(
+                    &ais[0],
+                    clock.unix_timestamp,
+                    max_age,
+                )?)
             }
             OracleSetup::PythPushOracle => {
                 check!(ais.len() == 1, MarginfiError::InvalidOracleAccount);
@@ -128,14 +187,13 @@ impl OraclePriceFeedAdapter {
 
                 let price_feed_id = bank_config.get_pyth_push_oracle_feed_id().unwrap();
 
-                Ok(OraclePriceFeedAdapter::PythPushOracle(
-                    PythPushOraclePriceFeed::load_checked(
-                        account_info,
-                        price_feed_id,
-                        clock,
-                        max_age,
-                    )?,
-                ))
+                OraclePriceFeed::PythPushOracle(PythPushOraclePriceFeed::load_checked(
+                    account_info,
+                    price_feed_id,
+                    clock,
+                    max_age,
+                    bank_config.get_oracle_max_slot_age(),
+                )?)
             }
             OracleSetup::SwitchboardPull => {
                 check!(ais.len() == 1, MarginfiError::InvalidOracleAccount);
@@ -144,17 +202,82 @@ impl OraclePriceFeedAdapter {
                     MarginfiError::InvalidOracleAccount
                 );
 
-                Ok(OraclePriceFeedAdapter::SwitchboardPull(
-                    SwitchboardPullPriceFeed::load_checked(&ais[0], clock.unix_timestamp, max_age)?,
-                ))
+                OraclePriceFeed::SwitchboardPull(SwitchboardPullPriceFeed::load_checked(
+                    &ais[0],
+                    clock,
+                    max_age,
+                    bank_config.get_oracle_max_slot_age(),
+                )?)
             }
+            OracleSetup::Fixed => {
+                check!(ais.is_empty(), MarginfiError::InvalidOracleAccount);
+
+                OraclePriceFeed::Fixed(FixedPriceFeed::load_checked(bank_config)?)
+            }
+            OracleSetup::StakePoolPythPush => {
+                check!(ais.len() == 2, MarginfiError::InvalidOracleAccount);
+                check!(
+                    ais[1].key == &bank_config.oracle_keys[1],
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                let price_feed_id = bank_config.get_pyth_push_oracle_feed_id().unwrap();
+
+                OraclePriceFeed::StakePoolPythPush(StakePoolPythPushPriceFeed::load_checked(
+                    &ais[0],
+                    price_feed_id,
+                    &ais[1],
+                    clock,
+                    max_age,
+                    bank_config.get_oracle_max_slot_age(),
+                )?)
+            }
+        };
+
+        Ok(Self {
+            price_feed,
+            oracle_min_price: bank_config.oracle_min_price,
+            oracle_max_price: bank_config.oracle_max_price,
+        })
+    }
+
+    /// Check that an oracle price is within the bank price band, if configured.
+    fn check_price_band(&self, price: I80F48) -> MarginfiResult {
+        if self.oracle_min_price != 0 {
+            let min_price = price_band_to_i80f48(self.oracle_min_price)?;
+            check!(
+                price >= min_price,
+                MarginfiError::OraclePriceOutOfBand,
+                "Oracle price {} below min price {}",
+                price,
+                min_price
+            );
         }
+
+        if self.oracle_max_price != 0 {
+            let max_price = price_band_to_i80f48(self.oracle_max_price)?;
+            check!(
+                price <= max_price,
+                MarginfiError::OraclePriceOutOfBand,
+                "Oracle price {} above max price {}",
+                price,
+                max_price
+            );
+        }
+
+        Ok(())
     }
 
     pub fn validate_bank_config(
         bank_config: &BankConfig,
         oracle_ais: &[AccountInfo],
+        bank_mint: &Pubkey,
     ) -> MarginfiResult {
+        check!(
+            !(cfg!(feature = "no-legacy-oracles") && bank_config.oracle_setup.is_legacy()),
+            MarginfiError::DeprecatedOracleSetup
+        );
+
         match bank_config.oracle_setup {
             OracleSetup::None => Err(MarginfiError::OracleNotSetup.into()),
             OracleSetup::PythLegacy => {
@@ -198,12 +321,196 @@ impl OraclePriceFeedAdapter {
 
                 SwitchboardPullPriceFeed::check_ais(&oracle_ais[0])?;
 
+                Ok(())
+            }
+            OracleSetup::Fixed => {
+                check!(oracle_ais.is_empty(), MarginfiError::InvalidOracleAccount);
+
+                FixedPriceFeed::load_checked(bank_config)?;
+
+                Ok(())
+            }
+            OracleSetup::StakePoolPythPush => {
+                check!(oracle_ais.len() == 2, MarginfiError::InvalidOracleAccount);
+                check!(
+                    oracle_ais[1].key == &bank_config.oracle_keys[1],
+                    MarginfiError::InvalidOracleAccount
+                );
+
+                PythPushOraclePriceFeed::check_ai_and_feed_id(
+                    &oracle_ais[0],
+                    bank_config.get_pyth_push_oracle_feed_id().unwrap(),
+                )?;
+
+                let stake_pool = LiteStakePool::load_checked(&oracle_ais[1])?;
+                check!(
+                    stake_pool.pool_mint == *bank_mint,
+                    MarginfiError::InvalidOracleAccount,
+                    "Stake pool mint does not match the bank mint"
+                );
+
                 Ok(())
             }
         }
     }
 }
 
+#[inline]
+fn price_band_to_i80f48(value: u64) -> MarginfiResult<I80F48> {
+    Ok(I80F48::from_num(value)
+        .checked_div(EXP_10_I80F48[ORACLE_PRICE_BAND_DECIMALS as usize])
+        .ok_or_else(math_error!())?)
+}
+
+/// Price feed backed by a price set by the group admin in the bank config.
+///
+/// Intended for pegged assets (e.g. stablecoins) and test markets, no oracle account is required
+/// and the price has no confidence interval or staleness.
+#[cfg_attr(feature = "client", derive(Clone, Debug))]
+pub struct FixedPriceFeed {
+    price: I80F48,
+}
+
+impl FixedPriceFeed {
+    pub fn load_checked(bank_config: &BankConfig) -> MarginfiResult<Self> {
+        let price: I80F48 = bank_config.fixed_price.into();
+
+        check!(price > I80F48::ZERO, MarginfiError::InvalidPrice);
+
+        Ok(Self { price })
+    }
+}
+
+impl PriceAdapter for FixedPriceFeed {
+    fn get_price_of_type(
+        &self,
+        _price_type: OraclePriceType,
+        _bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        Ok(self.price)
+    }
+}
+
+/// Price feed for SPL stake pool LSTs.
+///
+/// The LST price is derived from the SOL price and the stake pool exchange rate
+/// (`total_lamports / pool_token_supply`), instead of a thin LST market feed.
+/// Both SOL and the pool token have 9 decimals, so the exchange rate applies to UI amounts as is.
+#[cfg_attr(feature = "client", derive(Clone, Debug))]
+pub struct StakePoolPythPushPriceFeed {
+    sol_feed: PythPushOraclePriceFeed,
+    exchange_rate: I80F48,
+}
+
+impl StakePoolPythPushPriceFeed {
+    pub fn load_checked(
+        sol_feed_ai: &AccountInfo,
+        sol_feed_id: &FeedId,
+        stake_pool_ai: &AccountInfo,
+        clock: &Clock,
+        max_age: u64,
+        max_slot_age: Option<u64>,
+    ) -> MarginfiResult<Self> {
+        let sol_feed = PythPushOraclePriceFeed::load_checked(
+            sol_feed_ai,
+            sol_feed_id,
+            clock,
+            max_age,
+            max_slot_age,
+        )?;
+
+        let stake_pool = LiteStakePool::load_checked(stake_pool_ai)?;
+
+        check!(
+            stake_pool
+                .last_update_epoch
+                .saturating_add(MAX_STAKE_POOL_EPOCH_AGE)
+                >= clock.epoch,
+            MarginfiError::StaleOracle
+        );
+
+        Ok(Self {
+            sol_feed,
+            exchange_rate: stake_pool.get_exchange_rate()?,
+        })
+    }
+}
+
+impl PriceAdapter for StakePoolPythPushPriceFeed {
+    fn get_price_of_type(
+        &self,
+        price_type: OraclePriceType,
+        bias: Option<PriceBias>,
+    ) -> MarginfiResult<I80F48> {
+        let sol_price = self.sol_feed.get_price_of_type(price_type, bias)?;
+
+        Ok(sol_price
+            .checked_mul(self.exchange_rate)
+            .ok_or_else(math_error!())?)
+    }
+}
+
+/// The subset of the SPL stake pool account (spl-stake-pool/src/state.rs) needed to compute the
+/// pool token exchange rate.
+#[cfg_attr(feature = "client", derive(Clone, Debug))]
+pub struct LiteStakePool {
+    pub pool_mint: Pubkey,
+    pub total_lamports: u64,
+    pub pool_token_supply: u64,
+    pub last_update_epoch: u64,
+}
+
+impl LiteStakePool {
+    /// `AccountType::StakePool`
+    const ACCOUNT_TYPE: u8 = 1;
+    /// account_type (1) + manager, staker, stake_deposit_authority (3 * 32)
+    /// + stake_withdraw_bump_seed (1) + validator_list, reserve_stake (2 * 32)
+    const POOL_MINT_OFFSET: usize = 162;
+    /// `POOL_MINT_OFFSET` + pool_mint, manager_fee_account, token_program_id (3 * 32)
+    const TOTAL_LAMPORTS_OFFSET: usize = 258;
+
+    pub fn load_checked(ai: &AccountInfo) -> MarginfiResult<Self> {
+        check!(
+            ai.owner.eq(&SPL_STAKE_POOL_ID),
+            MarginfiError::InvalidOracleAccount
+        );
+
+        let data = ai.try_borrow_data()?;
+
+        check!(
+            data.len() >= Self::TOTAL_LAMPORTS_OFFSET + 24,
+            MarginfiError::InvalidOracleAccount
+        );
+        check!(
+            data[0] == Self::ACCOUNT_TYPE,
+            MarginfiError::InvalidOracleAccount
+        );
+
+        let read_u64 =
+            |offset: usize| u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+
+        Ok(Self {
+            pool_mint: Pubkey::try_from(&data[Self::POOL_MINT_OFFSET..Self::POOL_MINT_OFFSET + 32])
+                .unwrap(),
+            total_lamports: read_u64(Self::TOTAL_LAMPORTS_OFFSET),
+            pool_token_supply: read_u64(Self::TOTAL_LAMPORTS_OFFSET + 8),
+            last_update_epoch: read_u64(Self::TOTAL_LAMPORTS_OFFSET + 16),
+        })
+    }
+
+    /// SOL per pool token
+    pub fn get_exchange_rate(&self) -> MarginfiResult<I80F48> {
+        check!(
+            self.pool_token_supply > 0,
+            MarginfiError::InvalidOracleAccount
+        );
+
+        Ok(I80F48::from_num(self.total_lamports)
+            .checked_div(I80F48::from_num(self.pool_token_supply))
+            .ok_or_else(math_error!())?)
+    }
+}
+
 #[cfg_attr(feature = "client", derive(Clone, Debug))]
 pub struct PythLegacyPriceFeed {
     ema_price: Box<Price>,
@@ -314,8 +621,9 @@ pub struct SwitchboardPullPriceFeed {
 impl SwitchboardPullPriceFeed {
     pub fn load_checked(
         ai: &AccountInfo,
-        current_timestamp: i64,
+        clock: &Clock,
         max_age: u64,
+        max_slot_age: Option<u64>,
     ) -> MarginfiResult<Self> {
         let ai_data = ai.data.borrow();
 
@@ -329,10 +637,12 @@ impl SwitchboardPullPriceFeed {
 
         // Check staleness
         let last_updated = feed.last_update_timestamp;
-        if current_timestamp.saturating_sub(last_updated) > max_age as i64 {
+        if clock.unix_timestamp.saturating_sub(last_updated) > max_age as i64 {
             return err!(MarginfiError::StaleOracle);
         }
 
+        check_oracle_slot_age(clock, feed.result.slot, max_slot_age)?;
+
         Ok(Self {
             feed: Box::new(feed.into()),
         })
@@ -528,6 +838,21 @@ impl PriceAdapter for SwitchboardV2PriceFeed {
     }
 }
 
+/// Reject oracle updates posted more than `max_slot_age` slots ago, no check if `None`.
+fn check_oracle_slot_age(
+    clock: &Clock,
+    posted_slot: u64,
+    max_slot_age: Option<u64>,
+) -> MarginfiResult {
+    if let Some(max_slot_age) = max_slot_age {
+        if clock.slot.saturating_sub(posted_slot) > max_slot_age {
+            return err!(MarginfiError::StaleOracle);
+        }
+    }
+
+    Ok(())
+}
+
 pub fn load_price_update_v2_checked(ai: &AccountInfo) -> MarginfiResult<PriceUpdateV2> {
     check!(
         ai.owner.eq(&pyth_solana_receiver_sdk::id()),
@@ -570,14 +895,18 @@ impl PythPushOraclePriceFeed {
     ///     - pyth-push-oracle asserts the a valid price update has a matching feed_id with the existing pyth-push-oracle update https://github.com/pyth-network/pyth-crosschain/blob/94f1bd54612adc3e186eaf0bb0f1f705880f20a6/target_chains/solana/programs/pyth-push-oracle/src/lib.rs#L101
     ///     - pyth-solana-receiver set the feed_id directly from a pythnet verified price_update message https://github.com/pyth-network/pyth-crosschain/blob/94f1bd54612adc3e186eaf0bb0f1f705880f20a6/target_chains/solana/programs/pyth-solana-receiver/src/lib.rs#L437
     /// - The pyth-push-oracle account is not older than the max_age, checked in `get_price_no_older_than_with_custom_verification_level`
+    /// - The pyth-push-oracle account was posted no more than max_slot_age slots ago, if set
     pub fn load_checked(
         ai: &AccountInfo,
         feed_id: &FeedId,
         clock: &Clock,
         max_age: u64,
+        max_slot_age: Option<u64>,
     ) -> MarginfiResult<Self> {
         let price_feed_account = load_price_update_v2_checked(ai)?;
 
+        check_oracle_slot_age(clock, price_feed_account.posted_slot, max_slot_age)?;
+
         let price = price_feed_account
             .get_price_no_older_than_with_custom_verification_level(
                 clock,
@@ -1018,6 +1347,71 @@ mod tests {
         assert_eq!(low_conf_interval, I80F48!(1.96));
     }
 
+    #[test]
+    fn fixed_price_feed() {
+        let mut bank_config = BankConfig {
+            oracle_setup: OracleSetup::Fixed,
+            fixed_price: I80F48!(1.01).into(),
+            ..Default::default()
+        };
+
+        let adapter =
+            OraclePriceFeedAdapter::try_from_bank_config(&bank_config, &[], &Clock::default())
+                .unwrap();
+
+        for price_type in [OraclePriceType::TimeWeighted, OraclePriceType::RealTime] {
+            for bias in [None, Some(PriceBias::Low), Some(PriceBias::High)] {
+                assert_eq!(
+                    adapter.get_price_of_type(price_type, bias).unwrap(),
+                    I80F48!(1.01)
+                );
+            }
+        }
+
+        // A fixed price must be set
+        bank_config.fixed_price = I80F48::ZERO.into();
+        assert!(
+            OraclePriceFeedAdapter::try_from_bank_config(&bank_config, &[], &Clock::default())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn oracle_price_band() {
+        let mut bank_config = BankConfig {
+            oracle_setup: OracleSetup::Fixed,
+            fixed_price: I80F48!(1.25).into(),
+            oracle_min_price: 1_000_000_000,
+            oracle_max_price: 1_250_000_000,
+            ..Default::default()
+        };
+
+        let price_of_type = |bank_config: &BankConfig, price_type: OraclePriceType| {
+            OraclePriceFeedAdapter::try_from_bank_config(bank_config, &[], &Clock::default())
+                .unwrap()
+                .get_price_of_type(price_type, None)
+        };
+
+        // Bounds are inclusive
+        for price_type in [OraclePriceType::RealTime, OraclePriceType::TimeWeighted] {
+            assert!(price_of_type(&bank_config, price_type).is_ok());
+        }
+        bank_config.fixed_price = I80F48!(1).into();
+        assert!(price_of_type(&bank_config, OraclePriceType::RealTime).is_ok());
+
+        // Prices of every type outside of the band are rejected
+        for fixed_price in [I80F48!(1.5), I80F48!(0.75)] {
+            bank_config.fixed_price = fixed_price.into();
+            for price_type in [OraclePriceType::RealTime, OraclePriceType::TimeWeighted] {
+                assert!(price_of_type(&bank_config, price_type).is_err());
+            }
+        }
+
+        // 0 disables the bound
+        bank_config.oracle_min_price = 0;
+        assert!(price_of_type(&bank_config, OraclePriceType::TimeWeighted).is_ok());
+    }
+
     #[test]
     fn pyth_and_pyth_push_cmp() {
         fn get_prices(
@@ -1228,22 +1622,28 @@ mod tests {
         }
     }
 
+    /// From mainnet: https://solana.fm/address/BSzfJs4d1tAkSDqkepnfzEVcx2WtDVnwwXa2giy9PLeP
+    const SWB_PULL_SOL_USD_HEX: &str = "c41b6cc40ad7db286f5e7566ac000a9530e56b1db49585772719aeaaeeadb4d9bd8c2357b88e9e782e53d81000000000000000000000000000985f538057856308000000000000005cba953f3f15356b17703e554d3983801916531d7976aa424ad64348ec50e4224650d81000000000000000000000000000a0d5a780cc7f580800000000000000a20b742cedab55efd1faf60aef2cb872a092d24dfba8a48c8b953a5e90ac7bbf874ed81000000000000000000000000000c04958360093580800000000000000e7ef024ea756f8beec2eaa40234070da356754a8eeb2ac6a17c32d17c3e99f8ddc50d81000000000000000000000000000bc8739b45d215b0800000000000000e3e5130902c3e9c27917789769f1ae05de15cf504658beafeed2c598a949b3b7bf53d810000000000000000000000000007cec168c94d667080000000000000020e270b743473d87eff321663e267ba1c9a151f7969cef8147f625e9a2af7287ea54d81000000000000000000000000000dc65eccc174d6f0800000000000000ab605484238ac93f225c65f24d7705bb74b00cdb576555c3995e196691a4de5f484ed8100000000000000000000000000088f28dc9271d59080000000000000015196392573dc9043242716f629d4c0fb93bc0cff7a1a10ede24281b0e98fb7d5454d810000000000000000000000000000441a10ca4a268080000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000048ac38271f28ab1b12e49439bddf54871094e4832a56c7a8ec57bd18d357980086807068432f186a147cf0b13a30067d386204ea9d6c8b04743ac2ef010b07524c935636f2523f6aeeb6dc7b7dab0e86a13ff2c794f7895fc78851d69fdb593bdccdb36600000000000000000000000000e40b540200000001000000534f4c2f55534400000000000000000000000000000000000000000000000000000000019e9eb66600000000fca3d11000000000000000000000000000000000000000000000000000000000000000000000000000dc65eccc174d6f0800000000000000006c9225e039550300000000000000000070d3c6ecddf76b080000000000000000d8244bc073aa060000000000000000000441a10ca4a268080000000000000000dc65eccc174d6f08000000000000000200000000000000ea54d810000000005454d81000000000ea54d81000000000fa0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000";
+
     #[test]
     fn swb_pull_get_price() {
         // From mainnet: https://solana.fm/address/BSzfJs4d1tAkSDqkepnfzEVcx2WtDVnwwXa2giy9PLeP
         // Actual price $155.59404527
         // conf/Std_dev ~5%
-        let bytes = hex_to_bytes("c41b6cc40ad7db286f5e7566ac000a9530e56b1db49585772719aeaaeeadb4d9bd8c2357b88e9e782e53d81000000000000000000000000000985f538057856308000000000000005cba953f3f15356b17703e554d3983801916531d7976aa424ad64348ec50e4224650d81000000000000000000000000000a0d5a780cc7f580800000000000000a20b742cedab55efd1faf60aef2cb872a092d24dfba8a48c8b953a5e90ac7bbf874ed81000000000000000000000000000c04958360093580800000000000000e7ef024ea756f8beec2eaa40234070da356754a8eeb2ac6a17c32d17c3e99f8ddc50d81000000000000000000000000000bc8739b45d215b0800000000000000e3e5130902c3e9c27917789769f1ae05de15cf504658beafeed2c598a949b3b7bf53d810000000000000000000000000007cec168c94d667080000000000000020e270b743473d87eff321663e267ba1c9a151f7969cef8147f625e9a2af7287ea54d81000000000000000000000000000dc65eccc174d6f0800000000000000ab605484238ac93f225c65f24d7705bb74b00cdb576555c3995e196691a4de5f484ed8100000000000000000000000000088f28dc9271d59080000000000000015196392573dc9043242716f629d4c0fb93bc0cff7a1a10ede24281b0e98fb7d5454d810000000000000000000000000000441a10ca4a268080000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000048ac38271f28ab1b12e49439bddf54871094e4832a56c7a8ec57bd18d357980086807068432f186a147cf0b13a30067d386204ea9d6c8b04743ac2ef010b07524c935636f2523f6aeeb6dc7b7dab0e86a13ff2c794f7895fc78851d69fdb593bdccdb36600000000000000000000000000e40b540200000001000000534f4c2f55534400000000000000000000000000000000000000000000000000000000019e9eb66600000000fca3d11000000000000000000000000000000000000000000000000000000000000000000000000000dc65eccc174d6f0800000000000000006c9225e039550300000000000000000070d3c6ecddf76b080000000000000000d8244bc073aa060000000000000000000441a10ca4a268080000000000000000dc65eccc174d6f08000000000000000200000000000000ea54d810000000005454d81000000000ea54d81000000000fa0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000");
+        let bytes = hex_to_bytes(SWB_PULL_SOL_USD_HEX);
         let mut acc = create_switch_pull_oracle_account_from_bytes(bytes);
         let key = pubkey!("BSzfJs4d1tAkSDqkepnfzEVcx2WtDVnwwXa2giy9PLeP");
         let ai = account_to_account_info(&mut acc, &key);
         let ai_check = SwitchboardPullPriceFeed::check_ais(&ai);
         assert!(ai_check.is_ok());
 
-        let current_timestamp = 42;
+        let clock = Clock {
+            unix_timestamp: 42,
+            ..Default::default()
+        };
         let max_age = 100;
         let feed: SwitchboardPullPriceFeed =
-            SwitchboardPullPriceFeed::load_checked(&ai, current_timestamp, max_age).unwrap();
+            SwitchboardPullPriceFeed::load_checked(&ai, &clock, max_age, None).unwrap();
         let price: I80F48 = feed.get_price().unwrap();
         let conf: I80F48 = feed.get_confidence_interval().unwrap();
 
@@ -1284,4 +1684,84 @@ mod tests {
         let max_price: I80F48 = target_price_high.checked_add(price_tolerance).unwrap();
         assert!(price_bias_high >= min_price && price_bias_high <= max_price);
     }
+
+    #[test]
+    fn oracle_slot_age() {
+        let clock = Clock {
+            slot: 1_000,
+            ..Default::default()
+        };
+
+        assert!(check_oracle_slot_age(&clock, 990, Some(10)).is_ok());
+        assert!(check_oracle_slot_age(&clock, 989, Some(10)).is_err());
+        // Updates posted in a later slot than the clock are not stale
+        assert!(check_oracle_slot_age(&clock, 1_001, Some(10)).is_ok());
+        assert!(check_oracle_slot_age(&clock, 0, None).is_ok());
+
+        // A max slot age of 0 disables the check
+        let mut config = BankConfig::default();
+        assert_eq!(config.get_oracle_max_slot_age(), None);
+        config.oracle_max_slot_age = 10;
+        assert_eq!(config.get_oracle_max_slot_age(), Some(10));
+    }
+
+    #[test]
+    fn swb_pull_slot_age() {
+        let bytes = hex_to_bytes(SWB_PULL_SOL_USD_HEX);
+        let mut acc = create_switch_pull_oracle_account_from_bytes(bytes);
+        let key = pubkey!("BSzfJs4d1tAkSDqkepnfzEVcx2WtDVnwwXa2giy9PLeP");
+        let ai = account_to_account_info(&mut acc, &key);
+        let result_slot = PullFeedAccountData::parse(ai.data.borrow())
+            .unwrap()
+            .result
+            .slot;
+
+        let mut clock = Clock {
+            unix_timestamp: 42,
+            slot: result_slot + 20,
+            ..Default::default()
+        };
+
+        assert!(SwitchboardPullPriceFeed::load_checked(&ai, &clock, 100, Some(20)).is_ok());
+        assert!(SwitchboardPullPriceFeed::load_checked(&ai, &clock, 100, Some(19)).is_err());
+
+        clock.slot = result_slot + 1_000_000;
+        assert!(SwitchboardPullPriceFeed::load_checked(&ai, &clock, 100, None).is_ok());
+    }
+
+    #[test]
+    fn stake_pool_exchange_rate() {
+        let mut data = vec![0u8; 300];
+        data[0] = LiteStakePool::ACCOUNT_TYPE;
+        let pool_mint = Pubkey::new_unique();
+        let mint_offset = LiteStakePool::POOL_MINT_OFFSET;
+        data[mint_offset..mint_offset + 32].copy_from_slice(pool_mint.as_ref());
+        let offset = LiteStakePool::TOTAL_LAMPORTS_OFFSET;
+        data[offset..offset + 8].copy_from_slice(&1_250_000_000_000u64.to_le_bytes());
+        data[offset + 8..offset + 16].copy_from_slice(&1_000_000_000_000u64.to_le_bytes());
+        data[offset + 16..offset + 24].copy_from_slice(&650u64.to_le_bytes());
+
+        let mut acc = Account {
+            lamports: 1_000_000,
+            data,
+            owner: SPL_STAKE_POOL_ID,
+            executable: false,
+            rent_epoch: 361,
+        };
+        let key = Pubkey::new_unique();
+        let ai = account_to_account_info(&mut acc, &key);
+
+        let stake_pool = LiteStakePool::load_checked(&ai).unwrap();
+        assert_eq!(stake_pool.pool_mint, pool_mint);
+        assert_eq!(stake_pool.last_update_epoch, 650);
+        assert_eq!(stake_pool.get_exchange_rate().unwrap(), I80F48!(1.25));
+
+        // Only accounts owned by the stake pool program are accepted
+        let mut wrong_owner_acc = Account {
+            owner: PYTH_ID,
+            ..acc.clone()
+        };
+        let wrong_owner_ai = account_to_account_info(&mut wrong_owner_acc, &key);
+        assert!(LiteStakePool::load_checked(&wrong_owner_ai).is_err());
+    }
 }
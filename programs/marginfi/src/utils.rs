@@ -1,6 +1,7 @@
 use crate::{
-    bank_authority_seed, bank_seed,
-    state::marginfi_group::{Bank, BankVaultType},
+    bank_authority_seed, bank_seed, check,
+    constants::GROUP_INSURANCE_VAULT_AUTHORITY_SEED,
+    state::marginfi_group::{Bank, BankVaultType, MarginfiGroup},
     MarginfiError, MarginfiResult,
 };
 use anchor_lang::prelude::*;
@@ -13,7 +14,7 @@ use anchor_spl::{
             BaseStateWithExtensions, StateWithExtensions,
         },
     },
-    token_interface::Mint,
+    token_interface::{Mint, TokenAccount},
 };
 use fixed::types::I80F48;
 
@@ -143,6 +144,69 @@ pub fn maybe_take_bank_mint<'info>(
     }
 }
 
+/// Group insurance fund accounts used to cover bad debt of a bank.
+pub struct GroupInsuranceFund<'info> {
+    pub marginfi_group: Pubkey,
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub vault_authority: AccountInfo<'info>,
+    pub vault_authority_bump: u8,
+}
+
+/// Take the optional group insurance fund accounts
+/// `[insurance_fund_vault, insurance_fund_vault_authority]` from the remaining accounts, they are
+/// recognized by the fund vault leading them.
+///
+/// Only banks holding the fund mint can be covered by the fund.
+pub fn maybe_take_group_insurance_fund<'info>(
+    remaining_accounts: &mut &'info [AccountInfo<'info>],
+    marginfi_group_pk: Pubkey,
+    marginfi_group: &MarginfiGroup,
+    bank: &Bank,
+) -> MarginfiResult<Option<GroupInsuranceFund<'info>>> {
+    let is_fund_provided = marginfi_group.is_insurance_fund_initialized()
+        && remaining_accounts
+            .first()
+            .map_or(false, |ai| ai.key.eq(&marginfi_group.insurance_fund_vault));
+
+    if !is_fund_provided {
+        return Ok(None);
+    }
+
+    check!(
+        marginfi_group.is_insurance_fund_mint(bank),
+        MarginfiError::GroupInsuranceFundMintMismatch
+    );
+    check!(
+        remaining_accounts.len() >= 2,
+        MarginfiError::GroupInsuranceFundAccountRequired
+    );
+    let (fund_ais, remaining) = remaining_accounts.split_at(2);
+    *remaining_accounts = remaining;
+
+    let vault = InterfaceAccount::<TokenAccount>::try_from(&fund_ais[0])?;
+
+    let vault_authority = Pubkey::create_program_address(
+        &[
+            GROUP_INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(),
+            &marginfi_group_pk.to_bytes(),
+            &[marginfi_group.insurance_fund_vault_authority_bump],
+        ],
+        &crate::id(),
+    )
+    .map_err(|_| MarginfiError::GroupInsuranceFundAccountRequired)?;
+    check!(
+        fund_ais[1].key() == vault_authority,
+        MarginfiError::GroupInsuranceFundAccountRequired
+    );
+
+    Ok(Some(GroupInsuranceFund {
+        marginfi_group: marginfi_group_pk,
+        vault,
+        vault_authority: fund_ais[1].clone(),
+        vault_authority_bump: marginfi_group.insurance_fund_vault_authority_bump,
+    }))
+}
+
 const ONE_IN_BASIS_POINTS: u128 = 10_000;
 /// backported fix from
 /// https://github.com/solana-labs/solana-program-library/commit/20e6792179fc7f1251579c1c33a4a0feec48e15e
@@ -192,3 +256,78 @@ pub fn hex_to_bytes(hex: &str) -> Vec<u8> {
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaked_account_info(key: Pubkey) -> AccountInfo<'static> {
+        AccountInfo::new(
+            Box::leak(Box::new(key)),
+            false,
+            true,
+            Box::leak(Box::new(0)),
+            Box::leak(Vec::new().into_boxed_slice()),
+            Box::leak(Box::new(Pubkey::default())),
+            false,
+            0,
+        )
+    }
+
+    #[test]
+    fn group_insurance_fund_accounts_are_optional() {
+        let marginfi_group = MarginfiGroup {
+            insurance_fund_mint: Pubkey::new_unique(),
+            insurance_fund_vault: Pubkey::new_unique(),
+            ..Default::default()
+        };
+        let bank = Bank {
+            mint: marginfi_group.insurance_fund_mint,
+            ..Default::default()
+        };
+
+        // Accounts not starting with the fund vault are left untouched
+        let ais: &'static [AccountInfo<'static>] =
+            Box::leak(vec![leaked_account_info(Pubkey::new_unique())].into_boxed_slice());
+        let mut remaining_accounts = ais;
+        assert!(maybe_take_group_insurance_fund(
+            &mut remaining_accounts,
+            Pubkey::new_unique(),
+            &marginfi_group,
+            &bank,
+        )
+        .unwrap()
+        .is_none());
+        assert_eq!(remaining_accounts.len(), 1);
+
+        // A fund vault without its authority is rejected
+        let ais: &'static [AccountInfo<'static>] = Box::leak(
+            vec![leaked_account_info(marginfi_group.insurance_fund_vault)].into_boxed_slice(),
+        );
+        let mut remaining_accounts = ais;
+        assert!(maybe_take_group_insurance_fund(
+            &mut remaining_accounts,
+            Pubkey::new_unique(),
+            &marginfi_group,
+            &bank,
+        )
+        .is_err());
+
+        // Banks with a different mint can't be covered by the fund
+        let ais: &'static [AccountInfo<'static>] = Box::leak(
+            vec![
+                leaked_account_info(marginfi_group.insurance_fund_vault),
+                leaked_account_info(Pubkey::new_unique()),
+            ]
+            .into_boxed_slice(),
+        );
+        let mut remaining_accounts = ais;
+        assert!(maybe_take_group_insurance_fund(
+            &mut remaining_accounts,
+            Pubkey::new_unique(),
+            &marginfi_group,
+            &Bank::default(),
+        )
+        .is_err());
+    }
+}
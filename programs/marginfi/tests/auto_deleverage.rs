@@ -0,0 +1,93 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::prelude::MarginfiError;
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn auto_deleverage_over_utilized_bank() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(10_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank, 10_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_001)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank, 1_001)
+        .await?;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank, 5_000)
+        .await?;
+
+    let keeper_mfi_account_f = test_f.create_marginfi_account().await;
+    let keeper_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(10_000)
+        .await;
+
+    // Only over-utilized banks can be deleveraged
+    let res = keeper_mfi_account_f
+        .try_auto_deleverage(
+            &borrower_mfi_account_f,
+            sol_bank,
+            usdc_bank,
+            1_000,
+            keeper_token_account_usdc.key,
+        )
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BankNotOverUtilized);
+
+    // A socialized loss wipes out 3/4 of the deposits, liabilities are now twice the assets
+    usdc_bank
+        .set_asset_share_value(I80F48::from_num(0.25))
+        .await;
+
+    keeper_mfi_account_f
+        .try_auto_deleverage(
+            &borrower_mfi_account_f,
+            sol_bank,
+            usdc_bank,
+            1_000,
+            keeper_token_account_usdc.key,
+        )
+        .await?;
+
+    // The keeper repays 1_000 USDC for 100 SOL of collateral, without any bonus
+    assert_eq!(
+        keeper_token_account_usdc.balance().await,
+        native!(9_000, "USDC")
+    );
+    let keeper_mfi_account = keeper_mfi_account_f.load().await;
+    let keeper_sol_balance = keeper_mfi_account.lending_account.balances[0];
+    assert_eq!(keeper_sol_balance.bank_pk, sol_bank.key);
+    assert_eq!(
+        I80F48::from(keeper_sol_balance.asset_shares),
+        I80F48::from_num(native!(100, "SOL"))
+    );
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    let borrower_usdc_balance = borrower_mfi_account.lending_account.balances[1];
+    assert_eq!(borrower_usdc_balance.bank_pk, usdc_bank.key);
+    let borrower_liability = usdc_bank
+        .load()
+        .await
+        .get_liability_amount(borrower_usdc_balance.liability_shares.into())
+        .unwrap();
+    assert!(borrower_liability < I80F48::from_num(native!(4_001, "USDC")));
+
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{
+    prelude::MarginfiError,
+    state::{marginfi_account::DISABLED_FLAG, marginfi_group::BankVaultType},
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn recover_bad_debt_of_bankrupt_account() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(100_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank, 100_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_001)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank, 1_001)
+        .await?;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank, 10_000)
+        .await?;
+
+    // Wipe the collateral so the account is bankrupt
+    let mut borrower_mfi_account = borrower_mfi_account_f.load().await;
+    borrower_mfi_account.lending_account.balances[0].asset_shares = I80F48::ZERO.into();
+    borrower_mfi_account_f
+        .set_account(&borrower_mfi_account)
+        .await?;
+
+    test_f
+        .marginfi_group
+        .try_handle_bankruptcy(usdc_bank, &borrower_mfi_account_f)
+        .await?;
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    assert!(borrower_mfi_account.get_flag(DISABLED_FLAG));
+    let bad_debt = borrower_mfi_account
+        .get_recoverable_bad_debt(&usdc_bank.key)
+        .unwrap();
+    assert!(bad_debt >= native!(10_000, "USDC"));
+
+    // Recovery works on the disabled account and goes to the insurance vault
+    let insurance_vault = usdc_bank
+        .get_vault_token_account(BankVaultType::Insurance)
+        .await;
+    let insurance_vault_balance = insurance_vault.balance().await;
+    let recovery_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(20_000)
+        .await;
+
+    borrower_mfi_account_f
+        .try_recover_bad_debt(recovery_token_account.key, usdc_bank, 4_000)
+        .await?;
+
+    assert_eq!(
+        insurance_vault.balance().await,
+        insurance_vault_balance + native!(4_000, "USDC")
+    );
+    assert_eq!(
+        borrower_mfi_account_f
+            .load()
+            .await
+            .get_recoverable_bad_debt(&usdc_bank.key)
+            .unwrap(),
+        bad_debt - native!(4_000, "USDC")
+    );
+    assert_eq!(
+        I80F48::from(usdc_bank.load().await.bad_debt_recovered),
+        I80F48::from_num(native!(4_000, "USDC"))
+    );
+
+    // Repayments are capped at the recorded bad debt
+    borrower_mfi_account_f
+        .try_recover_bad_debt(recovery_token_account.key, usdc_bank, 20_000)
+        .await?;
+
+    assert_eq!(
+        insurance_vault.balance().await,
+        insurance_vault_balance + bad_debt
+    );
+    assert_eq!(
+        recovery_token_account.balance().await,
+        native!(20_000, "USDC") - bad_debt
+    );
+    assert!(!borrower_mfi_account_f.load().await.has_bad_debt_records());
+
+    let res = borrower_mfi_account_f
+        .try_recover_bad_debt(recovery_token_account.key, usdc_bank, 1)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BadDebtRecordNotFound);
+
+    Ok(())
+}
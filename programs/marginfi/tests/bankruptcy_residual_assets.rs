@@ -0,0 +1,91 @@
+use fixed::types::I80F48;
+use fixtures::{marginfi_account::MarginfiAccountFixture, prelude::*};
+use marginfi::state::marginfi_account::BalanceSide;
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+/// Residual SOL collateral left in a bankrupt account borrowing USDC.
+const RESIDUAL_ASSET_SHARES: u64 = 1_000;
+
+async fn create_bankrupt_account(test_f: &TestFixture) -> anyhow::Result<MarginfiAccountFixture> {
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_001)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank, 1_001)
+        .await?;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank, 10_000)
+        .await?;
+
+    // Leave dust worth less than the bankrupt threshold as collateral
+    let mut borrower_mfi_account = borrower_mfi_account_f.load().await;
+    borrower_mfi_account.lending_account.balances[0].asset_shares =
+        I80F48::from_num(RESIDUAL_ASSET_SHARES).into();
+    borrower_mfi_account_f
+        .set_account(&borrower_mfi_account)
+        .await?;
+
+    Ok(borrower_mfi_account_f)
+}
+
+#[tokio::test]
+async fn handle_bankruptcy_with_and_without_residual_asset_sweep() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(100_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank, 100_000)
+        .await?;
+
+    // Without the residual asset banks the collateral is left in the account
+    let borrower_mfi_account_f = create_bankrupt_account(&test_f).await?;
+    test_f
+        .marginfi_group
+        .try_handle_bankruptcy(usdc_bank, &borrower_mfi_account_f)
+        .await?;
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    let sol_balance = borrower_mfi_account.lending_account.balances[0];
+    assert!(sol_balance.active);
+    assert_eq!(sol_balance.bank_pk, sol_bank.key);
+    assert_eq!(
+        I80F48::from(sol_balance.asset_shares),
+        I80F48::from_num(RESIDUAL_ASSET_SHARES)
+    );
+    assert_eq!(
+        I80F48::from(sol_bank.load().await.collected_insurance_fees_outstanding),
+        I80F48::ZERO
+    );
+
+    // With them the collateral is swept to the insurance fees of its bank
+    let borrower_mfi_account_f = create_bankrupt_account(&test_f).await?;
+    test_f
+        .marginfi_group
+        .try_handle_bankruptcy_and_sweep_residual_assets(usdc_bank, &borrower_mfi_account_f)
+        .await?;
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    assert!(borrower_mfi_account
+        .lending_account
+        .balances
+        .iter()
+        .all(|balance| !balance.active || balance.is_empty(BalanceSide::Assets)));
+    assert!(
+        I80F48::from(sol_bank.load().await.collected_insurance_fees_outstanding) > I80F48::ZERO
+    );
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{
+    prelude::MarginfiError,
+    state::conditional_order::{
+        ConditionalOrder, CONDITIONAL_ORDER_TRIGGER_HEALTH_ABOVE,
+        CONDITIONAL_ORDER_TRIGGER_HEALTH_BELOW,
+    },
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn conditional_order_create_execute_and_cancel() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(10_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank, 10_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_001)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank, 1_001)
+        .await?;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank, 5_000)
+        .await?;
+
+    // Take-profit order, triggered as long as the account is healthy
+    borrower_mfi_account_f
+        .try_create_conditional_order(
+            0,
+            sol_bank,
+            usdc_bank,
+            CONDITIONAL_ORDER_TRIGGER_HEALTH_ABOVE,
+            I80F48::ZERO,
+            1_000,
+            0,
+        )
+        .await?;
+    // Stop-loss order, only triggered once the account is unhealthy
+    borrower_mfi_account_f
+        .try_create_conditional_order(
+            1,
+            sol_bank,
+            usdc_bank,
+            CONDITIONAL_ORDER_TRIGGER_HEALTH_BELOW,
+            I80F48::ZERO,
+            1_000,
+            100,
+        )
+        .await?;
+
+    let keeper_mfi_account_f = test_f.create_marginfi_account().await;
+    let keeper_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(10_000)
+        .await;
+
+    let res = keeper_mfi_account_f
+        .try_execute_conditional_order(
+            &borrower_mfi_account_f,
+            1,
+            sol_bank,
+            usdc_bank,
+            1_000,
+            keeper_token_account_usdc.key,
+        )
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::ConditionalOrderNotTriggered
+    );
+
+    // Executions are capped at the amount left in the order
+    keeper_mfi_account_f
+        .try_execute_conditional_order(
+            &borrower_mfi_account_f,
+            0,
+            sol_bank,
+            usdc_bank,
+            2_000,
+            keeper_token_account_usdc.key,
+        )
+        .await?;
+
+    assert_eq!(
+        keeper_token_account_usdc.balance().await,
+        native!(9_000, "USDC")
+    );
+    let keeper_sol_balance = keeper_mfi_account_f.load().await.lending_account.balances[0];
+    assert_eq!(keeper_sol_balance.bank_pk, sol_bank.key);
+    // The test banks weigh assets and liabilities at 1, where any keeper fee would lower the
+    // account health, so the order pays exactly the value of the repaid liability
+    assert_eq!(
+        I80F48::from(keeper_sol_balance.asset_shares),
+        I80F48::from_num(native!(100, "SOL"))
+    );
+
+    let order_address = get_conditional_order_address(borrower_mfi_account_f.key, 0).0;
+    let order: ConditionalOrder = test_f.load_and_deserialize(&order_address).await;
+    assert_eq!(order.liab_amount_remaining, 0);
+
+    // Cancelling closes the order
+    let stop_loss_address = get_conditional_order_address(borrower_mfi_account_f.key, 1).0;
+    borrower_mfi_account_f
+        .try_cancel_conditional_order(1)
+        .await?;
+    assert!(test_f.try_load(&stop_loss_address).await?.is_none());
+
+    Ok(())
+}
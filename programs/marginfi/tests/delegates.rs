@@ -0,0 +1,148 @@
+use anchor_lang::prelude::*;
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{
+    prelude::MarginfiError,
+    state::marginfi_account::{DELEGATE_PERMISSION_REPAY, DELEGATE_PERMISSION_WITHDRAW},
+};
+use pretty_assertions::assert_eq;
+use solana_program::instruction::Instruction;
+use solana_program_test::*;
+use solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
+
+/// Send `ix`, built by a fixture for the payer, signed by `signer` instead.
+async fn process_ix_as(
+    test_f: &TestFixture,
+    mut ix: Instruction,
+    signer: &Keypair,
+) -> std::result::Result<(), BanksClientError> {
+    let payer = test_f.payer();
+    let signer_meta = ix
+        .accounts
+        .iter_mut()
+        .find(|meta| meta.pubkey == payer && meta.is_signer)
+        .unwrap();
+    *signer_meta = AccountMeta::new_readonly(signer.pubkey(), true);
+
+    let mut ctx = test_f.context.borrow_mut();
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&ctx.payer.pubkey()),
+        &[&ctx.payer, signer],
+        ctx.last_blockhash,
+    );
+
+    ctx.banks_client.process_transaction(tx).await
+}
+
+#[tokio::test]
+async fn delegate_withdraw_to_authority() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+
+    let user_mfi_account_f = test_f.create_marginfi_account().await;
+    let user_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    user_mfi_account_f
+        .try_bank_deposit(user_token_account.key, usdc_bank, 1_000)
+        .await?;
+
+    let delegate = Keypair::new();
+    user_mfi_account_f
+        .try_set_delegate(delegate.pubkey(), DELEGATE_PERMISSION_WITHDRAW, 0)
+        .await?;
+
+    let marginfi_account = user_mfi_account_f.load().await;
+    assert_eq!(marginfi_account.delegate, delegate.pubkey());
+    assert_eq!(
+        marginfi_account.delegate_permissions,
+        DELEGATE_PERMISSION_WITHDRAW
+    );
+
+    // Withdraw to a token account of the authority
+    let ix = user_mfi_account_f
+        .make_bank_withdraw_ix(user_token_account.key, usdc_bank, 100, None)
+        .await;
+    process_ix_as(&test_f, ix, &delegate).await?;
+    assert_eq!(user_token_account.balance().await, native!(100, "USDC"));
+
+    // Withdraw to a token account of the delegate
+    let delegate_token_account = TokenAccountFixture::new(
+        test_f.context.clone(),
+        &test_f.usdc_mint,
+        &delegate.pubkey(),
+    )
+    .await;
+    let ix = user_mfi_account_f
+        .make_bank_withdraw_ix(delegate_token_account.key, usdc_bank, 101, None)
+        .await;
+    let res = process_ix_as(&test_f, ix, &delegate).await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::Unauthorized);
+    assert_eq!(delegate_token_account.balance().await, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn delegate_permissions_and_expiry() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+
+    let user_mfi_account_f = test_f.create_marginfi_account().await;
+    let user_token_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    user_mfi_account_f
+        .try_bank_deposit(user_token_account.key, usdc_bank, 1_000)
+        .await?;
+
+    // Missing permission
+    let delegate = Keypair::new();
+    user_mfi_account_f
+        .try_set_delegate(delegate.pubkey(), DELEGATE_PERMISSION_REPAY, 0)
+        .await?;
+    let ix = user_mfi_account_f
+        .make_bank_withdraw_ix(user_token_account.key, usdc_bank, 100, None)
+        .await;
+    let res = process_ix_as(&test_f, ix, &delegate).await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::Unauthorized);
+
+    // Expired delegate
+    let now = test_f.get_clock().await.unix_timestamp;
+    user_mfi_account_f
+        .try_set_delegate(delegate.pubkey(), DELEGATE_PERMISSION_WITHDRAW, now + 10)
+        .await?;
+    let ix = user_mfi_account_f
+        .make_bank_withdraw_ix(user_token_account.key, usdc_bank, 101, None)
+        .await;
+    process_ix_as(&test_f, ix, &delegate).await?;
+
+    test_f.advance_time(20).await;
+    let ix = user_mfi_account_f
+        .make_bank_withdraw_ix(user_token_account.key, usdc_bank, 102, None)
+        .await;
+    let res = process_ix_as(&test_f, ix, &delegate).await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::Unauthorized);
+
+    // Removed delegate
+    user_mfi_account_f
+        .try_set_delegate(Pubkey::default(), 0, 0)
+        .await?;
+    let marginfi_account = user_mfi_account_f.load().await;
+    assert_eq!(marginfi_account.delegate, Pubkey::default());
+    assert_eq!(marginfi_account.delegate_permissions, 0);
+
+    // Expiry in the past
+    let res = user_mfi_account_f
+        .try_set_delegate(delegate.pubkey(), DELEGATE_PERMISSION_WITHDRAW, now + 15)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidDelegateConfig);
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use fixtures::{native, prelude::*};
+use marginfi::constants::EMISSIONS_FLAG_LENDING_ACTIVE;
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn withdraw_emissions_of_several_banks() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank, 1_000)
+        .await?;
+    let lender_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 100)
+        .await?;
+
+    // Both banks emit 0.01 USDC per second to their lenders
+    let funding_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(2_000)
+        .await;
+    for bank in [usdc_bank, sol_bank] {
+        bank.try_setup_emissions(
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            native!(0.01, "USDC", f64),
+            native!(1_000, "USDC"),
+            test_f.usdc_mint.key,
+            funding_account.key,
+            test_f.usdc_mint.token_program,
+        )
+        .await?;
+    }
+
+    test_f.advance_time(1_000).await;
+
+    // The same destination account can take the emissions of both banks
+    let recv_account = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    lender_mfi_account_f
+        .try_withdraw_emissions_batch(&[(usdc_bank, &recv_account), (sol_bank, &recv_account)])
+        .await?;
+
+    let claimed = recv_account.balance().await;
+    assert!(claimed > native!(19.99, "USDC", f64));
+    assert!(claimed <= native!(20, "USDC"));
+
+    let emissions_vaults_balance = balance_of(
+        test_f.context.clone(),
+        get_emissions_token_account_address(usdc_bank.key, test_f.usdc_mint.key).0,
+    )
+    .await
+        + balance_of(
+            test_f.context.clone(),
+            get_emissions_token_account_address(sol_bank.key, test_f.usdc_mint.key).0,
+        )
+        .await;
+    assert_eq!(emissions_vaults_balance, native!(2_000, "USDC") - claimed);
+
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{
+    constants::{EMISSIONS_FLAG_BORROW_ACTIVE, EMISSIONS_FLAG_LENDING_ACTIVE},
+    prelude::MarginfiError,
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn emissions_campaign_create_and_claim() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 100)
+        .await?;
+
+    let funding_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    let start_time = test_f.get_clock().await.unix_timestamp;
+    let end_time = start_time + 30 * 24 * 60 * 60;
+
+    // A campaign emits to either lenders or borrowers
+    let res = sol_bank
+        .try_create_emissions_campaign(
+            EMISSIONS_FLAG_LENDING_ACTIVE | EMISSIONS_FLAG_BORROW_ACTIVE,
+            native!(1, "USDC"),
+            native!(1_000, "USDC"),
+            start_time,
+            end_time,
+            test_f.usdc_mint.key,
+            funding_account.key,
+            test_f.usdc_mint.token_program,
+        )
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidEmissionsCampaign);
+
+    // 1 USDC per SOL lent per year
+    let campaign = sol_bank
+        .try_create_emissions_campaign(
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            native!(1, "USDC"),
+            native!(1_000, "USDC"),
+            start_time,
+            end_time,
+            test_f.usdc_mint.key,
+            funding_account.key,
+            test_f.usdc_mint.token_program,
+        )
+        .await?;
+
+    let campaign_vault = get_emissions_campaign_vault_address(campaign).0;
+    assert_eq!(
+        balance_of(test_f.context.clone(), campaign_vault).await,
+        native!(1_000, "USDC")
+    );
+    assert_eq!(funding_account.balance().await, 0);
+
+    lender_mfi_account_f
+        .try_init_campaign_position(campaign)
+        .await?;
+
+    test_f.advance_time(24 * 60 * 60).await;
+
+    let recv_account = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    lender_mfi_account_f
+        .try_claim_campaign_emissions(sol_bank, campaign, test_f.usdc_mint.key, &recv_account)
+        .await?;
+
+    // 100 SOL lent for a day
+    let claimed = recv_account.balance().await;
+    assert!(claimed > 0);
+    assert!(claimed <= native!(100, "USDC") / 365 + 1);
+    assert_eq!(
+        balance_of(test_f.context.clone(), campaign_vault).await,
+        native!(1_000, "USDC") - claimed
+    );
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::Pubkey;
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{
+    constants::EMISSIONS_FLAG_LENDING_ACTIVE,
+    prelude::MarginfiError,
+    state::{marginfi_account::EMISSIONS_AUTO_COMPOUND_FLAG, marginfi_group::BankVaultType},
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn compound_emissions_of_own_mint() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank, 1_000)
+        .await?;
+
+    // The bank emits 0.01 USDC per second to its lenders
+    let funding_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    usdc_bank
+        .try_setup_emissions(
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            native!(0.01, "USDC", f64),
+            native!(1_000, "USDC"),
+            test_f.usdc_mint.key,
+            funding_account.key,
+            test_f.usdc_mint.token_program,
+        )
+        .await?;
+
+    test_f.advance_time(1_000).await;
+
+    // Compounding is opt-in
+    let res = lender_mfi_account_f.try_compound_emissions(usdc_bank).await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::EmissionsCompoundNotAllowed);
+
+    let claim_delegate = Pubkey::new_unique();
+    lender_mfi_account_f
+        .try_configure_emissions(Some(claim_delegate), Some(true))
+        .await?;
+
+    let lender_mfi_account = lender_mfi_account_f.load().await;
+    assert_eq!(lender_mfi_account.emissions_claim_delegate, claim_delegate);
+    assert!(lender_mfi_account.get_flag(EMISSIONS_AUTO_COMPOUND_FLAG));
+
+    let liquidity_vault = usdc_bank
+        .get_vault_token_account(BankVaultType::Liquidity)
+        .await;
+    let liquidity_vault_balance = liquidity_vault.balance().await;
+
+    // Permissionless, the emissions are deposited into the balance they accrued on
+    lender_mfi_account_f
+        .try_compound_emissions(usdc_bank)
+        .await?;
+
+    let compounded = liquidity_vault.balance().await - liquidity_vault_balance;
+    assert!(compounded > native!(9.99, "USDC", f64));
+    assert!(compounded <= native!(10, "USDC"));
+    assert_eq!(
+        I80F48::from(lender_mfi_account_f.load().await.lending_account.balances[0].asset_shares),
+        I80F48::from_num(native!(1_000, "USDC") + compounded)
+    );
+
+    // Only the auto-compound mode is turned off, the claim delegate stays
+    lender_mfi_account_f
+        .try_configure_emissions(None, Some(false))
+        .await?;
+
+    let lender_mfi_account = lender_mfi_account_f.load().await;
+    assert_eq!(lender_mfi_account.emissions_claim_delegate, claim_delegate);
+    assert!(!lender_mfi_account.get_flag(EMISSIONS_AUTO_COMPOUND_FLAG));
+
+    Ok(())
+}
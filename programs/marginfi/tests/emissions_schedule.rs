@@ -0,0 +1,70 @@
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{constants::EMISSIONS_FLAG_LENDING_ACTIVE, prelude::MarginfiError};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn reclaim_emissions_after_schedule_end() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_sol = test_f.sol_mint.create_token_account_and_mint_to(100).await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_sol.key, sol_bank, 100)
+        .await?;
+
+    let funding_account = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    sol_bank
+        .try_setup_emissions(
+            EMISSIONS_FLAG_LENDING_ACTIVE,
+            native!(0.01, "USDC", f64),
+            native!(1_000, "USDC"),
+            test_f.usdc_mint.key,
+            funding_account.key,
+            test_f.usdc_mint.token_program,
+        )
+        .await?;
+
+    let start_time = test_f.get_clock().await.unix_timestamp;
+
+    let res = sol_bank
+        .try_update_emissions_schedule(start_time, start_time)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidEmissionsSchedule);
+
+    sol_bank
+        .try_update_emissions_schedule(start_time, start_time + 3_600)
+        .await?;
+
+    let destination_account = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+
+    let res = sol_bank
+        .try_reclaim_emissions(destination_account.key, test_f.usdc_mint.token_program)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::EmissionsNotEnded);
+
+    test_f.advance_time(7_200).await;
+
+    sol_bank
+        .try_reclaim_emissions(destination_account.key, test_f.usdc_mint.token_program)
+        .await?;
+
+    // Only the hour of the schedule is distributed, the emissions of the lenders stay in the vault
+    let emissions_vault = get_emissions_token_account_address(sol_bank.key, test_f.usdc_mint.key).0;
+    assert_eq!(
+        destination_account.balance().await,
+        native!(1_000, "USDC") - native!(36, "USDC")
+    );
+    assert_eq!(
+        balance_of(test_f.context.clone(), emissions_vault).await,
+        native!(36, "USDC")
+    );
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{
+    prelude::MarginfiError,
+    state::marginfi_group::{
+        BankVaultType, FeeDestination, GroupConfig, FEE_DESTINATION_INSURANCE_VAULT,
+        FEE_DESTINATION_TOKEN_ACCOUNT,
+    },
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn distribute_fee_vault_between_group_destinations() -> anyhow::Result<()> {
+    let mut test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_fee_vault = test_f
+        .get_bank(&BankMint::Usdc)
+        .get_vault(BankVaultType::Fee)
+        .0;
+    test_f.usdc_mint.mint_to(&usdc_fee_vault, 1_000).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let treasury_token_account = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+
+    let res = usdc_bank
+        .try_distribute_fees(&[treasury_token_account.key])
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::FeeDistributionNotConfigured
+    );
+
+    test_f
+        .marginfi_group
+        .try_update(GroupConfig {
+            fee_distribution: Some([
+                FeeDestination {
+                    owner: test_f.payer(),
+                    share_bps: 7_000,
+                    destination_type: FEE_DESTINATION_TOKEN_ACCOUNT,
+                    ..Default::default()
+                },
+                FeeDestination {
+                    share_bps: 3_000,
+                    destination_type: FEE_DESTINATION_INSURANCE_VAULT,
+                    ..Default::default()
+                },
+                FeeDestination::default(),
+                FeeDestination::default(),
+            ]),
+            ..Default::default()
+        })
+        .await?;
+
+    // Destinations must hold the bank mint
+    let sol_token_account = test_f.sol_mint.create_token_account_and_mint_to(0).await;
+    let res = usdc_bank
+        .try_distribute_fees(&[sol_token_account.key])
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::InvalidFeeDestination);
+
+    let insurance_vault = usdc_bank
+        .get_vault_token_account(BankVaultType::Insurance)
+        .await;
+    let insurance_vault_balance = insurance_vault.balance().await;
+
+    usdc_bank
+        .try_distribute_fees(&[treasury_token_account.key])
+        .await?;
+
+    assert_eq!(treasury_token_account.balance().await, native!(700, "USDC"));
+    assert_eq!(
+        insurance_vault.balance().await,
+        insurance_vault_balance + native!(300, "USDC")
+    );
+    assert_eq!(
+        usdc_bank
+            .get_vault_token_account(BankVaultType::Fee)
+            .await
+            .balance()
+            .await,
+        0
+    );
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{
+    prelude::MarginfiError,
+    state::marginfi_group::{Bank, GroupConfig},
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn collect_group_fees_as_deposit() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+
+    // Book outstanding group fees on the bank
+    let mut bank_ai = test_f
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_account(usdc_bank.key)
+        .await?
+        .unwrap();
+    let bank = bytemuck::from_bytes_mut::<Bank>(&mut bank_ai.data.as_mut_slice()[8..]);
+    bank.collected_group_fees_outstanding = I80F48::from_num(native!(50, "USDC")).into();
+    test_f
+        .context
+        .borrow_mut()
+        .set_account(&usdc_bank.key, &bank_ai.into());
+
+    let fee_mfi_account_f = test_f.create_marginfi_account().await;
+
+    let res = usdc_bank
+        .try_collect_fees_as_deposit(fee_mfi_account_f.key)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::FeeMarginfiAccountNotConfigured
+    );
+
+    test_f
+        .marginfi_group
+        .try_update(GroupConfig {
+            fee_marginfi_account: Some(fee_mfi_account_f.key),
+            ..Default::default()
+        })
+        .await?;
+
+    // Only the configured fee account can receive the fees
+    let other_mfi_account_f = test_f.create_marginfi_account().await;
+    let res = usdc_bank
+        .try_collect_fees_as_deposit(other_mfi_account_f.key)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::FeeMarginfiAccountNotConfigured
+    );
+
+    usdc_bank
+        .try_collect_fees_as_deposit(fee_mfi_account_f.key)
+        .await?;
+
+    let fee_mfi_account = fee_mfi_account_f.load().await;
+    let usdc_balance = fee_mfi_account.lending_account.balances[0];
+    assert!(usdc_balance.active);
+    assert_eq!(usdc_balance.bank_pk, usdc_bank.key);
+    assert_eq!(
+        I80F48::from(usdc_balance.asset_shares),
+        I80F48::from_num(native!(50, "USDC"))
+    );
+    assert_eq!(
+        I80F48::from(usdc_bank.load().await.collected_group_fees_outstanding),
+        I80F48::ZERO
+    );
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{prelude::MarginfiError, state::marginfi_group::BankVaultType};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn group_insurance_fund_covers_same_mint_bad_debt() -> anyhow::Result<()> {
+    let mut test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_insurance_vault = test_f
+        .get_bank(&BankMint::Usdc)
+        .get_vault(BankVaultType::Insurance)
+        .0;
+    test_f.usdc_mint.mint_to(&usdc_insurance_vault, 5_000).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+
+    test_f
+        .marginfi_group
+        .try_init_group_insurance_fund(&test_f.usdc_mint)
+        .await?;
+    let group_insurance_fund_vault = test_f.marginfi_group.get_group_insurance_fund_vault().0;
+
+    // Only banks holding the fund mint can top up the fund
+    let res = test_f
+        .marginfi_group
+        .try_fund_group_insurance(sol_bank, native!(1, "SOL"))
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::GroupInsuranceFundMintMismatch
+    );
+
+    test_f
+        .marginfi_group
+        .try_fund_group_insurance(usdc_bank, native!(4_000, "USDC"))
+        .await?;
+    assert_eq!(
+        balance_of(test_f.context.clone(), group_insurance_fund_vault).await,
+        native!(4_000, "USDC")
+    );
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(100_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank, 100_000)
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_001)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank, 1_001)
+        .await?;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank, 10_000)
+        .await?;
+
+    let mut borrower_mfi_account = borrower_mfi_account_f.load().await;
+    borrower_mfi_account.lending_account.balances[0].asset_shares = I80F48::ZERO.into();
+    borrower_mfi_account_f
+        .set_account(&borrower_mfi_account)
+        .await?;
+
+    let asset_share_value_before = I80F48::from(usdc_bank.load().await.asset_share_value);
+
+    test_f
+        .marginfi_group
+        .try_handle_bankruptcy(usdc_bank, &borrower_mfi_account_f)
+        .await?;
+
+    // The bank insurance vault is drained first, then the group insurance fund
+    let insurance_vault = usdc_bank
+        .get_vault_token_account(BankVaultType::Insurance)
+        .await;
+    assert_eq!(insurance_vault.balance().await, 0);
+    assert_eq!(
+        balance_of(test_f.context.clone(), group_insurance_fund_vault).await,
+        0
+    );
+
+    // Only the uncovered rest of the bad debt is socialized, the whole of it is recoverable
+    let usdc_bank_after = usdc_bank.load().await;
+    assert!(I80F48::from(usdc_bank_after.asset_share_value) < asset_share_value_before);
+    assert!(
+        borrower_mfi_account_f
+            .load()
+            .await
+            .get_recoverable_bad_debt(&usdc_bank.key)
+            .unwrap()
+            >= native!(10_000, "USDC")
+    );
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use fixed::types::I80F48;
+use fixtures::{assert_custom_error, prelude::*};
+use marginfi::{
+    prelude::MarginfiError,
+    state::marginfi_account::{BalanceSide, DISABLED_FLAG},
+};
+use solana_program_test::*;
+
+#[tokio::test]
+async fn handle_bankruptcies_settles_every_liability() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let sol_bank = test_f.get_bank(&BankMint::Sol);
+    let sol_equivalent_bank = test_f.get_bank(&BankMint::SolEquivalent);
+
+    let lender_mfi_account_f = test_f.create_marginfi_account().await;
+    let lender_token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(100_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(lender_token_account_usdc.key, usdc_bank, 100_000)
+        .await?;
+    let lender_token_account_sol_equivalent = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    lender_mfi_account_f
+        .try_bank_deposit(
+            lender_token_account_sol_equivalent.key,
+            sol_equivalent_bank,
+            1_000,
+        )
+        .await?;
+
+    let borrower_mfi_account_f = test_f.create_marginfi_account().await;
+    let borrower_token_account_sol = test_f
+        .sol_mint
+        .create_token_account_and_mint_to(1_001)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_deposit(borrower_token_account_sol.key, sol_bank, 1_001)
+        .await?;
+    let borrower_token_account_usdc = test_f.usdc_mint.create_token_account_and_mint_to(0).await;
+    borrower_mfi_account_f
+        .try_bank_borrow(borrower_token_account_usdc.key, usdc_bank, 5_000)
+        .await?;
+    let borrower_token_account_sol_equivalent = test_f
+        .sol_equivalent_mint
+        .create_token_account_and_mint_to(0)
+        .await;
+    borrower_mfi_account_f
+        .try_bank_borrow(
+            borrower_token_account_sol_equivalent.key,
+            sol_equivalent_bank,
+            100,
+        )
+        .await?;
+
+    // Wipe the collateral so the account is bankrupt
+    let mut borrower_mfi_account = borrower_mfi_account_f.load().await;
+    borrower_mfi_account.lending_account.balances[0].asset_shares = I80F48::ZERO.into();
+    borrower_mfi_account_f
+        .set_account(&borrower_mfi_account)
+        .await?;
+
+    // Every liability must be settled in the batch
+    let res = test_f
+        .marginfi_group
+        .try_handle_bankruptcies(&[usdc_bank], &borrower_mfi_account_f)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(res.unwrap_err(), MarginfiError::BadDebtNotFullySettled);
+
+    test_f
+        .marginfi_group
+        .try_handle_bankruptcies(&[usdc_bank, sol_equivalent_bank], &borrower_mfi_account_f)
+        .await?;
+
+    let borrower_mfi_account = borrower_mfi_account_f.load().await;
+    assert!(borrower_mfi_account.get_flag(DISABLED_FLAG));
+    assert!(borrower_mfi_account
+        .lending_account
+        .balances
+        .iter()
+        .filter(|balance| balance.active)
+        .all(|balance| balance.is_empty(BalanceSide::Liabilities)));
+    assert!(borrower_mfi_account
+        .get_recoverable_bad_debt(&usdc_bank.key)
+        .is_ok());
+    assert!(borrower_mfi_account
+        .get_recoverable_bad_debt(&sol_equivalent_bank.key)
+        .is_ok());
+
+    Ok(())
+}
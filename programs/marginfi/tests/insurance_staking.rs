@@ -0,0 +1,95 @@
+use fixtures::{assert_custom_error, native, prelude::*};
+use marginfi::{prelude::MarginfiError, state::marginfi_group::BankVaultType};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn insurance_stake_unstake_and_claim() -> anyhow::Result<()> {
+    let mut test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+
+    let usdc_insurance_vault = test_f
+        .get_bank(&BankMint::Usdc)
+        .get_vault(BankVaultType::Insurance)
+        .0;
+    test_f.usdc_mint.mint_to(&usdc_insurance_vault, 1_000).await;
+
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+    let staker_token_account = test_f.usdc_mint.create_token_account_and_mint_to(100).await;
+
+    // The share mint doesn't exist before staking is initialized
+    let res = usdc_bank
+        .try_insurance_stake(staker_token_account.key, staker_token_account.key, 100)
+        .await;
+    assert!(res.is_err());
+
+    usdc_bank.try_init_insurance_staking(3_600).await?;
+
+    // The vault balance at initialization belongs to the protocol
+    let bank = usdc_bank.load().await;
+    assert_eq!(
+        bank.insurance_share_mint,
+        get_insurance_share_mint_address(usdc_bank.key).0
+    );
+    assert_eq!(bank.insurance_protocol_shares, native!(1_000, "USDC"));
+    assert_eq!(bank.insurance_unstake_cooldown, 3_600);
+
+    // The share mint can only be created once
+    let res = usdc_bank.try_init_insurance_staking(3_600).await;
+    assert!(res.is_err());
+
+    let share_account = TokenAccountFixture::new_with_token_program(
+        test_f.context.clone(),
+        &bank.insurance_share_mint,
+        &test_f.payer(),
+        &test_f.usdc_mint.token_program,
+    )
+    .await;
+
+    usdc_bank
+        .try_insurance_stake(staker_token_account.key, share_account.key, 100)
+        .await?;
+
+    assert_eq!(staker_token_account.balance().await, 0);
+    assert_eq!(share_account.balance().await, native!(100, "USDC"));
+    assert_eq!(
+        usdc_bank.load().await.insurance_staker_shares,
+        native!(100, "USDC")
+    );
+
+    usdc_bank
+        .try_insurance_request_unstake(share_account.key, native!(100, "USDC"), 0)
+        .await?;
+
+    assert_eq!(share_account.balance().await, 0);
+    let bank = usdc_bank.load().await;
+    assert_eq!(bank.insurance_staker_shares, 0);
+    assert_eq!(bank.insurance_pending_unstake_shares, native!(100, "USDC"));
+
+    let res = usdc_bank
+        .try_insurance_claim_unstake(0, staker_token_account.key)
+        .await;
+    assert!(res.is_err());
+    assert_custom_error!(
+        res.unwrap_err(),
+        MarginfiError::InsuranceUnstakeCooldownActive
+    );
+
+    test_f.advance_time(3_600).await;
+
+    usdc_bank
+        .try_insurance_claim_unstake(0, staker_token_account.key)
+        .await?;
+
+    assert_eq!(staker_token_account.balance().await, native!(100, "USDC"));
+    assert_eq!(
+        balance_of(test_f.context.clone(), usdc_insurance_vault).await,
+        native!(1_000, "USDC")
+    );
+    assert_eq!(usdc_bank.load().await.insurance_pending_unstake_shares, 0);
+
+    // Claiming closes the ticket
+    let unstake_ticket = get_insurance_unstake_ticket_address(usdc_bank.key, test_f.payer(), 0).0;
+    assert!(test_f.try_load(&unstake_ticket).await?.is_none());
+
+    Ok(())
+}
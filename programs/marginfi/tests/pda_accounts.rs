@@ -0,0 +1,55 @@
+use fixtures::{native, prelude::*};
+use marginfi::state::{
+    marginfi_account::{find_marginfi_account_pda, PDA_ACCOUNT_FLAG},
+    marginfi_group::BankVaultType,
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+
+#[tokio::test]
+async fn marginfi_account_pda_per_index() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(Some(TestSettings::all_banks_payer_not_admin())).await;
+    let usdc_bank = test_f.get_bank(&BankMint::Usdc);
+
+    let mfi_account_f = test_f.create_marginfi_account_pda(0).await?;
+    let (account_key, account_bump) =
+        find_marginfi_account_pda(&test_f.marginfi_group.key, &test_f.payer(), 0);
+    assert_eq!(mfi_account_f.key, account_key);
+
+    let mfi_account = mfi_account_f.load().await;
+    assert!(mfi_account.get_flag(PDA_ACCOUNT_FLAG));
+    assert_eq!(mfi_account.authority, test_f.payer());
+    assert_eq!(mfi_account.account_index, 0);
+    assert_eq!(mfi_account.pda_bump, account_bump);
+
+    // Each index holds a single account
+    let res = test_f.create_marginfi_account_pda(0).await;
+    assert!(res.is_err());
+
+    let other_mfi_account_f = test_f.create_marginfi_account_pda(1).await?;
+    assert_eq!(
+        other_mfi_account_f.key,
+        find_marginfi_account_pda(&test_f.marginfi_group.key, &test_f.payer(), 1).0
+    );
+    assert_eq!(other_mfi_account_f.load().await.account_index, 1);
+
+    // PDA accounts are regular marginfi accounts
+    let token_account_usdc = test_f
+        .usdc_mint
+        .create_token_account_and_mint_to(1_000)
+        .await;
+    mfi_account_f
+        .try_bank_deposit(token_account_usdc.key, usdc_bank, 1_000)
+        .await?;
+    assert_eq!(token_account_usdc.balance().await, 0);
+    assert_eq!(
+        usdc_bank
+            .get_vault_token_account(BankVaultType::Liquidity)
+            .await
+            .balance()
+            .await,
+        native!(1_000, "USDC")
+    );
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+use fixtures::prelude::*;
+use marginfi::{
+    constants::SPL_STAKE_POOL_ID,
+    state::{marginfi_group::BankConfig, price::OracleSetup},
+};
+use pretty_assertions::assert_eq;
+use solana_program_test::*;
+use solana_sdk::account::AccountSharedData;
+
+/// Minimal SPL stake pool account holding `total_lamports` for `pool_token_supply` pool tokens.
+fn create_stake_pool_account(
+    pool_mint: Pubkey,
+    total_lamports: u64,
+    pool_token_supply: u64,
+) -> AccountSharedData {
+    let mut data = vec![0u8; 282];
+    // `AccountType::StakePool`
+    data[0] = 1;
+    data[162..194].copy_from_slice(pool_mint.as_ref());
+    data[258..266].copy_from_slice(&total_lamports.to_le_bytes());
+    data[266..274].copy_from_slice(&pool_token_supply.to_le_bytes());
+
+    let mut account = AccountSharedData::new(1_000_000_000, data.len(), &SPL_STAKE_POOL_ID);
+    account.set_data_from_slice(&data);
+
+    account
+}
+
+#[tokio::test]
+async fn add_stake_pool_pyth_push_bank() -> anyhow::Result<()> {
+    let test_f = TestFixture::new(None).await;
+
+    let stake_pool = Pubkey::new_unique();
+    test_f.context.borrow_mut().set_account(
+        &stake_pool,
+        &create_stake_pool_account(test_f.sol_equivalent_mint.key, 1_250, 1_000),
+    );
+
+    let mut oracle_keys = create_oracle_key_array(Pubkey::new_from_array(PYTH_PUSH_FULLV_FEED_ID));
+    oracle_keys[1] = stake_pool;
+
+    let bank_f = test_f
+        .marginfi_group
+        .try_lending_pool_add_bank(
+            &test_f.sol_equivalent_mint,
+            BankConfig {
+                oracle_setup: OracleSetup::StakePoolPythPush,
+                oracle_keys,
+                ..*DEFAULT_SOL_EQUIVALENT_TEST_BANK_CONFIG
+            },
+        )
+        .await?;
+
+    let bank = bank_f.load().await;
+    assert_eq!(bank.config.oracle_setup, OracleSetup::StakePoolPythPush);
+    assert_eq!(bank.config.oracle_keys, oracle_keys);
+
+    // SOL at $10 scaled by the 1.25 exchange rate of the pool
+    assert_eq!(bank_f.get_price().await, 12.5);
+
+    Ok(())
+}
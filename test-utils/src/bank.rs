@@ -1,8 +1,11 @@
 use super::utils::load_and_deserialize;
 use crate::prelude::{
-    get_emissions_authority_address, get_emissions_token_account_address, MintFixture,
-    TokenAccountFixture,
+    get_emissions_authority_address, get_emissions_campaign_authority_address,
+    get_emissions_campaign_vault_address, get_emissions_token_account_address,
+    get_insurance_share_mint_address, get_insurance_unstake_ticket_address,
+    get_oracle_account_metas, MintFixture, TokenAccountFixture,
 };
+use crate::ui_to_native;
 use anchor_lang::{
     prelude::{AccountMeta, Pubkey},
     InstructionData, ToAccountMetas,
@@ -22,7 +25,6 @@ use solana_program::instruction::Instruction;
 use solana_program::sysvar::clock::Clock;
 use solana_program_test::BanksClientError;
 use solana_program_test::ProgramTestContext;
-#[cfg(feature = "lip")]
 use solana_sdk::signature::Keypair;
 use solana_sdk::{signer::Signer, transaction::Transaction};
 use std::{cell::RefCell, fmt::Debug, rc::Rc};
@@ -61,18 +63,30 @@ impl BankFixture {
 
     pub async fn get_price(&self) -> f64 {
         let bank = self.load().await;
-        let oracle_key = bank.config.oracle_keys[0];
-        let mut oracle_account = self
-            .ctx
-            .borrow_mut()
-            .banks_client
-            .get_account(oracle_key)
-            .await
-            .unwrap()
-            .unwrap();
-        let ai = (&oracle_key, &mut oracle_account).into_account_info();
+        let oracle_keys =
+            get_oracle_account_metas(bank.config.oracle_setup, &bank.config.oracle_keys)
+                .iter()
+                .map(|meta| meta.pubkey)
+                .collect::<Vec<_>>();
+        let mut oracle_accounts = vec![];
+        for oracle_key in oracle_keys.iter() {
+            oracle_accounts.push(
+                self.ctx
+                    .borrow_mut()
+                    .banks_client
+                    .get_account(*oracle_key)
+                    .await
+                    .unwrap()
+                    .unwrap(),
+            );
+        }
+        let ais = oracle_keys
+            .iter()
+            .zip(oracle_accounts.iter_mut())
+            .map(|account| account.into_account_info())
+            .collect::<Vec<_>>();
         let oracle_adapter =
-            OraclePriceFeedAdapter::try_from_bank_config(&bank.config, &[ai], &Clock::default())
+            OraclePriceFeedAdapter::try_from_bank_config(&bank.config, &ais, &Clock::default())
                 .unwrap();
 
         oracle_adapter
@@ -94,12 +108,10 @@ impl BankFixture {
         .to_account_metas(Some(true));
 
         if let Some(oracle_config) = config.oracle {
-            accounts.extend(
-                oracle_config
-                    .keys
-                    .iter()
-                    .map(|k| AccountMeta::new_readonly(*k, false)),
-            );
+            accounts.extend(get_oracle_account_metas(
+                oracle_config.setup,
+                &oracle_config.keys,
+            ));
         }
 
         let ix = Instruction {
@@ -244,6 +256,152 @@ impl BankFixture {
         Ok(())
     }
 
+    pub async fn try_update_emissions_schedule(
+        &self,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingPoolUpdateEmissionsSchedule {
+                marginfi_group: self.load().await.group,
+                admin: self.ctx.borrow().payer.pubkey(),
+                bank: self.key,
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingPoolUpdateEmissionsSchedule {
+                start_time,
+                end_time,
+            }
+            .data(),
+        };
+
+        let tx = {
+            let ctx = self.ctx.borrow_mut();
+
+            Transaction::new_signed_with_payer(
+                &[ix],
+                Some(&ctx.payer.pubkey()),
+                &[&ctx.payer],
+                ctx.last_blockhash,
+            )
+        };
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn try_reclaim_emissions(
+        &self,
+        destination_account: Pubkey,
+        token_program: Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingPoolReclaimEmissions {
+                marginfi_group: bank.group,
+                admin: self.ctx.borrow().payer.pubkey(),
+                bank: self.key,
+                emissions_mint: bank.emissions_mint,
+                emissions_auth: get_emissions_authority_address(self.key, bank.emissions_mint).0,
+                emissions_token_account: get_emissions_token_account_address(
+                    self.key,
+                    bank.emissions_mint,
+                )
+                .0,
+                destination_account,
+                token_program,
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingPoolReclaimEmissions {}.data(),
+        };
+
+        let tx = {
+            let ctx = self.ctx.borrow_mut();
+
+            Transaction::new_signed_with_payer(
+                &[ix],
+                Some(&ctx.payer.pubkey()),
+                &[&ctx.payer],
+                ctx.last_blockhash,
+            )
+        };
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Create a campaign funded by the payer, returns the campaign address
+    #[allow(clippy::too_many_arguments)]
+    pub async fn try_create_emissions_campaign(
+        &self,
+        flag: u64,
+        rate: u64,
+        total_emissions: u64,
+        start_time: i64,
+        end_time: i64,
+        emissions_mint: Pubkey,
+        funding_account: Pubkey,
+        token_program: Pubkey,
+    ) -> Result<Pubkey, BanksClientError> {
+        let campaign = Keypair::new();
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingPoolCreateEmissionsCampaign {
+                marginfi_group: self.load().await.group,
+                authority: self.ctx.borrow().payer.pubkey(),
+                bank: self.key,
+                campaign: campaign.pubkey(),
+                emissions_mint,
+                emissions_auth: get_emissions_campaign_authority_address(campaign.pubkey()).0,
+                emissions_vault: get_emissions_campaign_vault_address(campaign.pubkey()).0,
+                emissions_funding_account: funding_account,
+                token_program,
+                system_program: solana_program::system_program::id(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingPoolCreateEmissionsCampaign {
+                emissions_flag: flag,
+                emissions_rate: rate,
+                total_emissions,
+                start_time,
+                end_time,
+            }
+            .data(),
+        };
+
+        let tx = {
+            let ctx = self.ctx.borrow_mut();
+
+            Transaction::new_signed_with_payer(
+                &[ix],
+                Some(&ctx.payer.pubkey()),
+                &[&ctx.payer, &campaign],
+                ctx.last_blockhash,
+            )
+        };
+
+        self.ctx
+            .borrow_mut()
+            .banks_client
+            .process_transaction(tx)
+            .await?;
+
+        Ok(campaign.pubkey())
+    }
+
     pub async fn try_update_emissions(
         &self,
         emissions_flags: Option<u64>,
@@ -342,6 +500,85 @@ impl BankFixture {
         Ok(())
     }
 
+    pub async fn try_collect_fees_as_deposit(
+        &self,
+        fee_marginfi_account: Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingPoolCollectBankFeesAsDeposit {
+                marginfi_group: bank.group,
+                bank: self.key,
+                fee_marginfi_account,
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingPoolCollectBankFeesAsDeposit {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
+    /// `destination_accounts` must match the token account destinations of the group fee
+    /// distribution, in order.
+    pub async fn try_distribute_fees(
+        &self,
+        destination_accounts: &[Pubkey],
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let mut ctx = self.ctx.borrow_mut();
+        let (fee_vault_authority, _) = Pubkey::find_program_address(
+            bank_authority_seed!(BankVaultType::Fee, self.key),
+            &marginfi::id(),
+        );
+
+        let mut accounts = marginfi::accounts::LendingPoolDistributeBankFees {
+            marginfi_group: bank.group,
+            bank: self.key,
+            fee_vault: bank.fee_vault,
+            fee_vault_authority,
+            insurance_vault: bank.insurance_vault,
+            token_program: self.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if self.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(self.mint.key, false));
+        }
+        accounts.extend(
+            destination_accounts
+                .iter()
+                .map(|key| AccountMeta::new(*key, false)),
+        );
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingPoolDistributeBankFees {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
     pub async fn try_withdraw_insurance(
         &self,
         receiving_account: &TokenAccountFixture,
@@ -387,6 +624,178 @@ impl BankFixture {
         Ok(())
     }
 
+    pub async fn try_init_insurance_staking(
+        &self,
+        unstake_cooldown: u64,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingPoolInitInsuranceStaking {
+                marginfi_group: bank.group,
+                admin: ctx.payer.pubkey(),
+                bank: self.key,
+                insurance_vault: bank.insurance_vault,
+                insurance_vault_authority: self.get_vault_authority(BankVaultType::Insurance).0,
+                insurance_share_mint: get_insurance_share_mint_address(self.key).0,
+                token_program: self.get_token_program(),
+                system_program: solana_program::system_program::id(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingPoolInitInsuranceStaking { unstake_cooldown }
+                .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
+    /// Stake from a token account of the payer, minting the shares to `share_account`
+    pub async fn try_insurance_stake<T: Into<f64>>(
+        &self,
+        source_account: Pubkey,
+        share_account: Pubkey,
+        ui_amount: T,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let mut ctx = self.ctx.borrow_mut();
+
+        let mut accounts = marginfi::accounts::LendingPoolInsuranceStake {
+            marginfi_group: bank.group,
+            bank: self.key,
+            staker: ctx.payer.pubkey(),
+            source_token_account: source_account,
+            insurance_vault: bank.insurance_vault,
+            insurance_vault_authority: self.get_vault_authority(BankVaultType::Insurance).0,
+            insurance_share_mint: bank.insurance_share_mint,
+            staker_share_account: share_account,
+            token_program: self.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if self.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(self.mint.key, false));
+        }
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingPoolInsuranceStake {
+                amount: ui_to_native!(ui_amount.into(), self.mint.mint.decimals),
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
+    pub async fn try_insurance_request_unstake(
+        &self,
+        share_account: Pubkey,
+        shares: u64,
+        ticket_index: u16,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let mut ctx = self.ctx.borrow_mut();
+        let staker = ctx.payer.pubkey();
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingPoolInsuranceRequestUnstake {
+                marginfi_group: bank.group,
+                bank: self.key,
+                staker,
+                insurance_share_mint: bank.insurance_share_mint,
+                staker_share_account: share_account,
+                unstake_ticket: get_insurance_unstake_ticket_address(
+                    self.key,
+                    staker,
+                    ticket_index,
+                )
+                .0,
+                token_program: self.get_token_program(),
+                system_program: solana_program::system_program::id(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingPoolInsuranceRequestUnstake {
+                shares,
+                ticket_index,
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
+    pub async fn try_insurance_claim_unstake(
+        &self,
+        ticket_index: u16,
+        destination_account: Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let bank = self.load().await;
+        let mut ctx = self.ctx.borrow_mut();
+        let staker = ctx.payer.pubkey();
+
+        let mut accounts = marginfi::accounts::LendingPoolInsuranceClaimUnstake {
+            marginfi_group: bank.group,
+            bank: self.key,
+            staker,
+            unstake_ticket: get_insurance_unstake_ticket_address(self.key, staker, ticket_index).0,
+            insurance_vault: bank.insurance_vault,
+            insurance_vault_authority: self.get_vault_authority(BankVaultType::Insurance).0,
+            destination_token_account: destination_account,
+            token_program: self.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if self.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(self.mint.key, false));
+        }
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingPoolInsuranceClaimUnstake {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
     pub async fn get_vault_token_account(&self, vault_type: BankVaultType) -> TokenAccountFixture {
         let (vault, _) = self.get_vault(vault_type);
 
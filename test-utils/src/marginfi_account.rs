@@ -1,11 +1,14 @@
 use super::{bank::BankFixture, prelude::*};
 use crate::ui_to_native;
 use anchor_lang::{prelude::*, system_program, InstructionData, ToAccountMetas};
-
-use marginfi::state::{
-    marginfi_account::MarginfiAccount,
-    marginfi_group::{Bank, BankVaultType},
-    price::OracleSetup,
+use fixed::types::I80F48;
+
+use marginfi::{
+    instructions::EmissionsBatchBumps,
+    state::{
+        marginfi_account::{find_marginfi_account_pda, BalanceSide, MarginfiAccount},
+        marginfi_group::{Bank, BankVaultType},
+    },
 };
 use solana_program::{instruction::Instruction, sysvar};
 use solana_program_test::{BanksClientError, ProgramTestContext};
@@ -62,6 +65,44 @@ impl MarginfiAccountFixture {
         }
     }
 
+    pub async fn new_pda(
+        ctx: Rc<RefCell<ProgramTestContext>>,
+        marginfi_group: &Pubkey,
+        account_index: u16,
+    ) -> std::result::Result<MarginfiAccountFixture, BanksClientError> {
+        let ctx_ref = ctx.clone();
+        let mut ctx = ctx.borrow_mut();
+
+        let (account_key, _) =
+            find_marginfi_account_pda(marginfi_group, &ctx.payer.pubkey(), account_index);
+
+        let accounts = marginfi::accounts::MarginfiAccountInitializePda {
+            marginfi_account: account_key,
+            marginfi_group: *marginfi_group,
+            authority: ctx.payer.pubkey(),
+            fee_payer: ctx.payer.pubkey(),
+            system_program: system_program::ID,
+        };
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: accounts.to_account_metas(Some(true)),
+            data: marginfi::instruction::MarginfiAccountInitializePda { account_index }.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(MarginfiAccountFixture {
+            ctx: ctx_ref,
+            key: account_key,
+        })
+    }
+
     pub async fn make_bank_deposit_ix<T: Into<f64>>(
         &self,
         funding_account: Pubkey,
@@ -380,6 +421,50 @@ impl MarginfiAccountFixture {
         Ok(())
     }
 
+    pub async fn try_recover_bad_debt<T: Into<f64>>(
+        &self,
+        funding_account: Pubkey,
+        bank: &BankFixture,
+        ui_amount: T,
+    ) -> anyhow::Result<(), BanksClientError> {
+        let marginfi_account = self.load().await;
+        let mut ctx = self.ctx.borrow_mut();
+
+        let mut accounts = marginfi::accounts::LendingAccountRecoverBadDebt {
+            marginfi_group: marginfi_account.group,
+            marginfi_account: self.key,
+            signer: ctx.payer.pubkey(),
+            bank: bank.key,
+            signer_token_account: funding_account,
+            bank_insurance_vault: bank.get_vault(BankVaultType::Insurance).0,
+            token_program: bank.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if bank.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
+        }
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingAccountRecoverBadDebt {
+                amount: ui_to_native!(ui_amount.into(), bank.mint.mint.decimals),
+            }
+            .data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
     pub async fn try_balance_close(
         &self,
         bank: &BankFixture,
@@ -443,21 +528,9 @@ impl MarginfiAccountFixture {
             accounts.push(AccountMeta::new_readonly(liab_bank_fixture.mint.key, false));
         }
 
-        let oracle_accounts = vec![asset_bank.config, liab_bank.config]
+        let oracle_accounts = [asset_bank.config, liab_bank.config]
             .iter()
-            .map(|config| {
-                AccountMeta::new_readonly(
-                    {
-                        match config.oracle_setup {
-                            OracleSetup::PythPushOracle => {
-                                get_oracle_id_from_feed_id(config.oracle_keys[0]).unwrap()
-                            }
-                            _ => config.oracle_keys[0],
-                        }
-                    },
-                    false,
-                )
-            })
+            .flat_map(|config| get_oracle_account_metas(config.oracle_setup, &config.oracle_keys))
             .collect::<Vec<AccountMeta>>();
 
         accounts.extend(oracle_accounts);
@@ -527,6 +600,213 @@ impl MarginfiAccountFixture {
         ctx.banks_client.process_transaction(tx).await
     }
 
+    pub async fn try_auto_deleverage<T: Into<f64> + Copy>(
+        &self,
+        deleveraged: &MarginfiAccountFixture,
+        asset_bank_fixture: &BankFixture,
+        liab_bank_fixture: &BankFixture,
+        liab_ui_amount: T,
+        funding_account: Pubkey,
+    ) -> std::result::Result<(), BanksClientError> {
+        let marginfi_account = self.load().await;
+
+        let asset_bank = asset_bank_fixture.load().await;
+        let liab_bank = liab_bank_fixture.load().await;
+
+        let mut accounts = marginfi::accounts::LendingAccountAutoDeleverage {
+            marginfi_group: marginfi_account.group,
+            asset_bank: asset_bank_fixture.key,
+            liab_bank: liab_bank_fixture.key,
+            keeper_marginfi_account: self.key,
+            signer: self.ctx.borrow().payer.pubkey(),
+            deleveraged_marginfi_account: deleveraged.key,
+            signer_token_account: funding_account,
+            bank_liquidity_vault: liab_bank_fixture.get_vault(BankVaultType::Liquidity).0,
+            token_program: liab_bank_fixture.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+
+        if liab_bank_fixture.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(liab_bank_fixture.mint.key, false));
+        }
+
+        let oracle_accounts = [asset_bank.config, liab_bank.config]
+            .iter()
+            .flat_map(|config| get_oracle_account_metas(config.oracle_setup, &config.oracle_keys))
+            .collect::<Vec<AccountMeta>>();
+
+        accounts.extend(oracle_accounts);
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingAccountAutoDeleverage {
+                liab_amount: ui_to_native!(
+                    liab_ui_amount.into(),
+                    liab_bank_fixture.mint.mint.decimals
+                ),
+            }
+            .data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn try_create_conditional_order<T: Into<f64>>(
+        &self,
+        order_index: u16,
+        asset_bank_fixture: &BankFixture,
+        liab_bank_fixture: &BankFixture,
+        trigger: u8,
+        trigger_health: I80F48,
+        liab_ui_amount: T,
+        keeper_fee_bps: u16,
+    ) -> std::result::Result<(), BanksClientError> {
+        let marginfi_account = self.load().await;
+        let payer = self.ctx.borrow().payer.pubkey();
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingAccountCreateConditionalOrder {
+                marginfi_group: marginfi_account.group,
+                marginfi_account: self.key,
+                signer: payer,
+                asset_bank: asset_bank_fixture.key,
+                liab_bank: liab_bank_fixture.key,
+                conditional_order: get_conditional_order_address(self.key, order_index).0,
+                fee_payer: payer,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingAccountCreateConditionalOrder {
+                order_index,
+                trigger,
+                trigger_health: trigger_health.into(),
+                liab_amount: ui_to_native!(
+                    liab_ui_amount.into(),
+                    liab_bank_fixture.mint.mint.decimals
+                ),
+                keeper_fee_bps,
+            }
+            .data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_cancel_conditional_order(
+        &self,
+        order_index: u16,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingAccountCancelConditionalOrder {
+                marginfi_account: self.key,
+                signer: self.ctx.borrow().payer.pubkey(),
+                conditional_order: get_conditional_order_address(self.key, order_index).0,
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingAccountCancelConditionalOrder {}.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Execute a conditional order of `order_account` with this account as the keeper account.
+    pub async fn try_execute_conditional_order<T: Into<f64> + Copy>(
+        &self,
+        order_account: &MarginfiAccountFixture,
+        order_index: u16,
+        asset_bank_fixture: &BankFixture,
+        liab_bank_fixture: &BankFixture,
+        liab_ui_amount: T,
+        funding_account: Pubkey,
+    ) -> std::result::Result<(), BanksClientError> {
+        let marginfi_account = self.load().await;
+
+        let asset_bank = asset_bank_fixture.load().await;
+        let liab_bank = liab_bank_fixture.load().await;
+
+        let mut accounts = marginfi::accounts::LendingAccountExecuteConditionalOrder {
+            marginfi_group: marginfi_account.group,
+            asset_bank: asset_bank_fixture.key,
+            liab_bank: liab_bank_fixture.key,
+            keeper_marginfi_account: self.key,
+            signer: self.ctx.borrow().payer.pubkey(),
+            marginfi_account: order_account.key,
+            conditional_order: get_conditional_order_address(order_account.key, order_index).0,
+            signer_token_account: funding_account,
+            bank_liquidity_vault: liab_bank_fixture.get_vault(BankVaultType::Liquidity).0,
+            token_program: liab_bank_fixture.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+
+        if liab_bank_fixture.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(liab_bank_fixture.mint.key, false));
+        }
+
+        let oracle_accounts = [asset_bank.config, liab_bank.config]
+            .iter()
+            .flat_map(|config| get_oracle_account_metas(config.oracle_setup, &config.oracle_keys))
+            .collect::<Vec<AccountMeta>>();
+
+        accounts.extend(oracle_accounts);
+        accounts.extend(
+            order_account
+                .load_observation_account_metas(vec![], vec![])
+                .await,
+        );
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingAccountExecuteConditionalOrder {
+                liab_amount: ui_to_native!(
+                    liab_ui_amount.into(),
+                    liab_bank_fixture.mint.mint.decimals
+                ),
+            }
+            .data(),
+        };
+
+        let compute_budget_ix = ComputeBudgetInstruction::set_compute_unit_limit(1_400_000);
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[compute_budget_ix, ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     pub async fn try_withdraw_emissions(
         &self,
         bank: &BankFixture,
@@ -561,6 +841,217 @@ impl MarginfiAccountFixture {
         ctx.banks_client.process_transaction(tx).await
     }
 
+    pub async fn try_configure_emissions(
+        &self,
+        claim_delegate: Option<Pubkey>,
+        auto_compound: Option<bool>,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingAccountConfigureEmissions {
+                marginfi_account: self.key,
+                signer: self.ctx.borrow().payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingAccountConfigureEmissions {
+                claim_delegate,
+                auto_compound,
+            }
+            .data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_set_delegate(
+        &self,
+        delegate: Pubkey,
+        permissions: u64,
+        expiry: i64,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::MarginfiAccountSetDelegate {
+                marginfi_account: self.key,
+                signer: self.ctx.borrow().payer.pubkey(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::MarginfiAccountSetDelegate {
+                delegate,
+                permissions,
+                expiry,
+            }
+            .data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_compound_emissions(
+        &self,
+        bank: &BankFixture,
+    ) -> std::result::Result<(), BanksClientError> {
+        let emissions_mint = bank.load().await.emissions_mint;
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingAccountCompoundEmissions {
+                marginfi_account: self.key,
+                bank: bank.key,
+                emissions_mint,
+                emissions_auth: get_emissions_authority_address(bank.key, emissions_mint).0,
+                emissions_vault: get_emissions_token_account_address(bank.key, emissions_mint).0,
+                bank_liquidity_vault: bank.get_vault(BankVaultType::Liquidity).0,
+                token_program: bank.get_token_program(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingAccountCompoundEmissions {}.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Withdraw the emissions of several banks, each to its own destination account
+    pub async fn try_withdraw_emissions_batch(
+        &self,
+        banks: &[(&BankFixture, &TokenAccountFixture)],
+    ) -> std::result::Result<(), BanksClientError> {
+        let mut remaining_accounts = vec![];
+        let mut bumps = vec![];
+        for (bank, recv_account) in banks {
+            let emissions_mint = bank.load().await.emissions_mint;
+            let (emissions_auth, emissions_auth_bump) =
+                get_emissions_authority_address(bank.key, emissions_mint);
+            let (emissions_vault, emissions_vault_bump) =
+                get_emissions_token_account_address(bank.key, emissions_mint);
+            remaining_accounts.extend([
+                AccountMeta::new(bank.key, false),
+                AccountMeta::new_readonly(emissions_mint, false),
+                AccountMeta::new_readonly(emissions_auth, false),
+                AccountMeta::new(emissions_vault, false),
+                AccountMeta::new(recv_account.key, false),
+            ]);
+            bumps.push(EmissionsBatchBumps {
+                emissions_auth_bump,
+                emissions_vault_bump,
+            });
+        }
+
+        let mut accounts = marginfi::accounts::LendingAccountWithdrawEmissionsBatch {
+            marginfi_account: self.key,
+            signer: self.ctx.borrow().payer.pubkey(),
+            token_program: banks
+                .first()
+                .map(|(_, recv_account)| recv_account.token_program)
+                .unwrap_or_default(),
+        }
+        .to_account_metas(Some(true));
+        accounts.extend(remaining_accounts);
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingAccountWithdrawEmissionsBatch { bumps }.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_init_campaign_position(
+        &self,
+        campaign: Pubkey,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingAccountInitCampaignPosition {
+                marginfi_account: self.key,
+                campaign,
+                position: get_emissions_campaign_position_address(campaign, self.key).0,
+                fee_payer: self.ctx.borrow().payer.pubkey(),
+                system_program: system_program::ID,
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingAccountInitCampaignPosition {}.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_claim_campaign_emissions(
+        &self,
+        bank: &BankFixture,
+        campaign: Pubkey,
+        emissions_mint: Pubkey,
+        recv_account: &TokenAccountFixture,
+    ) -> std::result::Result<(), BanksClientError> {
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingAccountClaimCampaignEmissions {
+                marginfi_account: self.key,
+                signer: self.ctx.borrow().payer.pubkey(),
+                bank: bank.key,
+                campaign,
+                position: get_emissions_campaign_position_address(campaign, self.key).0,
+                emissions_mint,
+                emissions_auth: get_emissions_campaign_authority_address(campaign).0,
+                emissions_vault: get_emissions_campaign_vault_address(campaign).0,
+                destination_account: recv_account.key,
+                token_program: recv_account.token_program,
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingAccountClaimCampaignEmissions {}.data(),
+        };
+
+        let mut ctx = self.ctx.borrow_mut();
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey().clone()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     /// Set a flag on the account
     ///
     /// Function assumes signer is group admin
@@ -631,16 +1122,29 @@ impl MarginfiAccountFixture {
         include_banks: Vec<Pubkey>,
         exclude_banks: Vec<Pubkey>,
     ) -> Instruction {
+        let marginfi_account = self.load().await;
         let mut account_metas = marginfi::accounts::LendingAccountEndFlashloan {
             marginfi_account: self.key,
             signer: self.ctx.borrow().payer.pubkey(),
         }
         .to_account_metas(Some(true));
 
-        account_metas.extend(
-            self.load_observation_account_metas(include_banks, exclude_banks)
-                .await,
-        );
+        // Banks are writable, the flashloan fee may be charged on the net borrows of the flashloan
+        let bank_pks = marginfi_account
+            .lending_account
+            .balances
+            .iter()
+            .filter(|balance| balance.active)
+            .map(|balance| balance.bank_pk)
+            .chain(include_banks.iter().copied())
+            .collect::<Vec<_>>();
+        let mut observation_metas = self
+            .load_observation_account_metas(include_banks, exclude_banks)
+            .await;
+        for meta in observation_metas.iter_mut() {
+            meta.is_writable |= bank_pks.contains(&meta.pubkey);
+        }
+        account_metas.extend(observation_metas);
 
         Instruction {
             program_id: marginfi::id(),
@@ -680,6 +1184,23 @@ impl MarginfiAccountFixture {
         ctx.banks_client.process_transaction(tx).await
     }
 
+    /// Observation account metas for bankruptcy handling, followed by the writable banks of asset
+    /// balances to sweep residual assets to their insurance fees.
+    pub async fn load_bankruptcy_observation_account_metas(&self) -> Vec<AccountMeta> {
+        let marginfi_account = self.load().await;
+        let residual_asset_bank_metas = marginfi_account
+            .lending_account
+            .balances
+            .iter()
+            .filter(|balance| balance.active && !balance.is_empty(BalanceSide::Assets))
+            .map(|balance| AccountMeta::new(balance.bank_pk, false));
+
+        let mut account_metas = self.load_observation_account_metas(vec![], vec![]).await;
+        account_metas.extend(residual_asset_bank_metas);
+
+        account_metas
+    }
+
     pub async fn load_observation_account_metas(
         &self,
         include_banks: Vec<Pubkey>,
@@ -721,28 +1242,18 @@ impl MarginfiAccountFixture {
             .iter()
             .zip(bank_pks.iter())
             .flat_map(|(bank, bank_pk)| {
-                let oracle_key = {
-                    let oracle_key = bank.config.oracle_keys[0];
-                    match bank.config.oracle_setup {
-                        OracleSetup::PythPushOracle => {
-                            get_oracle_id_from_feed_id(oracle_key).unwrap()
-                        }
-                        _ => oracle_key,
-                    }
-                };
-
-                vec![
-                    AccountMeta {
-                        pubkey: *bank_pk,
-                        is_signer: false,
-                        is_writable: false,
-                    },
-                    AccountMeta {
-                        pubkey: oracle_key,
-                        is_signer: false,
-                        is_writable: false,
-                    },
-                ]
+                let mut metas = vec![AccountMeta {
+                    pubkey: *bank_pk,
+                    is_signer: false,
+                    is_writable: false,
+                }];
+
+                metas.extend(get_oracle_account_metas(
+                    bank.config.oracle_setup,
+                    &bank.config.oracle_keys,
+                ));
+
+                metas
             })
             .collect::<Vec<_>>();
         account_metas
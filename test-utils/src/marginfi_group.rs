@@ -1,10 +1,11 @@
 use super::{bank::BankFixture, marginfi_account::MarginfiAccountFixture};
-use crate::prelude::{get_oracle_id_from_feed_id, MintFixture};
+use crate::prelude::{get_oracle_account_metas, MintFixture};
 use crate::utils::*;
 use anchor_lang::{prelude::*, solana_program::system_program, InstructionData};
 
 use anyhow::Result;
 use marginfi::{
+    constants::{GROUP_INSURANCE_VAULT_AUTHORITY_SEED, GROUP_INSURANCE_VAULT_SEED},
     prelude::MarginfiGroup,
     state::marginfi_group::{BankConfig, BankConfigOpt, BankVaultType, GroupConfig},
 };
@@ -97,17 +98,10 @@ impl MarginfiGroupFixture {
         }
         .to_account_metas(Some(true));
 
-        let oracle_key = {
-            let oracle_key_or_feed_id = bank_config.oracle_keys[0];
-            match bank_config.oracle_setup {
-                marginfi::state::price::OracleSetup::PythPushOracle => {
-                    get_oracle_id_from_feed_id(oracle_key_or_feed_id).unwrap()
-                }
-                _ => oracle_key_or_feed_id,
-            }
-        };
-
-        accounts.push(AccountMeta::new_readonly(oracle_key, false));
+        accounts.extend(get_oracle_account_metas(
+            bank_config.oracle_setup,
+            &bank_config.oracle_keys,
+        ));
 
         let ix = Instruction {
             program_id: marginfi::id(),
@@ -174,7 +168,10 @@ impl MarginfiGroupFixture {
         }
         .to_account_metas(Some(true));
 
-        accounts.push(AccountMeta::new_readonly(bank_config.oracle_keys[0], false));
+        accounts.extend(get_oracle_account_metas(
+            bank_config.oracle_setup,
+            &bank_config.oracle_keys,
+        ));
 
         let ix = Instruction {
             program_id: marginfi::id(),
@@ -339,6 +336,107 @@ impl MarginfiGroupFixture {
         Ok(())
     }
 
+    pub fn get_group_insurance_fund_vault(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[GROUP_INSURANCE_VAULT_SEED.as_bytes(), self.key.as_ref()],
+            &marginfi::id(),
+        )
+    }
+
+    pub fn get_group_insurance_fund_vault_authority(&self) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                GROUP_INSURANCE_VAULT_AUTHORITY_SEED.as_bytes(),
+                self.key.as_ref(),
+            ],
+            &marginfi::id(),
+        )
+    }
+
+    /// Group insurance fund accounts covering the bad debt of `bank`, if the fund holds its mint.
+    async fn get_group_insurance_fund_metas(&self, bank: &BankFixture) -> Vec<AccountMeta> {
+        let group = self.load().await;
+
+        if group.insurance_fund_mint == Pubkey::default()
+            || group.insurance_fund_mint != bank.mint.key
+        {
+            return vec![];
+        }
+
+        vec![
+            AccountMeta::new(self.get_group_insurance_fund_vault().0, false),
+            AccountMeta::new_readonly(self.get_group_insurance_fund_vault_authority().0, false),
+        ]
+    }
+
+    pub async fn try_init_group_insurance_fund(
+        &self,
+        mint: &MintFixture,
+    ) -> Result<(), BanksClientError> {
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts: marginfi::accounts::LendingPoolInitGroupInsuranceFund {
+                marginfi_group: self.key,
+                admin: ctx.payer.pubkey(),
+                insurance_fund_mint: mint.key,
+                insurance_fund_vault_authority: self.get_group_insurance_fund_vault_authority().0,
+                insurance_fund_vault: self.get_group_insurance_fund_vault().0,
+                token_program: mint.token_program,
+                system_program: system_program::id(),
+            }
+            .to_account_metas(Some(true)),
+            data: marginfi::instruction::LendingPoolInitGroupInsuranceFund {}.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_fund_group_insurance(
+        &self,
+        bank: &BankFixture,
+        amount: u64,
+    ) -> Result<(), BanksClientError> {
+        let mut accounts = marginfi::accounts::LendingPoolFundGroupInsurance {
+            marginfi_group: self.key,
+            admin: self.ctx.borrow().payer.pubkey(),
+            bank: bank.key,
+            insurance_vault: bank.get_vault(BankVaultType::Insurance).0,
+            insurance_vault_authority: bank.get_vault_authority(BankVaultType::Insurance).0,
+            insurance_fund_vault: self.get_group_insurance_fund_vault().0,
+            token_program: bank.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if bank.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
+        }
+
+        let mut ctx = self.ctx.borrow_mut();
+
+        let ix = Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingPoolFundGroupInsurance { amount }.data(),
+        };
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
     pub async fn try_handle_bankruptcy(
         &self,
         bank: &BankFixture,
@@ -354,39 +452,103 @@ impl MarginfiGroupFixture {
         marginfi_account: &MarginfiAccountFixture,
         nonce: u64,
     ) -> Result<(), BanksClientError> {
-        let mut accounts = marginfi::accounts::LendingPoolHandleBankruptcy {
+        let ix = self
+            .make_handle_bankruptcy_ix(
+                bank,
+                marginfi_account,
+                marginfi_account
+                    .load_observation_account_metas(vec![], vec![])
+                    .await,
+            )
+            .await;
+
+        let mut ctx = self.ctx.borrow_mut();
+
+        let nonce_ix = ComputeBudgetInstruction::set_compute_unit_price(nonce);
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix, nonce_ix],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    /// Handle the bankruptcy and sweep the residual assets of the account.
+    pub async fn try_handle_bankruptcy_and_sweep_residual_assets(
+        &self,
+        bank: &BankFixture,
+        marginfi_account: &MarginfiAccountFixture,
+    ) -> Result<(), BanksClientError> {
+        let ix = self
+            .make_handle_bankruptcy_ix(
+                bank,
+                marginfi_account,
+                marginfi_account
+                    .load_bankruptcy_observation_account_metas()
+                    .await,
+            )
+            .await;
+
+        let mut ctx = self.ctx.borrow_mut();
+
+        let tx = Transaction::new_signed_with_payer(
+            &[ix],
+            Some(&ctx.payer.pubkey()),
+            &[&ctx.payer],
+            ctx.last_blockhash,
+        );
+
+        ctx.banks_client.process_transaction(tx).await
+    }
+
+    pub async fn try_handle_bankruptcies(
+        &self,
+        banks: &[&BankFixture],
+        marginfi_account: &MarginfiAccountFixture,
+    ) -> Result<(), BanksClientError> {
+        let mut accounts = marginfi::accounts::LendingPoolHandleBankruptcies {
             marginfi_group: self.key,
             signer: self.ctx.borrow().payer.pubkey(),
-            bank: bank.key,
             marginfi_account: marginfi_account.key,
-            liquidity_vault: bank.get_vault(BankVaultType::Liquidity).0,
-            insurance_vault: bank.get_vault(BankVaultType::Insurance).0,
-            insurance_vault_authority: bank.get_vault_authority(BankVaultType::Insurance).0,
-            token_program: bank.get_token_program(),
         }
         .to_account_metas(Some(true));
-        if bank.mint.token_program == spl_token_2022::ID {
-            accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
-        }
 
         accounts.append(
             &mut marginfi_account
-                .load_observation_account_metas(vec![], vec![])
+                .load_bankruptcy_observation_account_metas()
                 .await,
         );
 
+        for bank in banks {
+            accounts.extend([
+                AccountMeta::new(bank.key, false),
+                AccountMeta::new(bank.get_vault(BankVaultType::Liquidity).0, false),
+                AccountMeta::new(bank.get_vault(BankVaultType::Insurance).0, false),
+                AccountMeta::new_readonly(
+                    bank.get_vault_authority(BankVaultType::Insurance).0,
+                    false,
+                ),
+                AccountMeta::new_readonly(bank.get_token_program(), false),
+            ]);
+            if bank.mint.token_program == spl_token_2022::ID {
+                accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
+            }
+            accounts.append(&mut self.get_group_insurance_fund_metas(bank).await);
+        }
+
         let mut ctx = self.ctx.borrow_mut();
 
         let ix = Instruction {
             program_id: marginfi::id(),
             accounts,
-            data: marginfi::instruction::LendingPoolHandleBankruptcy {}.data(),
+            data: marginfi::instruction::LendingPoolHandleBankruptcies {}.data(),
         };
 
-        let nonce_ix = ComputeBudgetInstruction::set_compute_unit_price(nonce);
-
         let tx = Transaction::new_signed_with_payer(
-            &[ix, nonce_ix],
+            &[ix],
             Some(&ctx.payer.pubkey()),
             &[&ctx.payer],
             ctx.last_blockhash,
@@ -395,6 +557,37 @@ impl MarginfiGroupFixture {
         ctx.banks_client.process_transaction(tx).await
     }
 
+    async fn make_handle_bankruptcy_ix(
+        &self,
+        bank: &BankFixture,
+        marginfi_account: &MarginfiAccountFixture,
+        mut observation_metas: Vec<AccountMeta>,
+    ) -> Instruction {
+        let mut accounts = marginfi::accounts::LendingPoolHandleBankruptcy {
+            marginfi_group: self.key,
+            signer: self.ctx.borrow().payer.pubkey(),
+            bank: bank.key,
+            marginfi_account: marginfi_account.key,
+            liquidity_vault: bank.get_vault(BankVaultType::Liquidity).0,
+            insurance_vault: bank.get_vault(BankVaultType::Insurance).0,
+            insurance_vault_authority: bank.get_vault_authority(BankVaultType::Insurance).0,
+            token_program: bank.get_token_program(),
+        }
+        .to_account_metas(Some(true));
+        if bank.mint.token_program == spl_token_2022::ID {
+            accounts.push(AccountMeta::new_readonly(bank.mint.key, false));
+        }
+        accounts.append(&mut self.get_group_insurance_fund_metas(bank).await);
+
+        accounts.append(&mut observation_metas);
+
+        Instruction {
+            program_id: marginfi::id(),
+            accounts,
+            data: marginfi::instruction::LendingPoolHandleBankruptcy {}.data(),
+        }
+    }
+
     pub fn get_size() -> usize {
         8 + mem::size_of::<MarginfiGroup>()
     }
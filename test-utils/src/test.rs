@@ -75,7 +75,10 @@ impl TestSettings {
 
         Self {
             banks,
-            group_config: Some(GroupConfig { admin: None }),
+            group_config: Some(GroupConfig {
+                admin: None,
+                ..Default::default()
+            }),
         }
     }
 
@@ -92,7 +95,10 @@ impl TestSettings {
                     config: Some(*DEFAULT_SOL_TEST_SW_BANK_CONFIG),
                 },
             ],
-            group_config: Some(GroupConfig { admin: None }),
+            group_config: Some(GroupConfig {
+                admin: None,
+                ..Default::default()
+            }),
         }
     }
 
@@ -140,7 +146,10 @@ impl TestSettings {
                     ..TestBankSetting::default()
                 },
             ],
-            group_config: Some(GroupConfig { admin: None }),
+            group_config: Some(GroupConfig {
+                admin: None,
+                ..Default::default()
+            }),
         }
     }
 }
@@ -234,6 +243,30 @@ pub fn create_oracle_key_array(pyth_oracle: Pubkey) -> [Pubkey; MAX_ORACLE_KEYS]
     keys
 }
 
+/// Oracle account metas that follow a bank in remaining accounts, the number of metas matches
+/// `OracleSetup::get_oracle_ais_len`.
+pub fn get_oracle_account_metas(
+    oracle_setup: OracleSetup,
+    oracle_keys: &[Pubkey; MAX_ORACLE_KEYS],
+) -> Vec<AccountMeta> {
+    let oracle_ais = match oracle_setup {
+        OracleSetup::Fixed => vec![],
+        OracleSetup::PythPushOracle => vec![get_oracle_id_from_feed_id(oracle_keys[0]).unwrap()],
+        OracleSetup::StakePoolPythPush => vec![
+            get_oracle_id_from_feed_id(oracle_keys[0]).unwrap(),
+            oracle_keys[1],
+        ],
+        _ => vec![oracle_keys[0]],
+    };
+
+    assert_eq!(oracle_ais.len(), oracle_setup.get_oracle_ais_len());
+
+    oracle_ais
+        .into_iter()
+        .map(|key| AccountMeta::new_readonly(key, false))
+        .collect()
+}
+
 lazy_static! {
     pub static ref DEFAULT_TEST_BANK_INTEREST_RATE_CONFIG: InterestRateConfig =
         InterestRateConfig {
@@ -594,8 +627,16 @@ impl TestFixture {
             Rc::clone(&context),
             test_settings
                 .clone()
-                .map(|ts| ts.group_config.unwrap_or(GroupConfig { admin: None }))
-                .unwrap_or(GroupConfig { admin: None }),
+                .map(|ts| {
+                    ts.group_config.unwrap_or(GroupConfig {
+                        admin: None,
+                        ..Default::default()
+                    })
+                })
+                .unwrap_or(GroupConfig {
+                    admin: None,
+                    ..Default::default()
+                }),
         )
         .await;
 
@@ -687,6 +728,18 @@ impl TestFixture {
         MarginfiAccountFixture::new(Rc::clone(&self.context), &self.marginfi_group.key).await
     }
 
+    pub async fn create_marginfi_account_pda(
+        &self,
+        account_index: u16,
+    ) -> std::result::Result<MarginfiAccountFixture, BanksClientError> {
+        MarginfiAccountFixture::new_pda(
+            Rc::clone(&self.context),
+            &self.marginfi_group.key,
+            account_index,
+        )
+        .await
+    }
+
     pub async fn try_load(
         &self,
         address: &Pubkey,
@@ -617,6 +617,80 @@ macro_rules! f_native {
     };
 }
 
+pub fn get_emissions_campaign_authority_address(campaign_pk: Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            marginfi::constants::EMISSIONS_CAMPAIGN_AUTH_SEED.as_bytes(),
+            campaign_pk.as_ref(),
+        ],
+        &marginfi::id(),
+    )
+}
+
+pub fn get_emissions_campaign_vault_address(campaign_pk: Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            marginfi::constants::EMISSIONS_CAMPAIGN_VAULT_SEED.as_bytes(),
+            campaign_pk.as_ref(),
+        ],
+        &marginfi::id(),
+    )
+}
+
+pub fn get_emissions_campaign_position_address(
+    campaign_pk: Pubkey,
+    marginfi_account_pk: Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            marginfi::constants::EMISSIONS_CAMPAIGN_POSITION_SEED.as_bytes(),
+            campaign_pk.as_ref(),
+            marginfi_account_pk.as_ref(),
+        ],
+        &marginfi::id(),
+    )
+}
+
+pub fn get_conditional_order_address(
+    marginfi_account_pk: Pubkey,
+    order_index: u16,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            marginfi::constants::CONDITIONAL_ORDER_SEED.as_bytes(),
+            marginfi_account_pk.as_ref(),
+            &order_index.to_le_bytes(),
+        ],
+        &marginfi::id(),
+    )
+}
+
+pub fn get_insurance_share_mint_address(bank_pk: Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            marginfi::constants::INSURANCE_SHARE_MINT_SEED.as_bytes(),
+            bank_pk.as_ref(),
+        ],
+        &marginfi::id(),
+    )
+}
+
+pub fn get_insurance_unstake_ticket_address(
+    bank_pk: Pubkey,
+    staker_pk: Pubkey,
+    ticket_index: u16,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            marginfi::constants::INSURANCE_UNSTAKE_TICKET_SEED.as_bytes(),
+            bank_pk.as_ref(),
+            staker_pk.as_ref(),
+            &ticket_index.to_le_bytes(),
+        ],
+        &marginfi::id(),
+    )
+}
+
 pub fn clone_keypair(keypair: &Keypair) -> Keypair {
     Keypair::from_bytes(&keypair.to_bytes()).unwrap()
 }
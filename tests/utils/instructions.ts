@@ -9,8 +9,9 @@ import {
   deriveLiquidityVault,
   deriveLiquidityVaultAuthority,
 } from "./pdas";
-import { BankConfig } from "./types";
+import { BankConfig, FeeDestination } from "./types";
 import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { WrappedI80F48 } from "@mrgnlabs/mrgn-common";
 
 export const MAX_ORACLE_KEYS = 5;
 
@@ -53,6 +54,11 @@ export const addBank = (program: Program<Marginfi>, args: AddBankArgs) => {
       riskTier: args.config.riskTier,
       totalAssetValueInitLimit: args.config.totalAssetValueInitLimit,
       oracleMaxAge: args.config.oracleMaxAge,
+      fixedPrice: args.config.fixedPrice,
+      oracleMaxSlotAge: args.config.oracleMaxSlotAge,
+      oracleMinPrice: args.config.oracleMinPrice,
+      oracleMaxPrice: args.config.oracleMaxPrice,
+      secondaryOracleKey: args.config.secondaryOracleKey,
     })
     .accounts({
       marginfiGroup: args.marginfiGroup,
@@ -70,7 +76,8 @@ export const addBank = (program: Program<Marginfi>, args: AddBankArgs) => {
       tokenProgram: TOKEN_PROGRAM_ID,
       // systemProgram: SystemProgram.programId,
     })
-    .remainingAccounts([oracleMeta])
+    // Fixed price banks don't take an oracle account
+    .remainingAccounts("fixed" in args.config.oracleSetup ? [] : [oracleMeta])
     .instruction();
 
   return ix;
@@ -78,10 +85,25 @@ export const addBank = (program: Program<Marginfi>, args: AddBankArgs) => {
 
 /**
  * newAdmin - (Optional) pass null to keep current admin
+ * bankruptThreshold - (Optional) omit or pass null to keep the current threshold
+ * feeDistribution - (Optional) omit or pass null to keep the current fee destinations
+ * feeMarginfiAccount - (Optional) omit or pass null to keep the current fee account
+ * flashloanCpiCallers - (Optional) omit or pass null to keep the current whitelist
+ * flashloanFeeExemptAuthorities - (Optional) omit or pass null to keep the current exemption list
  * admin - must sign, must be current admin of marginfiGroup
  */
 export type GroupConfigureArgs = {
   newAdmin: PublicKey | null;
+  /** (Optional) USD value below which an account can be handled as bankrupt */
+  bankruptThreshold?: WrappedI80F48 | null;
+  /** (Optional) exactly 4 destinations, unused ones zeroed */
+  feeDistribution?: FeeDestination[] | null;
+  /** (Optional) marginfi account receiving group fees as deposits, default key to disable */
+  feeMarginfiAccount?: PublicKey | null;
+  /** (Optional) exactly 4 programs allowed to flashloan through CPI, unused ones default key */
+  flashloanCpiCallers?: PublicKey[] | null;
+  /** (Optional) exactly 4 authorities exempt from flashloan fees, unused ones default key */
+  flashloanFeeExemptAuthorities?: PublicKey[] | null;
   marginfiGroup: PublicKey;
   admin: PublicKey;
 };
@@ -91,7 +113,14 @@ export const groupConfigure = (
   args: GroupConfigureArgs
 ) => {
   const ix = program.methods
-    .marginfiGroupConfigure({ admin: args.newAdmin })
+    .marginfiGroupConfigure({
+      admin: args.newAdmin,
+      bankruptThreshold: args.bankruptThreshold ?? null,
+      feeDistribution: args.feeDistribution ?? null,
+      feeMarginfiAccount: args.feeMarginfiAccount ?? null,
+      flashloanCpiCallers: args.flashloanCpiCallers ?? null,
+      flashloanFeeExemptAuthorities: args.flashloanFeeExemptAuthorities ?? null,
+    })
     .accounts({
       marginfiGroup: args.marginfiGroup,
       admin: args.admin,
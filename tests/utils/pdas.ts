@@ -50,3 +50,22 @@ export const deriveFeeVault = (programId: PublicKey, bank: PublicKey) => {
     programId
   );
 };
+
+export const deriveMarginfiAccount = (
+  programId: PublicKey,
+  group: PublicKey,
+  authority: PublicKey,
+  accountIndex: number
+) => {
+  const index = Buffer.alloc(2);
+  index.writeUInt16LE(accountIndex);
+  return PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("marginfi_account", "utf-8"),
+      group.toBuffer(),
+      authority.toBuffer(),
+      index,
+    ],
+    programId
+  );
+};
@@ -17,7 +17,10 @@ export type OracleSetup =
   | { none: {} }
   | { pythLegacy: {} }
   | { switchboardV2: {} }
-  | { pythPushOracle: {} };
+  | { pythPushOracle: {} }
+  | { switchboardPull: {} }
+  | { fixed: {} }
+  | { stakePoolPythPush: {} };
 
 export type BankConfig = {
   assetWeightInit: WrappedI80F48;
@@ -32,7 +35,7 @@ export type BankConfig = {
   /** Paused = 0, Operational = 1, ReduceOnly = 2 */
   operationalState: OperationalState;
 
-  /** None = 0, PythLegacy = 1, SwitchboardV2 = 2, PythPushOracle =3 */
+  /** None = 0, PythLegacy = 1, SwitchboardV2 = 2, PythPushOracle = 3, SwitchboardPull = 4, Fixed = 5, StakePoolPythPush = 6 */
   oracleSetup: OracleSetup;
   oracleKey: PublicKey;
 
@@ -41,6 +44,26 @@ export type BankConfig = {
   riskTier: RiskTier;
   totalAssetValueInitLimit: BN;
   oracleMaxAge: number;
+  /** Price of one UI token, only used when oracleSetup = fixed */
+  fixedPrice: WrappedI80F48;
+  /** Max oracle age in slots, 0 = disabled */
+  oracleMaxSlotAge: number;
+  /** Min oracle price in USD with 9 decimals, 0 = disabled */
+  oracleMinPrice: BN;
+  /** Max oracle price in USD with 9 decimals, 0 = disabled */
+  oracleMaxPrice: BN;
+  /** Stake pool of StakePoolPythPush banks, PublicKey.default otherwise */
+  secondaryOracleKey: PublicKey;
+};
+
+export type FeeDestination = {
+  /** Owner of the destination token accounts, unused for the insurance vault */
+  owner: PublicKey;
+  /** Share of the collected group fees, all used destinations add up to 10000 */
+  shareBps: number;
+  /** Unused = 0, TokenAccount = 1, InsuranceVault = 2 */
+  destinationType: number;
+  pad0: number[];
 };
 
 /**
@@ -73,6 +96,11 @@ export const defaultBankConfig = (oracleKey: PublicKey) => {
     },
     totalAssetValueInitLimit: new BN(100_000_000_000),
     oracleMaxAge: 100,
+    fixedPrice: I80F48_ZERO,
+    oracleMaxSlotAge: 0,
+    oracleMinPrice: new BN(0),
+    oracleMaxPrice: new BN(0),
+    secondaryOracleKey: PublicKey.default,
   };
   return config;
 };
@@ -86,6 +114,13 @@ export type InterestRateConfig = {
   insuranceIrFee: WrappedI80F48;
   protocolFixedFeeApr: WrappedI80F48;
   protocolIrFee: WrappedI80F48;
+
+  /** One-time fee added to borrows, in bps */
+  originationFeeBps: number;
+  /** Part of the origination fee going to insurance, the rest goes to the group, in bps */
+  originationFeeInsuranceShareBps: number;
+  /** Fee added to borrows made within a flashloan, in bps */
+  flashloanFeeBps: number;
 };
 
 /**
@@ -105,6 +140,9 @@ export const defaultInterestRateConfig = () => {
     insuranceIrFee: I80F48_ZERO,
     protocolFixedFeeApr: I80F48_ZERO,
     protocolIrFee: I80F48_ZERO,
+    originationFeeBps: 0,
+    originationFeeInsuranceShareBps: 0,
+    flashloanFeeBps: 0,
   };
   return config;
 };